@@ -1,17 +1,31 @@
 use std::error::Error;
 
-use rusty_pipes::{core::definitions::PipelineDefinition, engine::Engine};
+use rusty_pipes::{
+    cache::ResultCache, core::definitions::PipelineDefinition, engine::Engine,
+    observer::ProgressReporter,
+};
+
+/// Construct an engine for the pipeline definition at the given path, which caches its results unless caching is
+/// disabled (by passing --no-cache), and reports its progress to the standard error stream.
+fn engine(path: &str, cache: Option<&ResultCache>) -> Result<Engine, Box<dyn Error>> {
+    let raw_definition = std::fs::read_to_string(path)?;
+    let parsed_definition: PipelineDefinition = serde_json::from_str(&raw_definition)?;
+    let mut engine = Engine::from_definition(parsed_definition);
+    engine.register_observer(ProgressReporter::new());
+    Ok(match cache {
+        Some(cache) => engine.with_cache(cache.clone()),
+        None => engine,
+    })
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let raw_filter_definition = std::fs::read_to_string("examples/filter.json")?;
-    let parsed_filter_definition: PipelineDefinition =
-        serde_json::from_str(&raw_filter_definition)?;
-    let mut filter_engine = Engine::from_definition(parsed_filter_definition);
+    let no_cache = std::env::args().any(|arg| arg == "--no-cache");
+    let cache = (!no_cache).then(|| ResultCache::new("target/rusty_pipes_cache"));
+
+    let mut filter_engine = engine("examples/filter.json", cache.as_ref())?;
     let filter_result = filter_engine.run(&Default::default());
 
-    let raw_join_definition = std::fs::read_to_string("examples/join.json")?;
-    let parsed_join_definition: PipelineDefinition = serde_json::from_str(&raw_join_definition)?;
-    let mut join_engine = Engine::from_definition(parsed_join_definition);
+    let mut join_engine = engine("examples/join.json", cache.as_ref())?;
     let join_result = join_engine.run(&Default::default());
 
     println!(
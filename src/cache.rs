@@ -0,0 +1,333 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::{
+        context::Context,
+        dataframe::{Dataframe, Row},
+        definitions::{DataSourceDefinition, Source},
+    },
+    loaders::file::resolve_paths,
+    plan::{Columns, OpaqueOperation, PlanNode},
+    spill::{read_u64, write_u64, Spillable},
+};
+
+/// A hasher of which the hashes are the same across runs (and builds) of the program, unlike those of the hashers of
+/// the standard library, which makes them suitable as fingerprints of persisted data. This is 64-bit FNV-1a.
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        StableHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+fn stable_hash(value: impl Hash) -> u64 {
+    let mut hasher = StableHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Add the path and contents of a file to a fingerprint. The contents are hashed rather than the modification time, as
+/// a file may be rewritten without changing its size or modification time. None if the file cannot be read.
+fn fingerprint_file(path: &Path, hasher: &mut StableHasher) -> Option<()> {
+    let mut reader = File::open(path).ok()?;
+    path.hash(hasher);
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        match reader.read(&mut buffer).ok()? {
+            0 => return Some(()),
+            len => hasher.write(&buffer[..len]),
+        }
+    }
+}
+
+/// The fingerprint of a source, which changes whenever the rows it yields may change: it covers the definition of the
+/// source (including its schema), the columns that are loaded of it and, for sources that are read from files, the
+/// path and contents of each of these files. None for sources of which the rows cannot be
+/// fingerprinted: PostgreSQL databases, HTTP endpoints and custom loaders.
+pub(crate) fn source_fingerprint(
+    definition: &DataSourceDefinition,
+    columns: &Columns,
+) -> Option<u64> {
+    let mut hasher = StableHasher::default();
+    format!("{:?}", definition).hash(&mut hasher);
+    columns.hash(&mut hasher);
+    match &definition.source {
+        Source::File { path, format, .. } => {
+            for path in resolve_paths(Path::new(path), format).ok()? {
+                fingerprint_file(&path, &mut hasher)?;
+            }
+        }
+        Source::Sqlite { path, .. } => fingerprint_file(Path::new(path), &mut hasher)?,
+        Source::Inline { .. } => {}
+        Source::Postgres { .. } | Source::Http { .. } | Source::Custom { .. } => return None,
+    }
+    Some(hasher.finish())
+}
+
+/// The fingerprint of the output of a plan node, which covers the operations of the node and of its inputs, the
+/// fingerprints of the sources that it scans (as given) and the parameters of the context. None if any of these
+/// sources has no fingerprint, or if the node performs a custom operation, of which the output cannot be
+/// fingerprinted. The WebAssembly modules of Wasm operations are fingerprinted like files.
+pub(crate) fn node_fingerprint(
+    node: &PlanNode,
+    sources: &HashMap<String, Option<u64>>,
+    context: &Context,
+) -> Option<u64> {
+    let mut hasher = StableHasher::default();
+    format!("{:?}", node).hash(&mut hasher);
+    let mut parameters = context.parameters().collect::<Vec<_>>();
+    parameters.sort();
+    parameters.hash(&mut hasher);
+    fingerprint_inputs(node, sources, &mut hasher)?;
+    Some(hasher.finish())
+}
+
+fn fingerprint_inputs(
+    node: &PlanNode,
+    sources: &HashMap<String, Option<u64>>,
+    hasher: &mut StableHasher,
+) -> Option<()> {
+    match node {
+        PlanNode::Scan { source } => sources.get(source).copied().flatten()?.hash(hasher),
+        PlanNode::Operation {
+            operation: OpaqueOperation::Custom { .. },
+            ..
+        } => return None,
+        PlanNode::Operation {
+            operation: OpaqueOperation::Wasm { path, .. },
+            ..
+        } => fingerprint_file(path, hasher)?,
+        _ => {}
+    }
+    node.inputs()
+        .iter()
+        .try_for_each(|input| fingerprint_inputs(input, sources, hasher))
+}
+
+/// A cache of the data frames of loaded sources and of the outputs of transformations in a local directory, which
+/// allows a pipeline to be run again without loading the sources and running the transformations that have not changed
+/// since (see [`Engine::with_cache`](crate::engine::Engine::with_cache)).
+///
+/// Each entry is keyed by its name along with the fingerprint of what it was derived from: the files and definition of
+/// a source, or the operations of a transformation and the fingerprints of its sources. Pipelines can thus share a
+/// cache directory without overwriting each other's entries, even when they define sources or transformations of the
+/// same name. An entry of which the fingerprint no longer matches is stale; it is never read again, but it is kept
+/// until the cache is cleared. The cache is best-effort: entries that cannot be read or written are treated as if they
+/// were not cached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultCache {
+    directory: PathBuf,
+}
+
+impl ResultCache {
+    /// Construct a cache in the given directory, which is created when the first entry is stored.
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        ResultCache {
+            directory: directory.into(),
+        }
+    }
+
+    /// The directory of this cache.
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Remove all entries from this cache, so that the next run loads all sources and runs all transformations anew.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.directory) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// The cached data frame of the source with the given name, if it has the given fingerprint.
+    pub(crate) fn source(&self, name: &str, fingerprint: u64) -> Option<Dataframe> {
+        self.read("source", name, fingerprint)?.into_iter().next()
+    }
+
+    /// Cache the data frame of the source with the given name.
+    pub(crate) fn store_source(&self, name: &str, fingerprint: u64, df: &Dataframe) {
+        self.write("source", name, fingerprint, std::slice::from_ref(df));
+    }
+
+    /// The cached output of the transformation with the given name, if it has the given fingerprint.
+    pub(crate) fn output(&self, name: &str, fingerprint: u64) -> Option<Vec<Dataframe>> {
+        self.read("output", name, fingerprint)
+    }
+
+    /// Cache the output of the transformation with the given name.
+    pub(crate) fn store_output(&self, name: &str, fingerprint: u64, output: &[Dataframe]) {
+        self.write("output", name, fingerprint, output);
+    }
+
+    fn entry_path(&self, kind: &str, name: &str, fingerprint: u64) -> PathBuf {
+        self.directory.join(format!(
+            "{}-{:016x}-{:016x}",
+            kind,
+            stable_hash(name),
+            fingerprint
+        ))
+    }
+
+    fn read(&self, kind: &str, name: &str, fingerprint: u64) -> Option<Vec<Dataframe>> {
+        let mut reader = BufReader::new(File::open(self.entry_path(kind, name, fingerprint)).ok()?);
+        if read_u64(&mut reader).ok()? != fingerprint {
+            return None;
+        }
+        let mut dfs = vec![];
+        for _ in 0..read_u64(&mut reader).ok()? {
+            let len = read_u64(&mut reader).ok()?;
+            let df = (0..len)
+                .map(|_| Row::read_from(&mut reader))
+                .collect::<std::io::Result<Dataframe>>()
+                .ok()?;
+            dfs.push(df);
+        }
+        Some(dfs)
+    }
+
+    fn write(&self, kind: &str, name: &str, fingerprint: u64, dfs: &[Dataframe]) {
+        let path = self.entry_path(kind, name, fingerprint);
+        // The entry is written to a temporary file first, so that an interrupted write never leaves a partial entry.
+        let temporary = path.with_extension("tmp");
+        let written = fs::create_dir_all(&self.directory).and_then(|_| {
+            let mut writer = BufWriter::new(File::create(&temporary)?);
+            write_u64(&mut writer, fingerprint)?;
+            write_u64(&mut writer, dfs.len() as u64)?;
+            for df in dfs {
+                write_u64(&mut writer, df.len() as u64)?;
+                for row in df {
+                    row.write_to(&mut writer)?;
+                }
+            }
+            writer.flush()?;
+            drop(writer);
+            fs::rename(&temporary, &path)
+        });
+        if written.is_err() {
+            let _ = fs::remove_file(&temporary);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::dataframe::ColumnValue;
+    use crate::core::definitions::DataSourceDefinition;
+
+    fn definition(path: &Path) -> DataSourceDefinition {
+        serde_json::from_value(serde_json::json!({
+            "schema": [{ "column_name": "id", "data_type": "Integer", "required": true }],
+            "source": { "type": "File", "path": path, "format": "Csv" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn stores_and_reads_back_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResultCache::new(dir.path().join("cache"));
+        let output = vec![
+            vec![HashMap::from([(
+                String::from("id"),
+                ColumnValue::Integer(1),
+            )])],
+            vec![],
+        ];
+
+        assert_eq!(cache.output("joined", 1), None);
+        cache.store_output("joined", 1, &output);
+
+        assert_eq!(cache.output("joined", 1), Some(output.clone()));
+        assert_eq!(cache.output("joined", 2), None);
+        assert_eq!(cache.source("joined", 1), None);
+
+        // Entries with other fingerprints, e.g., of another pipeline, are kept alongside.
+        cache.store_output("joined", 2, &output[1..]);
+        assert_eq!(cache.output("joined", 2), Some(output[1..].to_vec()));
+        assert_eq!(cache.output("joined", 1), Some(output));
+
+        cache.clear().unwrap();
+        assert_eq!(cache.output("joined", 1), None);
+        cache.clear().unwrap();
+    }
+
+    #[test]
+    fn fingerprints_change_with_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.csv");
+        fs::write(&path, "id\n1\n").unwrap();
+        let definition = definition(&path);
+
+        let fingerprint = source_fingerprint(&definition, &None);
+        assert!(fingerprint.is_some());
+        assert_eq!(source_fingerprint(&definition, &None), fingerprint);
+        assert_ne!(
+            source_fingerprint(&definition, &Some([String::from("id")].into())),
+            fingerprint
+        );
+
+        fs::write(&path, "id\n1\n2\n").unwrap();
+        assert_ne!(source_fingerprint(&definition, &None), fingerprint);
+        let fingerprint = source_fingerprint(&definition, &None);
+        fs::write(&path, "id\n2\n1\n").unwrap();
+        assert_ne!(source_fingerprint(&definition, &None), fingerprint);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(source_fingerprint(&definition, &None), None);
+    }
+
+    #[test]
+    fn does_not_fingerprint_custom_operations() {
+        let sources = HashMap::from([(String::from("people"), Some(1))]);
+        let scan = || PlanNode::Scan {
+            source: String::from("people"),
+        };
+        let custom = PlanNode::Operation {
+            operation: OpaqueOperation::Custom {
+                name: String::from("enrich"),
+                config: serde_json::Value::Null,
+            },
+            inputs: vec![scan()],
+        };
+
+        assert!(node_fingerprint(&scan(), &sources, &Context::default()).is_some());
+        assert_ne!(
+            node_fingerprint(&scan(), &sources, &Context::default()),
+            node_fingerprint(
+                &scan(),
+                &sources,
+                &Context::new(HashMap::from([(String::from("a"), String::from("b"))]))
+            )
+        );
+        assert_eq!(
+            node_fingerprint(&custom, &sources, &Context::default()),
+            None
+        );
+        assert_eq!(
+            node_fingerprint(&scan(), &HashMap::new(), &Context::default()),
+            None
+        );
+    }
+}
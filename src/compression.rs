@@ -0,0 +1,107 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use crate::core::definitions::Compression;
+
+/// Detect the compression of a file based on its extension. Files without a recognised extension are assumed to be
+/// uncompressed.
+pub fn detect(path: &Path) -> Compression {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("gz") | Some("gzip") => Compression::Gzip,
+        Some("zst") | Some("zstd") => Compression::Zstd,
+        Some("bz2") => Compression::Bzip2,
+        _ => Compression::None,
+    }
+}
+
+/// Open a file for reading, decompressing its contents as they are read. When no compression is given, it is
+/// detected from the file extension.
+pub fn open(
+    path: &Path,
+    compression: Option<Compression>,
+) -> std::io::Result<Box<dyn Read + Send>> {
+    let file = BufReader::new(File::open(path)?);
+    let reader: Box<dyn Read + Send> = match compression.unwrap_or_else(|| detect(path)) {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(file)),
+    };
+    Ok(reader)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::*;
+
+    const CONTENTS: &[u8] = b"name,amount\nfoo,1\n";
+
+    fn compress(compression: Compression) -> Vec<u8> {
+        match compression {
+            Compression::None => CONTENTS.to_vec(),
+            Compression::Gzip => {
+                let mut encoder =
+                    flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(CONTENTS).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Zstd => zstd::encode_all(CONTENTS, 0).unwrap(),
+            Compression::Bzip2 => {
+                let mut encoder =
+                    bzip2::write::BzEncoder::new(vec![], bzip2::Compression::default());
+                encoder.write_all(CONTENTS).unwrap();
+                encoder.finish().unwrap()
+            }
+        }
+    }
+
+    fn read(name: &str, contents: &[u8], compression: Option<Compression>) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(name);
+        std::fs::write(&path, contents).unwrap();
+
+        let mut decompressed = vec![];
+        open(&path, compression)
+            .unwrap()
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        decompressed
+    }
+
+    #[test]
+    fn detects_compression_from_extension() {
+        assert_eq!(detect(Path::new("a.csv.gz")), Compression::Gzip);
+        assert_eq!(detect(Path::new("a.ndjson.zst")), Compression::Zstd);
+        assert_eq!(detect(Path::new("a.csv.bz2")), Compression::Bzip2);
+        assert_eq!(detect(Path::new("a.csv")), Compression::None);
+    }
+
+    #[test]
+    fn decompresses_detected_compression() {
+        for (name, compression) in [
+            ("detected.csv", Compression::None),
+            ("detected.csv.gz", Compression::Gzip),
+            ("detected.csv.zst", Compression::Zstd),
+            ("detected.csv.bz2", Compression::Bzip2),
+        ] {
+            assert_eq!(read(name, &compress(compression), None), CONTENTS);
+        }
+    }
+
+    #[test]
+    fn decompresses_explicit_compression() {
+        assert_eq!(
+            read(
+                "explicit_gzip.csv",
+                &compress(Compression::Gzip),
+                Some(Compression::Gzip)
+            ),
+            CONTENTS
+        );
+    }
+}
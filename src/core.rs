@@ -1,7 +1,10 @@
+pub mod cancellation;
 pub mod context;
 pub mod dataframe;
 pub mod definitions;
 pub mod error;
 pub mod loader;
+pub mod registry;
 pub mod result;
+pub mod sink;
 pub mod transformation;
@@ -0,0 +1,165 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::core::{error::RustyPipesError, result::RustyPipesResult};
+
+/// The longest that a cancellable sleep waits before checking whether it was cancelled.
+const SLEEP_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A deadline after which a token is considered timed out, along with the error message to report when it is.
+#[derive(Debug)]
+struct Deadline {
+    at: Instant,
+    message: String,
+}
+
+/// A token by which a run of a pipeline can be cancelled from another thread. Clones of a token share its state, so
+/// that cancelling one cancels all of them.
+///
+/// Cancellation is cooperative: loaders and transformations call [`check`](CancellationToken::check) periodically
+/// (e.g., for every row or request), and stop with an error once the token is cancelled, or once its deadline (see
+/// [`with_timeout`](CancellationToken::with_timeout)) has passed. Custom loaders and transformations can retrieve the
+/// token of the run from the context.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Arc<Deadline>>,
+}
+
+impl CancellationToken {
+    /// Construct a token that is not cancelled, and has no deadline.
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /// Cancel this token, and all tokens that share its state.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether this token was cancelled. This does not consider the deadline of the token.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// A token that is cancelled along with this one, and that also times out once the given timeout has elapsed (or
+    /// once the deadline of this token has passed, if that is sooner). The name of what times out (e.g., "Source
+    /// people") is included in the error.
+    pub fn with_timeout(&self, timeout: Duration, name: &str) -> Self {
+        let at = Instant::now() + timeout;
+        let deadline = match &self.deadline {
+            Some(deadline) if deadline.at <= at => deadline.clone(),
+            _ => Arc::new(Deadline {
+                at,
+                message: format!("{} timed out after {:?}", name, timeout),
+            }),
+        };
+        CancellationToken {
+            cancelled: self.cancelled.clone(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// The time left until the deadline of this token, if any, which is zero once the deadline has passed. Blocking
+    /// operations that cannot check the token themselves (e.g., a request) can use it as their timeout.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline
+            .as_ref()
+            .map(|deadline| deadline.at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Yield a Cancelled error if this token was cancelled, or a TimedOut error if its deadline has passed.
+    pub fn check(&self) -> RustyPipesResult<()> {
+        if self.is_cancelled() {
+            return Err(RustyPipesError::Cancelled);
+        }
+        match &self.deadline {
+            Some(deadline) if Instant::now() >= deadline.at => {
+                Err(RustyPipesError::TimedOut(deadline.message.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Sleep for the given duration, waking up early with an error if this token is cancelled or times out meanwhile.
+    pub fn sleep(&self, duration: Duration) -> RustyPipesResult<()> {
+        let end = Instant::now() + duration;
+        loop {
+            self.check()?;
+            let now = Instant::now();
+            if now >= end {
+                return Ok(());
+            }
+            std::thread::sleep((end - now).min(SLEEP_INTERVAL));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cancels_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(clone.check().is_ok());
+
+        token.cancel();
+
+        assert!(clone.is_cancelled());
+        assert!(matches!(clone.check(), Err(RustyPipesError::Cancelled)));
+    }
+
+    #[test]
+    fn times_out_at_earliest_deadline() {
+        let token = CancellationToken::new();
+        let transformation = token.with_timeout(Duration::ZERO, "Transformation joined");
+        let source = transformation.with_timeout(Duration::from_secs(60), "Source people");
+
+        assert!(token.check().is_ok());
+        assert!(matches!(
+            source.check(),
+            Err(RustyPipesError::TimedOut(message)) if message == "Transformation joined timed out after 0ns"
+        ));
+
+        token.cancel();
+        assert!(matches!(source.check(), Err(RustyPipesError::Cancelled)));
+    }
+
+    #[test]
+    fn yields_time_remaining_until_deadline() {
+        let token = CancellationToken::new();
+        assert_eq!(token.remaining(), None);
+
+        let remaining = token
+            .with_timeout(Duration::from_secs(60), "Source people")
+            .remaining()
+            .unwrap();
+        assert!(remaining > Duration::from_secs(59) && remaining <= Duration::from_secs(60));
+        assert_eq!(
+            token
+                .with_timeout(Duration::ZERO, "Source people")
+                .remaining(),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn wakes_up_from_sleep_when_timed_out() {
+        let token =
+            CancellationToken::new().with_timeout(Duration::from_millis(20), "Source people");
+        let start = Instant::now();
+
+        assert!(matches!(
+            token.sleep(Duration::from_secs(60)),
+            Err(RustyPipesError::TimedOut(_))
+        ));
+        assert!(start.elapsed() < Duration::from_secs(60));
+    }
+}
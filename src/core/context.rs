@@ -1,22 +1,84 @@
 use std::collections::HashMap;
 
+use crate::core::cancellation::CancellationToken;
+
 /// A context that can be accessed by the pipeline at runtime. This can contain things like parameters to be passed to
-/// Loaders or Transformations.
-#[derive(Default)]
+/// Loaders or Transformations, as well as the token by which the run can be cancelled.
+#[derive(Default, Clone)]
 pub struct Context {
     parameters: HashMap<String, String>,
+    cancellation: CancellationToken,
 }
 
 impl Context {
     /// Construct a new context with the given parameters.
     pub fn new(parameters: HashMap<String, String>) -> Self {
-        Context { parameters }
+        Context {
+            parameters,
+            cancellation: CancellationToken::default(),
+        }
+    }
+
+    /// Cancel the run when the given token is cancelled. Loaders and Transformations check this token periodically.
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// The token by which the run is cancelled. Within a source or transformation that has a timeout, this token
+    /// also times out.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
     }
 
     /// Retrieve the value of a parameter.
     pub fn parameter_value(&self, key: &str) -> Option<&str> {
         self.parameters.get(key).map(|value| value.as_str())
     }
-}
 
+    /// Iterate over all parameters, as pairs of their name and value.
+    pub fn parameters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.parameters
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Resolve a value that may refer to a parameter. Values prefixed with a colon (e.g., ":param_name") refer to the
+    /// parameter with that name; any other value is returned as-is. Yields None if the referenced parameter does not
+    /// exist.
+    pub fn resolve<'a>(&'a self, value: &'a str) -> Option<&'a str> {
+        match value.strip_prefix(':') {
+            Some(key) => self.parameter_value(key),
+            None => Some(value),
+        }
+    }
 
+    /// Substitute the parameters referenced in a template. As with [`resolve`](Context::resolve), parameters are
+    /// referenced by their name prefixed with a colon (e.g., "https://example.com/:version/items"). A name consists
+    /// of letters, digits and underscores, and does not start with a digit, so that other colons (e.g., before the
+    /// port of a URL) are left as-is. Yields the first reference to a parameter that does not exist as an error.
+    pub fn substitute(&self, template: &str) -> Result<String, String> {
+        let mut result = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find(':') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let length = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            let key = &after[..length];
+            if key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                let value = self
+                    .parameter_value(key)
+                    .ok_or_else(|| format!(":{}", key))?;
+                result.push_str(value);
+                rest = &after[length..];
+            } else {
+                result.push(':');
+                rest = after;
+            }
+        }
+        result.push_str(rest);
+        Ok(result)
+    }
+}
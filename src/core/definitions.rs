@@ -1,10 +1,15 @@
 mod column;
 mod data_source;
+mod destination;
 mod pipeline;
 mod transformation;
 
 pub use column::{ColumnDefinition, DataType};
-pub use data_source::{DataSourceDefinition, Format, Source};
+pub use data_source::{
+    Compression, CsvDialect, DataSourceDefinition, Format, HttpMethod, Pagination, RetryPolicy,
+    Source, Trim,
+};
+pub use destination::{Destination, ParquetCompression, WriteMode};
 pub use pipeline::PipelineDefinition;
-pub use transformation::Operation;
+pub use transformation::{JoinStrategy, KeyCoercion, Operation, ScriptLanguage, ScriptMode};
 pub use transformation::TransformationDefinition;
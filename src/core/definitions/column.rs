@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub enum DataType {
     Integer,
     Decimal,
@@ -8,7 +8,7 @@ pub enum DataType {
 }
 
 /// The schema that defines a column.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct ColumnDefinition {
     pub column_name: String,
     pub data_type: DataType,
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::Deserialize;
 
 use super::column::ColumnDefinition;
@@ -6,13 +8,193 @@ use super::column::ColumnDefinition;
 #[derive(Deserialize, Debug)]
 pub enum Format {
     Csv,
+    /// Apache Parquet. Only the columns in the schema are read, and row groups are read in parallel.
+    Parquet,
+    /// The Arrow IPC file format (also known as Feather V2) or the Arrow IPC streaming format. Only the columns in
+    /// the schema are read.
+    ArrowIpc,
+}
+
+/// The compression applied to a file.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+/// The HTTP method used to request data.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+}
+
+/// How to request subsequent pages of records from an HTTP endpoint.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum Pagination {
+    /// The response contains a cursor at the given path, which is passed as the given query parameter to request the
+    /// next page. Pagination stops when the cursor is absent or null, and fails when a cursor is returned again.
+    Cursor {
+        cursor_path: String,
+        parameter: String,
+    },
+    /// Pages are requested by passing the offset of the first record as the given query parameter, and optionally
+    /// the page size as the limit parameter. Pagination stops when a page contains fewer records than the page size.
+    Offset {
+        parameter: String,
+        limit_parameter: Option<String>,
+        page_size: usize,
+    },
+    /// The next page is identified by the URL with rel="next" in the Link header of the response.
+    LinkHeader,
+}
+
+/// How often and how quickly to retry failed requests. Requests are retried upon connection errors, as well as upon
+/// responses with a status of 429 or 5xx.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// The maximum number of times a request is retried. Defaults to 3.
+    pub max_retries: u32,
+    /// The time to wait before the first retry, in milliseconds. This doubles with each subsequent retry. Defaults to
+    /// 500.
+    pub initial_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 3,
+            initial_backoff_ms: 500,
+        }
+    }
+}
+
+/// Which parts of a CSV record should have leading and trailing whitespace trimmed.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum Trim {
+    #[default]
+    None,
+    Headers,
+    Fields,
+    All,
+}
+
+/// The dialect of a CSV file, describing how it should be parsed. Every option has a default matching the most common
+/// form of CSV, so only the options that deviate from it need to be specified.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct CsvDialect {
+    /// The field delimiter. Defaults to ','.
+    pub delimiter: char,
+    /// The quote character. Defaults to '"'.
+    pub quote: char,
+    /// The escape character for quotes within quoted fields. When not set, quotes are escaped by doubling them.
+    pub escape: Option<char>,
+    /// Lines starting with this character are ignored.
+    pub comment: Option<char>,
+    /// Whether the first record contains headers rather than data. Defaults to true.
+    pub has_headers: bool,
+    /// Which parts of a record to trim. Defaults to trimming nothing.
+    pub trim: Trim,
+    /// Whether records may have a varying number of fields. Missing fields are treated as absent values.
+    pub flexible: bool,
+    /// The label of the encoding of the file (e.g., "windows-1252" or "utf-16le"). Defaults to UTF-8.
+    pub encoding: Option<String>,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        CsvDialect {
+            delimiter: ',',
+            quote: '"',
+            escape: None,
+            comment: None,
+            has_headers: true,
+            trim: Trim::None,
+            flexible: false,
+            encoding: None,
+        }
+    }
 }
 
 /// The definition of the data source, defining where/how to source the data.
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum Source {
-    File { path: String, format: Format },
+    File {
+        /// The path to load. This may refer to a single file, a directory (in which case every file within it with
+        /// an extension of the format, such as ".csv" or ".csv.gz", is loaded) or a glob pattern such as
+        /// "exports/2024-*.csv". Multiple files are loaded in lexicographical order of their paths and concatenated
+        /// into a single dataframe.
+        path: String,
+        format: Format,
+        /// The CSV dialect to use when the format is Csv.
+        #[serde(default)]
+        dialect: CsvDialect,
+        /// The compression of the file(s). When not set, the compression is detected from the file extension
+        /// (".gz", ".zst" or ".bz2"). Only CSV files can be compressed; Parquet and Arrow IPC files compress their
+        /// contents internally.
+        #[serde(default)]
+        compression: Option<Compression>,
+        /// When set, a column with this name is added to every row, containing the name of the file it was loaded
+        /// from (e.g., "2024-01.csv").
+        #[serde(default)]
+        filename_column: Option<String>,
+        /// Whether to load multiple files in parallel.
+        #[serde(default)]
+        parallel: bool,
+    },
+    /// The result of a SQL query against a SQLite database. The columns of the result are mapped onto the schema by
+    /// name.
+    Sqlite { path: String, query: String },
+    /// The result of a SQL query against a PostgreSQL database. The columns of the result are mapped onto the schema
+    /// by name.
+    Postgres {
+        /// The connection string (e.g., "host=localhost user=postgres"). To avoid committing credentials, this can
+        /// refer to a parameter in the context (e.g., ":connection_string").
+        connection: String,
+        query: String,
+        /// The values bound to the placeholders in the query ($1, $2, ...), in order. These can refer to parameters
+        /// in the context.
+        #[serde(default)]
+        parameters: Vec<String>,
+    },
+    /// Records retrieved from a JSON HTTP endpoint. The fields of each record are mapped onto the schema by name.
+    Http {
+        /// The URL to request. Parameters in the context can be referenced by name, prefixed with a colon (e.g.,
+        /// ":version").
+        url: String,
+        #[serde(default)]
+        method: HttpMethod,
+        /// The headers to send. Parameters in the context can be referenced in values in the same way as in the URL.
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// The JSON body to send with a POST request.
+        #[serde(default)]
+        body: Option<serde_json::Value>,
+        /// The path to the array of records within the response, as a dot-separated list of keys or array indices
+        /// (e.g., "data.items"). When not set, the response itself is expected to be the array of records.
+        #[serde(default)]
+        json_path: Option<String>,
+        #[serde(default)]
+        pagination: Option<Pagination>,
+        #[serde(default)]
+        retry: RetryPolicy,
+    },
+    /// Rows specified literally in the definition, as JSON objects whose fields are mapped onto the schema by name.
+    Inline { rows: Vec<serde_json::Value> },
+    /// A source that is loaded by the custom Loader registered with the Engine under the given name. The
+    /// configuration is passed to its factory as-is.
+    Custom {
+        name: String,
+        #[serde(default)]
+        config: serde_json::Value,
+    },
 }
 
 /// A definition for a data source.
@@ -22,4 +204,10 @@ pub struct DataSourceDefinition {
     pub schema: Vec<ColumnDefinition>,
     /// The source definition.
     pub source: Source,
+    /// The time within which the source must be loaded, in milliseconds. When it is exceeded, loading stops with a
+    /// TimedOut error, and the transformations that depend on the source fail with it. For a streamed source, this
+    /// includes the time spent passing its batches through the operations of a transformation. There is no timeout by
+    /// default.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
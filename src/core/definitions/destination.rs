@@ -0,0 +1,49 @@
+use serde::Deserialize;
+
+/// How a sink should treat existing data at its destination.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum WriteMode {
+    /// Create the destination, failing if it already exists.
+    #[default]
+    Create,
+    /// Replace the destination if it already exists.
+    Replace,
+    /// Append to the destination, creating it if it does not yet exist.
+    Append,
+}
+
+/// The compression codec used within a Parquet file.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ParquetCompression {
+    Uncompressed,
+    #[default]
+    Snappy,
+    Gzip,
+    Zstd,
+    Lz4,
+    Brotli,
+}
+
+/// The definition of a destination, defining where/how to write the output of a transformation.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum Destination {
+    /// A table in a SQLite database. The columns of the table are derived from the output of the transformation.
+    Sqlite {
+        path: String,
+        table: String,
+        #[serde(default)]
+        mode: WriteMode,
+    },
+    /// An Apache Parquet file. The schema of the file is derived from the output of the transformation, and the file
+    /// is overwritten if it already exists.
+    Parquet {
+        path: String,
+        /// The compression codec. Defaults to Snappy.
+        #[serde(default)]
+        compression: ParquetCompression,
+        /// The maximum number of rows in a row group. Defaults to the default of the Parquet writer.
+        #[serde(default)]
+        row_group_size: Option<usize>,
+    },
+}
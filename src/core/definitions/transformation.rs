@@ -1,7 +1,74 @@
+use std::path::PathBuf;
+
 use serde::Deserialize;
 
+use super::destination::Destination;
+
+/// The language in which a script is written.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ScriptLanguage {
+    /// Rhai (https://rhai.rs), an embedded scripting language for Rust.
+    #[default]
+    Rhai,
+}
+
+/// How a script is applied to a dataframe.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ScriptMode {
+    /// The script is run for each row, yielding the row to replace it with.
+    #[default]
+    Map,
+    /// The script is run for each row, yielding whether the row is retained.
+    Filter,
+    /// The script is run once, on all rows, yielding the rows that replace them.
+    Dataframe,
+}
+
+/// How the rows of two dataframes are matched by a join.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum JoinStrategy {
+    /// The rows of the right dataframe are grouped in a hash table, which the rows of the left dataframe are looked up
+    /// in.
+    #[default]
+    Hash,
+    /// Both dataframes are sorted on their identifiers, after which they are merged. This avoids hashing, and is
+    /// cheapest when both dataframes are already sorted on their identifiers, in which case they are not sorted again.
+    SortMerge,
+}
+
+/// How the values of join keys are coerced before they are compared. Keys of different types never match, unless
+/// a coercion makes them the same type. Null keys are never coerced.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum KeyCoercion {
+    /// Keys are not coerced: they only match when they have the same type and value, so that, e.g., the integer 1
+    /// matches neither the decimal 1.0 nor the string "1".
+    Strict,
+    /// Integer and decimal keys are compared as numbers, so that, e.g., the integer 1 matches the decimal 1.0. String
+    /// keys only match strings.
+    Numeric,
+    /// Keys are compared by their textual representation, so that, e.g., the integer 1 matches the string "1" and the
+    /// decimal 1.5 matches the string "1.5".
+    #[default]
+    Text,
+    /// String keys are parsed as ISO 8601 dates (e.g., "2024-01-31"), optionally followed by a time, which is ignored.
+    /// Other keys are compared as numbers. A string key that is not a date is an error.
+    Date,
+}
+
+fn default_max_operations() -> u64 {
+    1_000_000
+}
+
+fn default_fuel() -> u64 {
+    1_000_000_000
+}
+
+fn default_max_memory_bytes() -> usize {
+    256 * 1024 * 1024
+}
+
 /// A definition for an operation to be performed as part of the transformation pipeline.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(tag = "type")]
 pub enum Operation {
     /// The expected format of this predicate is
@@ -12,10 +79,69 @@ pub enum Operation {
     Filter { predicate: String },
     /// The expected format of the "on" clause is "left_column_name = right_column_name" where left_column_name
     /// and right_column_name refer to the names of the identifying columns in the left and right dataframes. E.g.,
-    /// "identifier = identifier".
+    /// "identifier = identifier". Joins of 10,000 rows or more are performed in parallel, in which case the order of
+    /// the joined rows is unspecified unless preserve_order is set, which orders them by the left row and then the
+    /// right row they originate from (at some cost). Smaller hash joins yield the joined rows in that order as well.
+    /// The strategy defaults to a hash join; either strategy yields the same rows.
+    /// Keys are compared by their typed values, after coercion (which defaults to Text). Null keys never match,
+    /// unless match_nulls is set, in which case they match each other. Rows without the key, or with a NaN key, are
+    /// never matched.
     ///
     /// This operation has an arity of 2 (i.e., it requires two dataframes to operate on).
-    InnerJoin { on: String },
+    InnerJoin {
+        on: String,
+        #[serde(default)]
+        preserve_order: bool,
+        #[serde(default)]
+        strategy: JoinStrategy,
+        #[serde(default)]
+        coercion: KeyCoercion,
+        #[serde(default)]
+        match_nulls: bool,
+    },
+    /// The columns to retain in each row; all other columns are dropped. E.g., ["column_one", "column_two"].
+    ///
+    /// This operation has an arity of 1 (i.e., it requires a single dataframe to operate on).
+    Select { columns: Vec<String> },
+    /// A script that is run on the rows of a dataframe, either per row (to map or filter rows) or once for the whole
+    /// dataframe. The row is available to the script as `row` (or the rows as `rows`), and the parameters of the
+    /// context as `params`. Each run of the script may perform at most max_operations operations, which defaults to
+    /// 1,000,000.
+    ///
+    /// This operation has an arity of 1 (i.e., it requires a single dataframe to operate on).
+    Script {
+        #[serde(default)]
+        language: ScriptLanguage,
+        code: String,
+        #[serde(default)]
+        mode: ScriptMode,
+        #[serde(default = "default_max_operations")]
+        max_operations: u64,
+    },
+    /// A transformation implemented by the WebAssembly module at the given path, which exchanges dataframes with the
+    /// host as JSON buffers (see `WasmPlugin` for the ABI that the module must implement). The configuration is passed
+    /// to the module as-is. Each run may consume at most the given amount of fuel, which defaults to 1,000,000,000,
+    /// and may use at most max_memory_bytes of memory, which defaults to 256 MiB.
+    ///
+    /// The arity of this operation is determined by the module.
+    Wasm {
+        path: PathBuf,
+        #[serde(default)]
+        config: serde_json::Value,
+        #[serde(default = "default_fuel")]
+        fuel: u64,
+        #[serde(default = "default_max_memory_bytes")]
+        max_memory_bytes: usize,
+    },
+    /// An operation performed by the custom Transformation registered with the Engine under the given name. The
+    /// configuration is passed to its factory as-is.
+    ///
+    /// The arity of this operation is determined by the custom Transformation.
+    Custom {
+        name: String,
+        #[serde(default)]
+        config: serde_json::Value,
+    },
 }
 
 /// A definition of a single transformation pipeline.
@@ -25,4 +151,12 @@ pub struct TransformationDefinition {
     pub sources: Vec<String>,
     /// The operations that this transformation pipeline performs. These will be executed in sequence.
     pub operations: Vec<Operation>,
+    /// The destination to which the output of this transformation pipeline is written, if any.
+    #[serde(default)]
+    pub destination: Option<Destination>,
+    /// The time within which the operations of this transformation pipeline must be performed, once its sources are
+    /// loaded, in milliseconds. When it is exceeded, the transformation stops with a TimedOut error. There is no
+    /// timeout by default.
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
 }
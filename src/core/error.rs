@@ -6,6 +6,11 @@ use serde::Serialize;
 pub enum RustyPipesError {
     LoaderError(String),
     TransformationError(String),
+    SinkError(String),
+    /// The run was cancelled by its cancellation token.
+    Cancelled,
+    /// A source or transformation did not finish within its timeout.
+    TimedOut(String),
 }
 
 impl Display for RustyPipesError {
@@ -13,6 +18,9 @@ impl Display for RustyPipesError {
         match self {
             RustyPipesError::LoaderError(s) => write!(f, "{}", s),
             RustyPipesError::TransformationError(s) => write!(f, "{}", s),
+            RustyPipesError::SinkError(s) => write!(f, "{}", s),
+            RustyPipesError::Cancelled => write!(f, "The run was cancelled"),
+            RustyPipesError::TimedOut(s) => write!(f, "{}", s),
         }
     }
 }
@@ -1,6 +1,22 @@
 use crate::core::{dataframe::Dataframe, result::RustyPipesResult};
 
+/// A stream of batches of rows, as yielded by a Loader. Each batch is a data frame of its own; together, the batches
+/// make up the full data frame.
+pub type Batches<'a> = Box<dyn Iterator<Item = RustyPipesResult<Dataframe>> + 'a>;
+
 /// A Loader is a struct that can yield a data frame. Individual loaders are expected to implement this trait.
 pub trait Loader {
     fn load(&self) -> RustyPipesResult<Dataframe>;
+
+    /// Yield the data frame in batches of at most batch_size rows, so that it need not be held in memory in its
+    /// entirety. Loaders that can read their data incrementally should override this; by default, the whole data
+    /// frame is loaded and yielded as a single batch.
+    fn load_batches<'a>(self: Box<Self>, batch_size: usize) -> RustyPipesResult<Batches<'a>>
+    where
+        Self: 'a,
+    {
+        let _ = batch_size;
+        let df = self.load()?;
+        Ok(Box::new(std::iter::once(Ok(df))))
+    }
 }
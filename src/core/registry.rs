@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::core::{
+    context::Context, definitions::ColumnDefinition, loader::Loader, result::RustyPipesResult,
+    transformation::Transformation,
+};
+
+/// A factory for a custom Loader. It receives the raw JSON configuration of the source, the schema of the source and
+/// the context of the run.
+///
+/// This is implemented for closures with a matching signature that yield a Loader which does not borrow from its
+/// configuration.
+pub trait LoaderFactory: Send + Sync {
+    fn create<'a>(
+        &self,
+        config: &'a Value,
+        schema: &'a [ColumnDefinition],
+        context: &Context,
+    ) -> RustyPipesResult<Box<dyn Loader + 'a>>;
+}
+
+impl<F> LoaderFactory for F
+where
+    F: Fn(&Value, &[ColumnDefinition], &Context) -> RustyPipesResult<Box<dyn Loader>> + Send + Sync,
+{
+    fn create<'a>(
+        &self,
+        config: &'a Value,
+        schema: &'a [ColumnDefinition],
+        context: &Context,
+    ) -> RustyPipesResult<Box<dyn Loader + 'a>> {
+        self(config, schema, context).map(|loader| loader as Box<dyn Loader + 'a>)
+    }
+}
+
+/// A factory for a custom Transformation. It receives the raw JSON configuration of the operation and the context of
+/// the run.
+///
+/// This is implemented for closures with a matching signature that yield a Transformation which does not borrow from
+/// its configuration.
+pub trait TransformationFactory: Send + Sync {
+    fn create<'a>(
+        &self,
+        config: &'a Value,
+        context: &Context,
+    ) -> RustyPipesResult<Box<dyn Transformation + 'a>>;
+}
+
+impl<F> TransformationFactory for F
+where
+    F: Fn(&Value, &Context) -> RustyPipesResult<Box<dyn Transformation>> + Send + Sync,
+{
+    fn create<'a>(
+        &self,
+        config: &'a Value,
+        context: &Context,
+    ) -> RustyPipesResult<Box<dyn Transformation + 'a>> {
+        self(config, context).map(|transformation| transformation as Box<dyn Transformation + 'a>)
+    }
+}
+
+/// A registry of named factories for custom Loaders and Transformations, which can be referred to from a pipeline
+/// definition by their name.
+#[derive(Default)]
+pub struct Registry {
+    loaders: HashMap<String, Box<dyn LoaderFactory>>,
+    transformations: HashMap<String, Box<dyn TransformationFactory>>,
+}
+
+impl Registry {
+    /// Register a factory for a custom Loader under the given name, replacing any factory with the same name.
+    pub fn register_loader(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl LoaderFactory + 'static,
+    ) {
+        self.loaders.insert(name.into(), Box::new(factory));
+    }
+
+    /// Register a factory for a custom Transformation under the given name, replacing any factory with the same name.
+    pub fn register_transformation(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl TransformationFactory + 'static,
+    ) {
+        self.transformations.insert(name.into(), Box::new(factory));
+    }
+
+    /// Retrieve the factory for the custom Loader with the given name.
+    pub fn loader(&self, name: &str) -> Option<&dyn LoaderFactory> {
+        self.loaders.get(name).map(|factory| factory.as_ref())
+    }
+
+    /// Retrieve the factory for the custom Transformation with the given name.
+    pub fn transformation(&self, name: &str) -> Option<&dyn TransformationFactory> {
+        self.transformations
+            .get(name)
+            .map(|factory| factory.as_ref())
+    }
+}
@@ -0,0 +1,8 @@
+use crate::core::{dataframe::Dataframe, result::RustyPipesResult};
+
+/// A Sink is a struct that can write the output of a transformation to a destination. Individual sinks are expected to
+/// implement this trait.
+pub trait Sink {
+    /// Write the given data frames to the destination. The data frames are written as a single data set, in order.
+    fn write(&self, dfs: &[Dataframe]) -> RustyPipesResult<()>;
+}
@@ -4,5 +4,6 @@ use crate::core::{dataframe::Dataframe, result::RustyPipesResult};
 /// Each individual transformation will have its own semantics about what it expects as its vector of inputs, as
 /// well as the arity of this vector.
 pub trait Transformation {
+    #[allow(clippy::ptr_arg)]
     fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>>;
 }
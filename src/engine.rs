@@ -1,38 +1,177 @@
 use rayon::prelude::*;
-use std::{collections::HashMap, path::Path};
+use std::{
+    borrow::Cow,
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
+    cache::{node_fingerprint, source_fingerprint, ResultCache},
     core::{
         context::Context,
         dataframe::Dataframe,
-        definitions::{Operation, PipelineDefinition, Source, TransformationDefinition},
+        definitions::{DataSourceDefinition, Destination, PipelineDefinition, Source},
+        error::RustyPipesError,
         loader::Loader,
+        registry::{LoaderFactory, Registry, TransformationFactory},
         result::RustyPipesResult,
+        sink::Sink,
         transformation::Transformation,
     },
-    loaders::FileLoader,
-    transformations::{Filter, InnerJoin},
+    loaders::{FileLoader, HttpLoader, InlineLoader, PostgresLoader, SqliteLoader},
+    observer::{Event, Observer},
+    plan::{
+        explain::{explain, Explanation},
+        Columns, LogicalPlan, OpaqueOperation, PlanNode,
+    },
+    report::{ReportCollector, RunReport},
+    sinks::{ParquetSink, SqliteSink},
+    spill::{estimated_size, MemoryBudget},
+    trace::{info_span, Span},
+    transformations::{
+        inner_join::{BatchedBuild, JoinTable},
+        Filter, InnerJoin, Script, Select, WasmPlugin,
+    },
 };
 
-fn build_pipeline<'a>(
-    definition: &'a TransformationDefinition,
+/// Build the transformation that performs an operation which the plan treats as opaque.
+fn build_operation<'a>(
+    operation: &'a OpaqueOperation,
     context: &Context,
-) -> RustyPipesResult<Vec<Box<dyn Transformation + 'a>>> {
-    let mut transformations = vec![];
-    for op_def in &definition.operations {
-        let op: Box<dyn Transformation> = match op_def {
-            Operation::Filter { predicate } => Box::new(Filter::new(predicate, context)?),
-            Operation::InnerJoin { on } => Box::new(InnerJoin::new(on)?),
-        };
-        transformations.push(op);
+    registry: &Registry,
+) -> RustyPipesResult<Box<dyn Transformation + 'a>> {
+    let op: Box<dyn Transformation> = match operation {
+        OpaqueOperation::Script {
+            language,
+            code,
+            mode,
+            max_operations,
+        } => Box::new(Script::new(
+            *language,
+            code,
+            *mode,
+            *max_operations,
+            context,
+        )?),
+        OpaqueOperation::Wasm {
+            path,
+            config,
+            fuel,
+            max_memory_bytes,
+        } => Box::new(WasmPlugin::new(
+            path,
+            config,
+            *fuel,
+            *max_memory_bytes,
+            context,
+        )?),
+        OpaqueOperation::Custom { name, config } => registry
+            .transformation(name)
+            .ok_or_else(|| {
+                RustyPipesError::TransformationError(format!("Unknown transformation {}", name))
+            })?
+            .create(config, context)?,
+    };
+    Ok(op)
+}
+
+/// The number of rows per batch in which the build side of a join is loaded when it is spilled, unless streaming.
+const SPILL_BATCH_SIZE: usize = 1_024;
+
+/// Build the transformation that performs the operation of a plan node, other than a scan.
+fn build_transformation<'a>(
+    node: &'a PlanNode,
+    context: &'a Context,
+    registry: &Registry,
+) -> RustyPipesResult<Box<dyn Transformation + 'a>> {
+    let op: Box<dyn Transformation> = match node {
+        PlanNode::Scan { source } => {
+            return Err(RustyPipesError::TransformationError(format!(
+                "Unable to transform scan of source {}",
+                source
+            )))
+        }
+        PlanNode::Filter { predicates, .. } => {
+            Box::new(Filter::all(predicates.iter().map(String::as_str), context)?)
+        }
+        PlanNode::Select { columns, .. } => Box::new(Select::new(columns)),
+        PlanNode::InnerJoin { .. } => Box::new(build_join(node, context)?),
+        PlanNode::Operation { operation, .. } => build_operation(operation, context, registry)?,
+    };
+    Ok(op)
+}
+
+/// Build the join that an InnerJoin node performs.
+fn build_join<'a>(node: &'a PlanNode, context: &'a Context) -> RustyPipesResult<InnerJoin<'a>> {
+    let PlanNode::InnerJoin {
+        on,
+        preserve_order,
+        strategy,
+        coercion,
+        match_nulls,
+        ..
+    } = node
+    else {
+        unreachable!("only InnerJoin nodes perform joins")
+    };
+    Ok(InnerJoin::new(on, context)?
+        .with_preserve_order(*preserve_order)
+        .with_strategy(*strategy)
+        .with_key_coercion(*coercion)
+        .with_matching_nulls(*match_nulls))
+}
+
+/// An operation through which the batches of a streamed source pass.
+enum Stage<'a> {
+    /// A transformation that is applied to each batch, which is passed along with the other inputs of the operation.
+    Transform(Box<dyn Transformation + 'a>),
+    /// A join of which each batch is the probe side, and of which the build side is prepared once.
+    Probe(JoinTable<'a, 'a>),
+}
+
+fn build_sink(destination: &Destination) -> Box<dyn Sink + '_> {
+    match destination {
+        Destination::Sqlite { path, table, mode } => {
+            Box::new(SqliteSink::new(Path::new(path), table, *mode))
+        }
+        Destination::Parquet {
+            path,
+            compression,
+            row_group_size,
+        } => Box::new(ParquetSink::new(
+            Path::new(path),
+            *compression,
+            *row_group_size,
+        )),
+    }
+}
+
+/// The context of a source or transformation with the given timeout, if any: its cancellation token also times out
+/// once the timeout has elapsed, with an error that names the source or transformation.
+fn with_timeout<'c>(context: &'c Context, timeout_ms: Option<u64>, name: &str) -> Cow<'c, Context> {
+    match timeout_ms {
+        Some(timeout_ms) => {
+            let cancellation = context
+                .cancellation()
+                .with_timeout(Duration::from_millis(timeout_ms), name);
+            Cow::Owned(context.clone().with_cancellation(cancellation))
+        }
+        None => Cow::Borrowed(context),
     }
-    Ok(transformations)
 }
 
 /// The engine is the entry point for running a pipeline. It is constructed based on a pipeline definition.
 /// It then sources the data and runs the transformations, yielding the outputs of each transformation.
 pub struct Engine {
     pipeline_definition: PipelineDefinition,
+    registered_dataframes: HashMap<String, Dataframe>,
+    registry: Registry,
+    batch_size: Option<usize>,
+    memory_budget: Option<MemoryBudget>,
+    cache: Option<ResultCache>,
+    observers: Vec<Box<dyn Observer>>,
 }
 
 impl Engine {
@@ -40,75 +179,850 @@ impl Engine {
     pub fn from_definition(pipeline_definition: PipelineDefinition) -> Self {
         Engine {
             pipeline_definition,
+            registered_dataframes: HashMap::new(),
+            registry: Registry::default(),
+            batch_size: None,
+            memory_budget: None,
+            cache: None,
+            observers: vec![],
         }
     }
 
-    fn load_dataframes(&self) -> HashMap<String, RustyPipesResult<Dataframe>> {
-        self.pipeline_definition
-            .sources
+    /// Run the pipeline in streaming mode, loading sources in batches of at most the given number of rows where
+    /// possible. A source is streamed when it is only processed by chains of streaming operations (Filters, Selects,
+    /// the probe side of InnerJoins, and Scripts that map or filter rows): each batch passes through the chain before
+    /// the next batch is loaded, so that, e.g., a large CSV file can be filtered in bounded memory. Blocking operations
+    /// (the build side of joins, and Scripts over whole data frames, WASM plugins and custom transformations) need
+    /// their inputs in full; [`Engine::explain`] marks them as such.
+    pub fn with_streaming(mut self, batch_size: usize) -> Self {
+        self.batch_size = Some(batch_size.max(1));
+        self
+    }
+
+    /// Limit the rows of the build side that each hash join holds in memory at once to the given budget. Sources that
+    /// are only scanned as the build side of hash joins are loaded in batches rather than up front: their rows are held
+    /// in memory for as long as they fit the budget, and are partitioned to a temporary directory otherwise (see
+    /// [`InnerJoin::build_batches`]). This does not bound the memory used by a run: other sources (unless streamed),
+    /// the build sides of sort-merge joins and of joins with other operations as their build side, and the outputs of
+    /// transformations are held in memory regardless. Loaders that do not load in batches load such sources in full
+    /// before they are partitioned.
+    pub fn with_memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    /// Cache the data frames of sources and the outputs of transformations in the given cache, and reuse them in later
+    /// runs for as long as their fingerprints match (see [`ResultCache`]). Sources of which the rows cannot be
+    /// fingerprinted (PostgreSQL databases, HTTP endpoints and custom loaders) are never cached, and neither are the
+    /// outputs of transformations that depend on them, on registered data frames or on custom transformations. Caching
+    /// is off by default.
+    pub fn with_cache(mut self, cache: ResultCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Register a factory for a custom Loader, which sources with a "Custom" source definition can refer to by the
+    /// given name.
+    pub fn register_loader(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl LoaderFactory + 'static,
+    ) {
+        self.registry.register_loader(name, factory);
+    }
+
+    /// Register a factory for a custom Transformation, which "Custom" operations can refer to by the given name.
+    pub fn register_transformation(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl TransformationFactory + 'static,
+    ) {
+        self.registry.register_transformation(name, factory);
+    }
+
+    /// Register an observer, which is notified of the events that occur while the pipeline is run: sources being
+    /// loaded, transformations and their operations being run (along with their row counts and durations), and
+    /// failures (see [`Event`]).
+    pub fn register_observer(&mut self, observer: impl Observer + 'static) {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Register a data frame that the application already holds as a source with the given name, so that
+    /// transformations can refer to it like any other source. A registered data frame takes the place of a source
+    /// with the same name in the pipeline definition, which is then not loaded.
+    pub fn register_dataframe(&mut self, name: impl Into<String>, dataframe: Dataframe) {
+        self.registered_dataframes.insert(name.into(), dataframe);
+    }
+
+    /// The optimized logical plan that describes what running the pipeline entails. Building the plan does not perform
+    /// any work.
+    pub fn plan(&self) -> LogicalPlan {
+        LogicalPlan::new(&self.pipeline_definition, &self.registered_dataframes).optimize()
+    }
+
+    /// Explain what running the pipeline entails, based on the optimized logical plan: which sources are loaded (and
+    /// which of their columns), and the tree of operations of each transformation along with their arities, inferred
+    /// columns and join strategies. Explaining the pipeline does not perform any work.
+    pub fn explain(&self) -> Explanation {
+        explain(
+            &self.plan(),
+            &self.pipeline_definition,
+            &self.registered_dataframes,
+        )
+    }
+
+    /// Build the loader for a source, which reads only the given columns where the loader supports this.
+    fn build_loader<'a>(
+        &'a self,
+        definition: &'a DataSourceDefinition,
+        columns: &'a Columns,
+        context: &'a Context,
+    ) -> RustyPipesResult<Box<dyn Loader + 'a>> {
+        match &definition.source {
+            Source::File {
+                path,
+                format,
+                dialect,
+                compression,
+                filename_column,
+                parallel,
+            } => {
+                let path = Path::new(path);
+                Ok(Box::new(
+                    FileLoader::new(path, format, dialect, &definition.schema, context)
+                        .with_compression(*compression)
+                        .with_filename_column(filename_column.as_deref())
+                        .with_parallelism(*parallel)
+                        .with_projection(columns.as_ref()),
+                ))
+            }
+            Source::Sqlite { path, query } => Ok(Box::new(SqliteLoader::new(
+                Path::new(path),
+                query,
+                &definition.schema,
+                context,
+            ))),
+            Source::Postgres {
+                connection,
+                query,
+                parameters,
+            } => PostgresLoader::new(connection, query, parameters, &definition.schema, context)
+                .map(|loader| Box::new(loader) as Box<dyn Loader>),
+            Source::Http {
+                url,
+                method,
+                headers,
+                body,
+                json_path,
+                pagination,
+                retry,
+            } => {
+                HttpLoader::new(url, *method, headers, &definition.schema, context).map(|loader| {
+                    Box::new(
+                        loader
+                            .with_body(body.as_ref())
+                            .with_json_path(json_path.as_deref())
+                            .with_pagination(pagination.as_ref())
+                            .with_retry_policy(retry.clone()),
+                    ) as Box<dyn Loader>
+                })
+            }
+            Source::Inline { rows } => Ok(Box::new(InlineLoader::new(rows, &definition.schema))),
+            Source::Custom { name, config } => self
+                .registry
+                .loader(name)
+                .ok_or_else(|| RustyPipesError::LoaderError(format!("Unknown loader {}", name)))
+                .and_then(|factory| factory.create(config, &definition.schema, context)),
+        }
+    }
+
+    fn notify(&self, event: Event) {
+        for observer in &self.observers {
+            observer.on_event(&event);
+        }
+    }
+
+    /// The estimated size in memory of the given data frames, which is only estimated when there are observers to
+    /// notify of it.
+    fn observed_size<'d>(&self, dfs: impl IntoIterator<Item = &'d Dataframe>) -> usize {
+        if self.observers.is_empty() {
+            return 0;
+        }
+        dfs.into_iter().flatten().map(estimated_size).sum()
+    }
+
+    /// Load the given sources of the plan, reading only the required columns where the loader supports this. Sources
+    /// with a fingerprint (as given) are read from the cache when they are cached under that fingerprint, and cached
+    /// once they are loaded otherwise.
+    fn load_dataframes(
+        &self,
+        plan: &LogicalPlan,
+        required: &BTreeSet<&str>,
+        fingerprints: &HashMap<String, Option<u64>>,
+        context: &Context,
+    ) -> HashMap<String, RustyPipesResult<Dataframe>> {
+        // The sources are loaded on other threads, which do not share the current span unless it is entered there.
+        let parent = Span::current();
+        plan.sources
             .par_iter()
-            .map(|(name, definition)| {
-                let loader = match &definition.source {
-                    Source::File { path, format } => {
-                        let path = Path::new(path);
-                        FileLoader::new(path, format, &definition.schema)
+            .filter(|(name, _)| required.contains(name.as_str()))
+            .filter_map(|(name, columns)| {
+                self.pipeline_definition
+                    .sources
+                    .get(name)
+                    .map(|definition| (name, columns, definition))
+            })
+            .map(|(name, columns, definition)| {
+                let span = parent.in_scope(|| {
+                    info_span!(
+                        "load_source",
+                        source = %name,
+                        rows = tracing::field::Empty,
+                        cached = tracing::field::Empty
+                    )
+                });
+                let _entered = span.enter();
+                self.notify(Event::SourceStarted { source: name });
+                let start = Instant::now();
+                let cache = self
+                    .cache
+                    .as_ref()
+                    .zip(fingerprints.get(name).copied().flatten());
+                let cached = cache.and_then(|(cache, fingerprint)| cache.source(name, fingerprint));
+                let is_cached = cached.is_some();
+                let context =
+                    with_timeout(context, definition.timeout_ms, &format!("Source {}", name));
+                let df = match cached {
+                    Some(df) => Ok(df),
+                    // The token is checked once more after loading, in case the loader does not check it.
+                    None => context
+                        .cancellation()
+                        .check()
+                        .and_then(|_| self.build_loader(definition, columns, &context))
+                        .and_then(|loader| loader.load())
+                        .and_then(|df| context.cancellation().check().map(|_| df)),
+                };
+                match &df {
+                    Ok(df) => {
+                        if let (Some((cache, fingerprint)), false) = (cache, is_cached) {
+                            cache.store_source(name, fingerprint, df);
+                        }
+                        span.record("rows", df.len());
+                        span.record("cached", is_cached);
+                        self.notify(Event::SourceFinished {
+                            source: name,
+                            rows: df.len(),
+                            bytes: self.observed_size([df]),
+                            duration: start.elapsed(),
+                            cached: is_cached,
+                        });
+                    }
+                    Err(error) => self.notify(Event::SourceFailed {
+                        source: name,
+                        error,
+                        duration: start.elapsed(),
+                    }),
+                }
+                (name.clone(), df)
+            })
+            .collect()
+    }
+
+    fn scan<'d>(
+        &'d self,
+        source: &str,
+        dfs: &'d HashMap<String, RustyPipesResult<Dataframe>>,
+    ) -> RustyPipesResult<&'d Dataframe> {
+        match self.registered_dataframes.get(source) {
+            Some(df) => Ok(df),
+            None => dfs
+                .get(source)
+                .ok_or_else(|| {
+                    RustyPipesError::TransformationError(format!("Unknown source {}", source))
+                })?
+                .as_ref()
+                .map_err(|err| err.clone()),
+        }
+    }
+
+    /// Execute a plan node of the given transformation: yield the data frame of a source for a scan, or perform the
+    /// operation of the node on the data frames yielded by its inputs. Data frames of sources are borrowed rather than
+    /// copied.
+    #[allow(clippy::too_many_arguments)]
+    fn execute<'d>(
+        &'d self,
+        transformation_name: &str,
+        node: &PlanNode,
+        plan: &LogicalPlan,
+        dfs: &'d HashMap<String, RustyPipesResult<Dataframe>>,
+        streamed: &BTreeSet<String>,
+        spilled: &BTreeSet<String>,
+        context: &Context,
+    ) -> RustyPipesResult<Vec<Cow<'d, Dataframe>>> {
+        if let (Some(source), Some(batch_size)) = (node.streamed_source(), self.batch_size) {
+            if streamed.contains(source) {
+                let df = self.execute_streaming(
+                    transformation_name,
+                    node,
+                    source,
+                    batch_size,
+                    plan,
+                    dfs,
+                    streamed,
+                    spilled,
+                    context,
+                )?;
+                return Ok(vec![Cow::Owned(df)]);
+            }
+        }
+        if let PlanNode::Scan { source } = node {
+            return Ok(vec![Cow::Borrowed(self.scan(source, dfs)?)]);
+        }
+        let mut transformation = build_transformation(node, context, &self.registry)?;
+        let mut inputs = vec![];
+        // A spilled build side is loaded in batches by the join itself, which is counted along with the other inputs.
+        let mut build_rows = 0;
+        match (node.build_source(), &self.memory_budget) {
+            (Some(source), Some(budget)) if spilled.contains(source) => {
+                inputs.extend(self.execute(
+                    transformation_name,
+                    &node.inputs()[0],
+                    plan,
+                    dfs,
+                    streamed,
+                    spilled,
+                    context,
+                )?);
+                let join = build_join(node, context)?;
+                match self.build_from_source(&join, source, plan, budget, context)? {
+                    BatchedBuild::Rows(df) => inputs.push(Cow::Owned(df)),
+                    BatchedBuild::Spilled(table) => {
+                        build_rows = table.build_rows();
+                        transformation = Box::new(table);
+                    }
+                }
+            }
+            _ => {
+                for input in node.inputs() {
+                    inputs.extend(self.execute(
+                        transformation_name,
+                        input,
+                        plan,
+                        dfs,
+                        streamed,
+                        spilled,
+                        context,
+                    )?);
+                }
+            }
+        }
+        context.cancellation().check()?;
+        let refs: Vec<&Dataframe> = inputs.iter().map(|df| df.as_ref()).collect();
+        self.notify(Event::OperationStarted {
+            transformation: transformation_name,
+            operation: node.operation_name(),
+        });
+        let span = info_span!(
+            "operation",
+            operation = node.operation_name(),
+            rows_in = tracing::field::Empty,
+            rows_out = tracing::field::Empty
+        );
+        let start = Instant::now();
+        let output = span.in_scope(|| transformation.transform(&refs))?;
+        let rows_in = refs.iter().map(|df| df.len()).sum::<usize>() + build_rows;
+        span.record("rows_in", rows_in);
+        span.record("rows_out", output.iter().map(Vec::len).sum::<usize>());
+        self.notify(Event::OperationFinished {
+            transformation: transformation_name,
+            operation: node.operation_name(),
+            rows_in,
+            rows_out: output.iter().map(Vec::len).sum(),
+            bytes_out: self.observed_size(&output),
+            duration: start.elapsed(),
+        });
+        Ok(output.into_iter().map(Cow::Owned).collect())
+    }
+
+    /// Execute a chain of streaming plan nodes on top of a scan of a streamed source. The source is loaded in batches,
+    /// and each batch is passed through the operations of the chain before the next batch is loaded, so that only the
+    /// output of the chain is held in memory. The other inputs of the nodes in the chain are executed up front, and
+    /// spilled build sides are prepared up front.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_streaming(
+        &self,
+        transformation_name: &str,
+        node: &PlanNode,
+        source: &str,
+        batch_size: usize,
+        plan: &LogicalPlan,
+        dfs: &HashMap<String, RustyPipesResult<Dataframe>>,
+        streamed: &BTreeSet<String>,
+        spilled: &BTreeSet<String>,
+        context: &Context,
+    ) -> RustyPipesResult<Dataframe> {
+        let mut chain = vec![];
+        let mut current = node;
+        while let [first, others @ ..] = current.inputs() {
+            let mut inputs = vec![];
+            let mut table = None;
+            match (current.build_source(), &self.memory_budget) {
+                (Some(source), Some(budget)) if spilled.contains(source) => {
+                    let join = build_join(current, context)?;
+                    match self.build_from_source(&join, source, plan, budget, context)? {
+                        BatchedBuild::Rows(df) => inputs.push(Cow::Owned(df)),
+                        BatchedBuild::Spilled(spilled) => table = Some(spilled),
                     }
+                }
+                _ => {
+                    for input in others {
+                        inputs.extend(self.execute(
+                            transformation_name,
+                            input,
+                            plan,
+                            dfs,
+                            streamed,
+                            spilled,
+                            context,
+                        )?);
+                    }
+                }
+            }
+            chain.push((current, inputs, table));
+            current = first;
+        }
+        chain.reverse();
+
+        let mut stages = vec![];
+        let mut stage_stats = vec![];
+        for (node, inputs, table) in &mut chain {
+            let operation = node.operation_name();
+            let span = info_span!(
+                "operation",
+                operation,
+                rows_in = tracing::field::Empty,
+                rows_out = tracing::field::Empty
+            );
+            let start = Instant::now();
+            // The rows of a spilled build side are counted along with the other inputs.
+            let build_rows = table.as_ref().map_or(0, JoinTable::build_rows);
+            let stage = match (*node, table.take()) {
+                (PlanNode::InnerJoin { .. }, Some(table)) => Stage::Probe(table),
+                (PlanNode::InnerJoin { .. }, None) => {
+                    let right = inputs.first().ok_or_else(|| {
+                        RustyPipesError::TransformationError(
+                            "Unable to join without a second data frame".to_owned(),
+                        )
+                    })?;
+                    let join = build_join(node, context)?;
+                    Stage::Probe(span.in_scope(|| join.build(right))?)
+                }
+                (node, _) => Stage::Transform(build_transformation(node, context, &self.registry)?),
+            };
+            // The other inputs are counted once, while the batches of the streamed source are counted as they pass.
+            let rows_in = inputs.iter().map(|df| df.len()).sum::<usize>() + build_rows;
+            stages.push((operation, span, stage, &*inputs));
+            stage_stats.push((rows_in, 0, 0, start.elapsed()));
+        }
+
+        let definition = self
+            .pipeline_definition
+            .sources
+            .get(source)
+            .ok_or_else(|| {
+                RustyPipesError::TransformationError(format!("Unknown source {}", source))
+            })?;
+        let columns = plan.sources.get(source).cloned().flatten();
+        let context = with_timeout(
+            context,
+            definition.timeout_ms,
+            &format!("Source {}", source),
+        );
+        for (operation, ..) in &stages {
+            self.notify(Event::OperationStarted {
+                transformation: transformation_name,
+                operation,
+            });
+        }
+        let source_span = info_span!(
+            "load_source",
+            source = %source,
+            rows = tracing::field::Empty,
+            cached = false,
+            streamed = true
+        );
+        self.notify(Event::SourceStarted { source });
+        let source_failed = |error: RustyPipesError, duration: Duration| {
+            self.notify(Event::SourceFailed {
+                source,
+                error: &error,
+                duration,
+            });
+            error
+        };
+
+        // The time spent loading the source and running each stage is measured separately, as they are interleaved.
+        let start = Instant::now();
+        let mut batches = source_span
+            .in_scope(|| {
+                self.build_loader(definition, &columns, &context)
+                    .and_then(|loader| loader.load_batches(batch_size))
+            })
+            .map_err(|error| source_failed(error, start.elapsed()))?;
+        let mut loading = start.elapsed();
+        let (mut rows, mut bytes) = (0, 0);
+        let mut output = vec![];
+        loop {
+            let start = Instant::now();
+            // The token is checked for each batch, as the loader may not check it while streaming.
+            let batch = match context.cancellation().check() {
+                Ok(()) => source_span.in_scope(|| batches.next()),
+                Err(error) => Some(Err(error)),
+            };
+            loading += start.elapsed();
+            let Some(batch) = batch else {
+                break;
+            };
+            let mut batch = batch.map_err(|error| source_failed(error, loading))?;
+            rows += batch.len();
+            bytes = bytes.max(self.observed_size([&batch]));
+            for ((operation, span, stage, inputs), (rows_in, rows_out, bytes_out, duration)) in
+                stages.iter().zip(&mut stage_stats)
+            {
+                let _entered = span.enter();
+                let start = Instant::now();
+                *rows_in += batch.len();
+                batch = match stage {
+                    Stage::Transform(transformation) => {
+                        let mut refs = vec![&batch];
+                        refs.extend(inputs.iter().map(|df| df.as_ref()));
+                        // The batches of a streamed source pass through the chain as a single data frame.
+                        match <[Dataframe; 1]>::try_from(transformation.transform(&refs)?) {
+                            Ok([batch]) => batch,
+                            Err(output) => {
+                                return Err(RustyPipesError::TransformationError(format!(
+                                    "Expected {} to yield a single data frame when streaming, but it yielded {}",
+                                    operation,
+                                    output.len()
+                                )))
+                            }
+                        }
+                    }
+                    Stage::Probe(table) => table.probe(&batch)?,
                 };
-                (name.clone(), loader.load())
+                *rows_out += batch.len();
+                *bytes_out += self.observed_size([&batch]);
+                *duration += start.elapsed();
+            }
+            output.extend(batch);
+        }
+
+        source_span.record("rows", rows);
+        self.notify(Event::SourceFinished {
+            source,
+            rows,
+            bytes,
+            duration: loading,
+            cached: false,
+        });
+        for ((operation, span, ..), (rows_in, rows_out, bytes_out, duration)) in
+            stages.iter().zip(stage_stats)
+        {
+            span.record("rows_in", rows_in);
+            span.record("rows_out", rows_out);
+            self.notify(Event::OperationFinished {
+                transformation: transformation_name,
+                operation,
+                rows_in,
+                rows_out,
+                bytes_out,
+                duration,
+            });
+        }
+        Ok(output)
+    }
+
+    /// Load a spilled source (see [`LogicalPlan::spilled_sources`]) in batches, and prepare the build side of the given
+    /// join from them within the memory budget (see [`InnerJoin::build_batches`]). Spilled sources are not cached.
+    fn build_from_source<'a>(
+        &self,
+        join: &InnerJoin<'a>,
+        source: &str,
+        plan: &LogicalPlan,
+        budget: &MemoryBudget,
+        context: &Context,
+    ) -> RustyPipesResult<BatchedBuild<'a>> {
+        let definition = self
+            .pipeline_definition
+            .sources
+            .get(source)
+            .ok_or_else(|| {
+                RustyPipesError::TransformationError(format!("Unknown source {}", source))
+            })?;
+        let columns = plan.sources.get(source).cloned().flatten();
+        let context = with_timeout(
+            context,
+            definition.timeout_ms,
+            &format!("Source {}", source),
+        );
+        let span = info_span!(
+            "load_source",
+            source = %source,
+            rows = tracing::field::Empty,
+            cached = false,
+            spilled = true
+        );
+        let _entered = span.enter();
+        self.notify(Event::SourceStarted { source });
+        // The time spent partitioning the rows is included, as it is interleaved with loading them.
+        let start = Instant::now();
+        let (mut rows, mut bytes) = (0, 0);
+        let build = context
+            .cancellation()
+            .check()
+            .and_then(|_| self.build_loader(definition, &columns, &context))
+            .and_then(|loader| loader.load_batches(self.batch_size.unwrap_or(SPILL_BATCH_SIZE)))
+            .and_then(|batches| {
+                let batches = batches.inspect(|batch| {
+                    if let Ok(batch) = batch {
+                        rows += batch.len();
+                        bytes = bytes.max(self.observed_size([batch]));
+                    }
+                });
+                join.build_batches(batches, budget)
+            })
+            .and_then(|build| context.cancellation().check().map(|_| build));
+        match &build {
+            Ok(_) => {
+                span.record("rows", rows);
+                self.notify(Event::SourceFinished {
+                    source,
+                    rows,
+                    bytes,
+                    duration: start.elapsed(),
+                    cached: false,
+                });
+            }
+            Err(error) => self.notify(Event::SourceFailed {
+                source,
+                error,
+                duration: start.elapsed(),
+            }),
+        }
+        build
+    }
+
+    /// The fingerprints of the sources of the plan, when caching.
+    fn source_fingerprints(&self, plan: &LogicalPlan) -> HashMap<String, Option<u64>> {
+        if self.cache.is_none() {
+            return HashMap::new();
+        }
+        plan.sources
+            .par_iter()
+            .map(|(name, columns)| {
+                let fingerprint = self
+                    .pipeline_definition
+                    .sources
+                    .get(name)
+                    .and_then(|definition| source_fingerprint(definition, columns));
+                (name.clone(), fingerprint)
             })
             .collect()
     }
 
     /// Run the pipeline. This will:
-    /// - fetch data from the defined data sources
-    /// - run each transformation
+    /// - plan and optimize the work to be done (see [`Engine::plan`])
+    /// - reuse the cached outputs of transformations that have not changed, when caching (see [`Engine::with_cache`])
+    /// - fetch data from the data sources that are needed by the other transformations
+    /// - run each of these transformations
+    /// - write the output of each transformation that has a destination to that destination
     /// - yield a map of each transformation output, keyed by their name
     pub fn run(&mut self, context: &Context) -> HashMap<String, RustyPipesResult<Vec<Dataframe>>> {
-        let dfs = self.load_dataframes();
+        self.run_plan(self.plan(), context)
+    }
 
-        self.pipeline_definition
-            .transformations
-            .par_iter()
-            .map(|(name, definition)| {
-                let name = name.clone();
+    /// Run the pipeline like [`Engine::run`], and report on the run: the load time and row count of each source, the
+    /// time and selectivity of each operation, an estimate of the peak memory used, the errors, and the overall
+    /// wall-clock time (see [`RunReport`]).
+    pub fn run_with_report(
+        &mut self,
+        context: &Context,
+    ) -> (HashMap<String, RustyPipesResult<Vec<Dataframe>>>, RunReport) {
+        let collector = Arc::new(ReportCollector::default());
+        self.observers.push(Box::new(Arc::clone(&collector)));
+        let results = self.run(context);
+        self.observers.pop();
+        (results, collector.report())
+    }
 
-                let pipeline = build_pipeline(definition, context);
-                if let Err(err) = pipeline {
-                    return (name, Err(err));
-                }
-                let unwrapped_pipeline = pipeline.unwrap();
-                let mut pipeline_iter = unwrapped_pipeline.iter();
+    /// Run only the transformations with the given names, like [`Engine::run`]: only the sources that these
+    /// transformations need are loaded (and only the columns they need of them), and all other transformations are
+    /// skipped. Yields a map of the output of each of the given transformations, keyed by their name, in which names
+    /// that do not refer to a transformation of the pipeline map to an error.
+    pub fn run_selected(
+        &mut self,
+        names: &[&str],
+        context: &Context,
+    ) -> HashMap<String, RustyPipesResult<Vec<Dataframe>>> {
+        let plan = LogicalPlan::new(&self.pipeline_definition, &self.registered_dataframes)
+            .select(names)
+            .optimize();
+        let mut results = self.run_plan(plan, context);
+        for name in names {
+            if !self.pipeline_definition.transformations.contains_key(*name) {
+                results.insert(
+                    String::from(*name),
+                    Err(RustyPipesError::TransformationError(format!(
+                        "Unknown transformation {}",
+                        name
+                    ))),
+                );
+            }
+        }
+        results
+    }
 
-                let source_dataframes: RustyPipesResult<Vec<&Dataframe>> = definition
-                    .sources
-                    .iter()
-                    .map(|source| dfs.get(source).unwrap().as_ref().map_err(|err| err.clone()))
-                    .collect();
+    /// Run the transformations of a plan, loading the sources that they need.
+    fn run_plan(
+        &self,
+        plan: LogicalPlan,
+        context: &Context,
+    ) -> HashMap<String, RustyPipesResult<Vec<Dataframe>>> {
+        let streamed = match self.batch_size {
+            Some(_) => plan.streamed_sources(),
+            None => BTreeSet::new(),
+        };
+        let spilled = match self.memory_budget {
+            Some(_) => plan.spilled_sources(),
+            None => BTreeSet::new(),
+        };
+        let span = info_span!(
+            "pipeline_run",
+            transformations = plan.transformations.len(),
+            sources = tracing::field::Empty
+        );
+        let _entered = span.enter();
+        let start = Instant::now();
+        let fingerprints = self.source_fingerprints(&plan);
+
+        let transformations = plan
+            .transformations
+            .iter()
+            .filter_map(|(name, root)| {
+                let definition = self.pipeline_definition.transformations.get(name)?;
+                let root = root.as_ref();
+                let cache = self
+                    .cache
+                    .as_ref()
+                    .zip(root.and_then(|root| node_fingerprint(root, &fingerprints, context)));
+                let cached = cache.and_then(|(cache, fingerprint)| cache.output(name, fingerprint));
+                Some((name, definition, root, cache, cached))
+            })
+            .collect::<Vec<_>>();
+        // Streamed and spilled sources are loaded by each transformation that scans them, and the other sources only
+        // once.
+        let (streamed_scans, scans): (Vec<&str>, Vec<&str>) = transformations
+            .iter()
+            .filter(|(.., cached)| cached.is_none())
+            .filter_map(|(_, _, root, ..)| *root)
+            .flat_map(PlanNode::sources)
+            .filter(|source| plan.sources.contains_key(*source))
+            .partition(|source| streamed.contains(*source) || spilled.contains(*source));
+        let required = scans.into_iter().collect::<BTreeSet<_>>();
+        span.record("sources", required.len() + streamed_scans.len());
+        self.notify(Event::RunStarted {
+            sources: required.len() + streamed_scans.len(),
+            transformations: transformations.len(),
+        });
+        let dfs = self.load_dataframes(&plan, &required, &fingerprints, context);
 
-                let mut current_output = if let Some(first_transformation) = pipeline_iter.next() {
-                    source_dataframes.and_then(|x| first_transformation.transform(&x))
-                } else {
-                    Ok(vec![])
+        let results = transformations
+            .into_par_iter()
+            .map(|(name, definition, root, cache, cached)| {
+                let is_cached = cached.is_some();
+                let transformation_span = span.in_scope(|| {
+                    info_span!(
+                        "transformation",
+                        transformation = %name,
+                        rows = tracing::field::Empty,
+                        cached = is_cached
+                    )
+                });
+                let _entered = transformation_span.enter();
+                self.notify(Event::TransformationStarted {
+                    transformation: name,
+                });
+                let start = Instant::now();
+                let context = with_timeout(
+                    context,
+                    definition.timeout_ms,
+                    &format!("Transformation {}", name),
+                );
+                let mut output = match (cached, root) {
+                    (Some(output), _) => Ok(output),
+                    (None, Some(root)) => {
+                        let output = context
+                            .cancellation()
+                            .check()
+                            .and_then(|_| {
+                                self.execute(name, root, &plan, &dfs, &streamed, &spilled, &context)
+                            })
+                            .map(|output| {
+                                output.into_iter().map(Cow::into_owned).collect::<Vec<_>>()
+                            });
+                        if let (Some((cache, fingerprint)), Ok(output)) = (cache, &output) {
+                            cache.store_output(name, fingerprint, output);
+                        }
+                        output
+                    }
+                    (None, None) => Ok(vec![]),
                 };
 
-                for transformation in pipeline_iter {
-                    current_output = current_output.and_then(|output| {
-                        let refs = output.iter().collect();
-                        transformation.transform(&refs)
+                // A cancelled transformation is not written to its destination, even when its output is complete.
+                output = output.and_then(|output| context.cancellation().check().map(|_| output));
+                if let Some(destination) = &definition.destination {
+                    output = output.and_then(|output| {
+                        build_sink(destination).write(&output)?;
+                        Ok(output)
                     });
                 }
 
-                (name, current_output)
+                match &output {
+                    Ok(output) => {
+                        let rows = output.iter().map(Vec::len).sum();
+                        transformation_span.record("rows", rows);
+                        self.notify(Event::TransformationFinished {
+                            transformation: name,
+                            rows,
+                            duration: start.elapsed(),
+                            cached: is_cached,
+                        })
+                    }
+                    Err(error) => self.notify(Event::TransformationFailed {
+                        transformation: name,
+                        error,
+                        duration: start.elapsed(),
+                    }),
+                }
+                (name.clone(), output)
             })
-            .collect()
+            .collect();
+        self.notify(Event::RunFinished {
+            duration: start.elapsed(),
+        });
+        results
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::core::dataframe::ColumnValue;
+    use crate::core::{
+        cancellation::CancellationToken, dataframe::ColumnValue, definitions::ColumnDefinition,
+    };
 
     use super::*;
+    #[cfg(feature = "tracing")]
+    use std::{collections::BTreeMap, sync::Mutex};
 
     #[test]
     fn it_runs_a_pipeline() {
@@ -130,4 +1044,862 @@ mod test {
             ])]
         );
     }
+
+    #[test]
+    fn it_notifies_observers() {
+        #[derive(Clone, Default)]
+        struct Recorder(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+        impl Observer for Recorder {
+            fn on_event(&self, event: &Event<'_>) {
+                let description = match event {
+                    Event::RunStarted {
+                        sources,
+                        transformations,
+                    } => format!("run {} {}", sources, transformations),
+                    Event::SourceFinished { source, rows, .. } => format!("{} {}", source, rows),
+                    Event::OperationFinished {
+                        transformation,
+                        operation,
+                        rows_in,
+                        rows_out,
+                        ..
+                    } => format!("{} {} {} {}", transformation, operation, rows_in, rows_out),
+                    Event::TransformationFinished {
+                        transformation,
+                        rows,
+                        ..
+                    } => format!("{} {}", transformation, rows),
+                    Event::RunFinished { .. } => String::from("done"),
+                    _ => return,
+                };
+                self.0.lock().unwrap().push(description);
+            }
+        }
+
+        for streaming in [false, true] {
+            let raw_definition = std::fs::read_to_string("examples/filter.json").unwrap();
+            let parsed: PipelineDefinition = serde_json::from_str(&raw_definition).unwrap();
+            let mut engine = Engine::from_definition(parsed);
+            if streaming {
+                engine = engine.with_streaming(2);
+            }
+            let recorder = Recorder::default();
+            engine.register_observer(recorder.clone());
+
+            engine.run(&Default::default());
+
+            assert_eq!(
+                *recorder.0.lock().unwrap(),
+                vec![
+                    "run 1 1",
+                    "salaries 4",
+                    "filtered Filter 4 1",
+                    "filtered 1",
+                    "done"
+                ]
+            );
+        }
+    }
+
+    /// The name of a span, the index of the span it is nested in, if any, and its fields.
+    #[cfg(feature = "tracing")]
+    type RecordedSpan = (&'static str, Option<usize>, BTreeMap<String, String>);
+
+    /// A subscriber that records each span, along with the span it is nested in and its fields, in a single thread.
+    #[cfg(feature = "tracing")]
+    #[derive(Default, Clone)]
+    struct SpanRecorder {
+        spans: Arc<Mutex<Vec<RecordedSpan>>>,
+        entered: Arc<Mutex<Vec<usize>>>,
+    }
+
+    #[cfg(feature = "tracing")]
+    struct FieldRecorder<'f>(&'f mut BTreeMap<String, String>);
+
+    #[cfg(feature = "tracing")]
+    impl tracing::field::Visit for FieldRecorder<'_> {
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_owned(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_owned(), format!("{:?}", value));
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    impl tracing::Subscriber for SpanRecorder {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let parent = self.entered.lock().unwrap().last().copied();
+            let mut fields = BTreeMap::new();
+            attributes.record(&mut FieldRecorder(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((attributes.metadata().name(), parent, fields));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            values.record(&mut FieldRecorder(
+                &mut spans[span.into_u64() as usize - 1].2,
+            ));
+        }
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+
+        fn event(&self, _: &tracing::Event<'_>) {}
+
+        fn enter(&self, span: &tracing::span::Id) {
+            self.entered
+                .lock()
+                .unwrap()
+                .push(span.into_u64() as usize - 1);
+        }
+
+        fn exit(&self, _: &tracing::span::Id) {
+            self.entered.lock().unwrap().pop();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn it_traces_a_run() {
+        let raw_definition = std::fs::read_to_string("examples/filter.json").unwrap();
+        let parsed: PipelineDefinition = serde_json::from_str(&raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+        let recorder = SpanRecorder::default();
+
+        // The subscriber is only the default of the current thread, so the sources are loaded in that thread too.
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let result = pool.install(|| {
+            tracing::subscriber::with_default(recorder.clone(), || engine.run(&Default::default()))
+        });
+
+        assert!(result["filtered"].is_ok());
+        let spans = recorder.spans.lock().unwrap();
+        let traced = spans
+            .iter()
+            .map(|(name, parent, fields)| {
+                let parent = parent.map_or("", |parent| spans[parent].0);
+                let fields = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}={}", field, value))
+                    .collect::<Vec<_>>();
+                format!("{} < {}: {}", name, parent, fields.join(" "))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            traced,
+            vec![
+                "pipeline_run < : sources=1 transformations=1",
+                "load_source < pipeline_run: cached=false rows=4 source=salaries",
+                "load_file < load_source: path=./examples/salaries.csv rows=4",
+                "transformation < pipeline_run: cached=false rows=1 transformation=filtered",
+                "operation < transformation: operation=Filter rows_in=4 rows_out=1",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reports_on_a_run() {
+        let raw_definition = std::fs::read_to_string("examples/filter.json").unwrap();
+        let parsed: PipelineDefinition = serde_json::from_str(&raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+
+        let (result, report) = engine.run_with_report(&Default::default());
+
+        assert_eq!(result["filtered"].as_ref().unwrap()[0].len(), 1);
+        assert_eq!(report.sources["salaries"].rows, 4);
+        assert!(report.sources["salaries"].bytes > 0);
+        let filtered = &report.transformations["filtered"];
+        assert_eq!(filtered.rows, 1);
+        assert_eq!(filtered.operations.len(), 1);
+        assert_eq!(filtered.operations[0].operation, "Filter");
+        assert_eq!(filtered.operations[0].selectivity, Some(0.25));
+        assert!(report.peak_memory_bytes > report.sources["salaries"].bytes);
+        assert!(report.wall_clock_ms > 0.0);
+        assert_eq!(report.errors, 0);
+        assert!(engine.observers.is_empty());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(
+            json["transformations"]["filtered"]["operations"][0]["rows_in"],
+            4
+        );
+    }
+
+    #[test]
+    fn it_runs_selected_transformations() {
+        let raw_definition = r#"{
+            "sources": {
+                "people": {
+                    "schema": [{ "column_name": "id", "data_type": "Integer", "required": true }],
+                    "source": { "type": "Inline", "rows": [{ "id": 1 }, { "id": 2 }] }
+                },
+                "missing": {
+                    "schema": [{ "column_name": "id", "data_type": "Integer", "required": true }],
+                    "source": { "type": "File", "format": "Csv", "path": "does/not/exist.csv" }
+                }
+            },
+            "transformations": {
+                "first": {
+                    "sources": ["people"],
+                    "operations": [{ "type": "Filter", "predicate": "id == 1" }]
+                },
+                "broken": {
+                    "sources": ["missing"],
+                    "operations": [{ "type": "Filter", "predicate": "id == 1" }]
+                }
+            }
+        }"#;
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+
+        let result = engine.run_selected(&["first", "unknown"], &Default::default());
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result.get("first").unwrap().as_ref().unwrap()[0],
+            vec![HashMap::from([(
+                String::from("id"),
+                ColumnValue::Integer(1)
+            )])]
+        );
+        assert!(result.get("unknown").unwrap().as_ref().is_err_and(|err| {
+            matches!(err, RustyPipesError::TransformationError(message) if message == "Unknown transformation unknown")
+        }));
+        assert!(engine.run(&Default::default())["broken"].is_err());
+    }
+
+    #[test]
+    fn it_writes_to_a_destination() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("destination.sqlite");
+        let raw_definition = format!(
+            r#"{{
+                "sources": {{
+                    "salaries": {{
+                        "schema": [
+                            {{ "column_name": "first_name", "data_type": "String", "required": true }},
+                            {{ "column_name": "salary", "data_type": "Decimal", "required": true }}
+                        ],
+                        "source": {{ "type": "File", "format": "Csv", "path": "./examples/salaries.csv" }}
+                    }}
+                }},
+                "transformations": {{
+                    "filtered": {{
+                        "sources": ["salaries"],
+                        "operations": [{{ "type": "Filter", "predicate": "first_name == Jen" }}],
+                        "destination": {{ "type": "Sqlite", "path": {:?}, "table": "filtered" }}
+                    }}
+                }}
+            }}"#,
+            path
+        );
+        let parsed: PipelineDefinition = serde_json::from_str(&raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+        let result = engine.run(&Default::default());
+        assert!(result.get("filtered").unwrap().is_ok());
+
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let salary: f64 = connection
+            .query_row(
+                "SELECT salary FROM filtered WHERE first_name = 'Jen'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(salary, 19319.0);
+    }
+
+    #[test]
+    fn it_runs_a_pipeline_on_inline_and_registered_sources() {
+        let raw_definition = r#"{
+            "sources": {
+                "names": {
+                    "schema": [
+                        { "column_name": "id", "data_type": "Integer", "required": true },
+                        { "column_name": "name", "data_type": "String", "required": true }
+                    ],
+                    "source": { "type": "Inline", "rows": [{ "id": 1, "name": "Jen" }, { "id": 2, "name": "Dave" }] }
+                }
+            },
+            "transformations": {
+                "joined": {
+                    "sources": ["names", "ages"],
+                    "operations": [{ "type": "InnerJoin", "on": "id = id" }]
+                }
+            }
+        }"#;
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+        engine.register_dataframe(
+            "ages",
+            vec![HashMap::from([
+                (String::from("id"), ColumnValue::Integer(2)),
+                (String::from("age"), ColumnValue::Integer(40)),
+            ])],
+        );
+
+        let result = engine.run(&Default::default());
+
+        assert_eq!(
+            result.get("joined").unwrap().as_ref().unwrap()[0],
+            vec![HashMap::from([
+                (String::from("id"), ColumnValue::Integer(2)),
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("Dave"))
+                ),
+                (String::from("age"), ColumnValue::Integer(40)),
+            ])]
+        );
+    }
+
+    #[test]
+    fn it_reports_unknown_sources() {
+        let raw_definition = r#"{
+            "sources": {},
+            "transformations": {
+                "filtered": {
+                    "sources": ["missing"],
+                    "operations": [{ "type": "Filter", "predicate": "id > 1" }]
+                }
+            }
+        }"#;
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+
+        let result = engine.run(&Default::default());
+
+        assert!(result
+            .get("filtered")
+            .unwrap()
+            .as_ref()
+            .is_err_and(|err| err.to_string().contains("Unknown source missing")));
+    }
+
+    struct Constant(Dataframe);
+
+    impl Loader for Constant {
+        fn load(&self) -> RustyPipesResult<Dataframe> {
+            Ok(self.0.clone())
+        }
+    }
+
+    struct Rename {
+        from: String,
+        to: String,
+    }
+
+    impl Transformation for Rename {
+        fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
+            let renamed = dfs[0]
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|(key, value)| {
+                            let key = if *key == self.from { &self.to } else { key };
+                            (key.clone(), value.clone())
+                        })
+                        .collect()
+                })
+                .collect();
+            Ok(vec![renamed])
+        }
+    }
+
+    #[test]
+    fn it_runs_custom_loaders_and_transformations() {
+        let raw_definition = r#"{
+            "sources": {
+                "constant": {
+                    "schema": [],
+                    "source": { "type": "Custom", "name": "constant", "config": { "value": 42 } }
+                }
+            },
+            "transformations": {
+                "renamed": {
+                    "sources": ["constant"],
+                    "operations": [
+                        { "type": "Custom", "name": "rename", "config": { "from": "value", "to": ":target" } }
+                    ]
+                }
+            }
+        }"#;
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+        engine.register_loader(
+            "constant",
+            |config: &serde_json::Value, _: &[ColumnDefinition], _: &Context| {
+                let value = config["value"].as_i64().unwrap();
+                Ok(Box::new(Constant(vec![HashMap::from([(
+                    String::from("value"),
+                    ColumnValue::Integer(value),
+                )])])) as Box<dyn Loader>)
+            },
+        );
+        engine.register_transformation(
+            "rename",
+            |config: &serde_json::Value, context: &Context| {
+                let target = config["to"].as_str().unwrap();
+                Ok(Box::new(Rename {
+                    from: config["from"].as_str().unwrap().to_owned(),
+                    to: context.resolve(target).unwrap().to_owned(),
+                }) as Box<dyn Transformation>)
+            },
+        );
+
+        let result = engine.run(&Context::new(HashMap::from([(
+            String::from("target"),
+            String::from("answer"),
+        )])));
+
+        assert_eq!(
+            result.get("renamed").unwrap().as_ref().unwrap()[0],
+            vec![HashMap::from([(
+                String::from("answer"),
+                ColumnValue::Integer(42)
+            )])]
+        );
+    }
+
+    #[test]
+    fn it_reports_unregistered_custom_loaders() {
+        let raw_definition = r#"{
+            "sources": {
+                "custom": { "schema": [], "source": { "type": "Custom", "name": "missing" } }
+            },
+            "transformations": {
+                "filtered": {
+                    "sources": ["custom"],
+                    "operations": [{ "type": "Filter", "predicate": "id > 1" }]
+                }
+            }
+        }"#;
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+
+        let result = engine.run(&Default::default());
+
+        assert!(result
+            .get("filtered")
+            .unwrap()
+            .as_ref()
+            .is_err_and(|err| err.to_string().contains("Unknown loader missing")));
+    }
+
+    #[test]
+    fn it_runs_an_optimized_plan() {
+        let raw_definition = r#"{
+            "sources": {
+                "people": {
+                    "schema": [
+                        { "column_name": "id", "data_type": "Integer", "required": true },
+                        { "column_name": "name", "data_type": "String", "required": true }
+                    ],
+                    "source": { "type": "Inline", "rows": [{ "id": 1, "name": "Jen" }, { "id": 2, "name": "Dave" }] }
+                },
+                "salaries": {
+                    "schema": [
+                        { "column_name": "person_id", "data_type": "Integer", "required": true },
+                        { "column_name": "salary", "data_type": "Decimal", "required": true }
+                    ],
+                    "source": { "type": "Inline", "rows": [{ "person_id": 1, "salary": 50 }, { "person_id": 2, "salary": 150 }] }
+                },
+                "unused": {
+                    "schema": [],
+                    "source": { "type": "File", "format": "Csv", "path": "./does/not/exist.csv" }
+                }
+            },
+            "transformations": {
+                "well_paid": {
+                    "sources": ["people", "salaries"],
+                    "operations": [
+                        { "type": "InnerJoin", "on": "id = person_id" },
+                        { "type": "Filter", "predicate": "salary > 100" },
+                        { "type": "Filter", "predicate": "name != Roy" },
+                        { "type": "Select", "columns": ["name", "salary"] }
+                    ]
+                }
+            }
+        }"#;
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+
+        let plan = engine.plan();
+        let result = engine.run(&Default::default());
+
+        assert!(!plan.sources.contains_key("unused"));
+        assert_eq!(
+            result.get("well_paid").unwrap().as_ref().unwrap()[0],
+            vec![HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("Dave"))
+                ),
+                (String::from("salary"), ColumnValue::Decimal(150.0)),
+            ])]
+        );
+    }
+
+    #[test]
+    fn it_loads_only_the_selected_columns_of_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("people.csv");
+        std::fs::write(&path, "name,amount\nJen,not a number\nDave,1\n").unwrap();
+        let raw_definition = format!(
+            r#"{{
+                "sources": {{
+                    "people": {{
+                        "schema": [
+                            {{ "column_name": "name", "data_type": "String", "required": true }},
+                            {{ "column_name": "amount", "data_type": "Integer", "required": true }}
+                        ],
+                        "source": {{ "type": "File", "format": "Csv", "path": {:?} }}
+                    }}
+                }},
+                "transformations": {{
+                    "names": {{
+                        "sources": ["people"],
+                        "operations": [{{ "type": "Select", "columns": ["name"] }}]
+                    }}
+                }}
+            }}"#,
+            path
+        );
+        let parsed: PipelineDefinition = serde_json::from_str(&raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+
+        let plan = engine.plan();
+        let result = engine.run(&Default::default());
+
+        assert_eq!(
+            plan.sources.get("people"),
+            Some(&Some(BTreeSet::from([String::from("name")])))
+        );
+        assert_eq!(
+            result.get("names").unwrap().as_ref().unwrap()[0],
+            vec![
+                HashMap::from([(
+                    String::from("name"),
+                    ColumnValue::String(String::from("Jen"))
+                )]),
+                HashMap::from([(
+                    String::from("name"),
+                    ColumnValue::String(String::from("Dave"))
+                )]),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_streams_sources_through_row_wise_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("streaming.csv");
+        let mut csv = String::from("person_id,amount\n");
+        for i in 0..1000 {
+            csv.push_str(&format!("{},{}\n", i % 2 + 1, i));
+        }
+        std::fs::write(&path, csv).unwrap();
+        let raw_definition = format!(
+            r#"{{
+                "sources": {{
+                    "events": {{
+                        "schema": [
+                            {{ "column_name": "person_id", "data_type": "Integer", "required": true }},
+                            {{ "column_name": "amount", "data_type": "Integer", "required": true }}
+                        ],
+                        "source": {{ "type": "File", "format": "Csv", "path": {:?} }}
+                    }},
+                    "people": {{
+                        "schema": [
+                            {{ "column_name": "id", "data_type": "Integer", "required": true }},
+                            {{ "column_name": "name", "data_type": "String", "required": true }}
+                        ],
+                        "source": {{ "type": "Inline", "rows": [{{ "id": 1, "name": "Jen" }}, {{ "id": 2, "name": "Dave" }}] }}
+                    }}
+                }},
+                "transformations": {{
+                    "large": {{
+                        "sources": ["events", "people"],
+                        "operations": [
+                            {{ "type": "InnerJoin", "on": "person_id = id" }},
+                            {{ "type": "Filter", "predicate": "amount >= 990" }},
+                            {{ "type": "Select", "columns": ["name", "amount"] }}
+                        ]
+                    }},
+                    "doubled": {{
+                        "sources": ["events"],
+                        "operations": [
+                            {{ "type": "Script", "code": "row.amount = row.amount * 2; row" }},
+                            {{ "type": "Filter", "predicate": "amount < 10" }}
+                        ]
+                    }}
+                }}
+            }}"#,
+            path
+        );
+        let parse = || serde_json::from_str::<PipelineDefinition>(&raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parse());
+        let mut streaming_engine = Engine::from_definition(parse()).with_streaming(7);
+
+        let expected = engine.run(&Default::default());
+        let (result, report) = streaming_engine.run_with_report(&Default::default());
+
+        assert_eq!(
+            streaming_engine.plan().streamed_sources(),
+            BTreeSet::from([String::from("events")])
+        );
+        // The filter is pushed below the join, which counts the filtered events and, once, the people.
+        let operations = &report.transformations["large"].operations;
+        assert_eq!(operations[1].operation, "InnerJoin");
+        assert_eq!(operations[1].rows_in, 12);
+        let large = result.get("large").unwrap().as_ref().unwrap();
+        assert_eq!(large[0].len(), 10);
+        assert_eq!(large, expected.get("large").unwrap().as_ref().unwrap());
+        let doubled = result.get("doubled").unwrap().as_ref().unwrap();
+        assert_eq!(doubled[0].len(), 5);
+        assert_eq!(doubled, expected.get("doubled").unwrap().as_ref().unwrap());
+    }
+
+    #[test]
+    fn it_spills_joins_exceeding_the_memory_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("salaries.csv");
+        let salaries = (0..300)
+            .map(|i| format!("{},{}\n", i % 3, i))
+            .collect::<String>();
+        std::fs::write(&path, format!("person_id,salary\n{}", salaries)).unwrap();
+        let raw_definition = format!(
+            r#"{{
+                "sources": {{
+                    "people": {{
+                        "schema": [
+                            {{ "column_name": "id", "data_type": "Integer", "required": true }},
+                            {{ "column_name": "name", "data_type": "String", "required": true }}
+                        ],
+                        "source": {{ "type": "Inline", "rows": [{{ "id": 1, "name": "Jen" }}, {{ "id": 2, "name": "Dave" }}] }}
+                    }},
+                    "salaries": {{
+                        "schema": [
+                            {{ "column_name": "person_id", "data_type": "Integer", "required": true }},
+                            {{ "column_name": "salary", "data_type": "Decimal", "required": true }}
+                        ],
+                        "source": {{ "type": "File", "format": "Csv", "path": {:?} }}
+                    }}
+                }},
+                "transformations": {{
+                    "joined": {{
+                        "sources": ["people", "salaries"],
+                        "operations": [{{ "type": "InnerJoin", "on": "id = person_id" }}]
+                    }}
+                }}
+            }}"#,
+            path
+        );
+        let parse = || serde_json::from_str::<PipelineDefinition>(&raw_definition).unwrap();
+        let temp = tempfile::tempdir().unwrap();
+        let directory = temp.path();
+        let mut engine = Engine::from_definition(parse());
+        let expected = engine.run(&Default::default());
+
+        for streaming in [false, true] {
+            let mut spilling_engine = Engine::from_definition(parse())
+                .with_memory_budget(MemoryBudget::new(1_000).with_directory(directory));
+            if streaming {
+                spilling_engine = spilling_engine.with_streaming(7);
+            }
+
+            let (result, report) = spilling_engine.run_with_report(&Default::default());
+
+            assert_eq!(
+                spilling_engine.plan().spilled_sources(),
+                BTreeSet::from([String::from("salaries")])
+            );
+            assert_eq!(report.sources["salaries"].rows, 300);
+            // The join counts the spilled salaries along with the people.
+            let operations = &report.transformations["joined"].operations;
+            assert_eq!(operations[0].rows_in, 302);
+            let joined = result.get("joined").unwrap().as_ref().unwrap();
+            assert_eq!(joined[0].len(), 200);
+            assert_eq!(joined, expected.get("joined").unwrap().as_ref().unwrap());
+            assert_eq!(std::fs::read_dir(directory).unwrap().count(), 0);
+        }
+    }
+
+    #[test]
+    fn it_reuses_cached_outputs_of_unchanged_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ids.csv");
+        std::fs::write(&path, "id\n1\n2\n").unwrap();
+        let raw_definition = format!(
+            r#"{{
+                "sources": {{
+                    "ids": {{
+                        "schema": [{{ "column_name": "id", "data_type": "Integer", "required": true }}],
+                        "source": {{ "type": "File", "format": "Csv", "path": {:?} }}
+                    }}
+                }},
+                "transformations": {{
+                    "small": {{
+                        "sources": ["ids"],
+                        "operations": [{{ "type": "Filter", "predicate": "id < 4" }}]
+                    }}
+                }}
+            }}"#,
+            path
+        );
+        let parse = || serde_json::from_str::<PipelineDefinition>(&raw_definition).unwrap();
+        let cache = ResultCache::new(dir.path().join("cache"));
+        let ids = |engine: &mut Engine| {
+            let result = engine.run(&Default::default());
+            let output = result.get("small").unwrap().as_ref().unwrap();
+            output[0]
+                .iter()
+                .map(|row| row["id"].clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut engine = Engine::from_definition(parse()).with_cache(cache.clone());
+        assert_eq!(
+            ids(&mut engine),
+            vec![ColumnValue::Integer(1), ColumnValue::Integer(2)]
+        );
+
+        // Rewrite the file without changing its size or modification time, which the cache tells apart by its contents.
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, "id\n3\n4\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(ids(&mut engine), vec![ColumnValue::Integer(3)]);
+
+        std::fs::write(&path, "id\n3\n4\n5\n").unwrap();
+        assert_eq!(ids(&mut engine), vec![ColumnValue::Integer(3)]);
+
+        cache.clear().unwrap();
+        assert!(!cache.directory().exists());
+    }
+
+    /// A loader and transformation that wait until the run is cancelled or times out.
+    struct Stall(CancellationToken);
+
+    impl Loader for Stall {
+        fn load(&self) -> RustyPipesResult<Dataframe> {
+            self.0.sleep(Duration::from_secs(60))?;
+            Ok(vec![])
+        }
+    }
+
+    impl Transformation for Stall {
+        fn transform(&self, _: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
+            self.0.sleep(Duration::from_secs(60))?;
+            Ok(vec![vec![]])
+        }
+    }
+
+    fn stalling_engine(raw_definition: &str) -> Engine {
+        let parsed: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let mut engine = Engine::from_definition(parsed);
+        engine.register_loader(
+            "stall",
+            |_: &serde_json::Value, _: &[ColumnDefinition], context: &Context| {
+                Ok(Box::new(Stall(context.cancellation().clone())) as Box<dyn Loader>)
+            },
+        );
+        engine.register_transformation("stall", |_: &serde_json::Value, context: &Context| {
+            Ok(Box::new(Stall(context.cancellation().clone())) as Box<dyn Transformation>)
+        });
+        engine
+    }
+
+    #[test]
+    fn it_times_out_sources_and_transformations() {
+        let mut engine = stalling_engine(
+            r#"{
+                "sources": {
+                    "stalled": {
+                        "schema": [],
+                        "source": { "type": "Custom", "name": "stall" },
+                        "timeout_ms": 20
+                    },
+                    "ids": {
+                        "schema": [{ "column_name": "id", "data_type": "Integer", "required": true }],
+                        "source": { "type": "Inline", "rows": [{ "id": 1 }] }
+                    }
+                },
+                "transformations": {
+                    "loading": {
+                        "sources": ["stalled"],
+                        "operations": [{ "type": "Filter", "predicate": "id > 0" }]
+                    },
+                    "transforming": {
+                        "sources": ["ids"],
+                        "operations": [{ "type": "Custom", "name": "stall" }],
+                        "timeout_ms": 20
+                    }
+                }
+            }"#,
+        );
+
+        let result = engine.run(&Default::default());
+
+        assert!(matches!(
+            &result["loading"],
+            Err(RustyPipesError::TimedOut(message)) if message == "Source stalled timed out after 20ms"
+        ));
+        assert!(matches!(
+            &result["transforming"],
+            Err(RustyPipesError::TimedOut(message))
+                if message == "Transformation transforming timed out after 20ms"
+        ));
+    }
+
+    #[test]
+    fn it_cancels_a_run() {
+        let mut engine = stalling_engine(
+            r#"{
+                "sources": {
+                    "ids": {
+                        "schema": [{ "column_name": "id", "data_type": "Integer", "required": true }],
+                        "source": { "type": "Inline", "rows": [{ "id": 1 }] }
+                    }
+                },
+                "transformations": {
+                    "stalled": {
+                        "sources": ["ids"],
+                        "operations": [{ "type": "Custom", "name": "stall" }]
+                    }
+                }
+            }"#,
+        );
+        let cancellation = CancellationToken::new();
+        let context = Context::default().with_cancellation(cancellation.clone());
+
+        let canceller = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            cancellation.cancel();
+        });
+        let result = engine.run(&context);
+        canceller.join().unwrap();
+
+        assert!(matches!(result["stalled"], Err(RustyPipesError::Cancelled)));
+        assert!(matches!(
+            engine.run(&context)["stalled"],
+            Err(RustyPipesError::Cancelled)
+        ));
+    }
 }
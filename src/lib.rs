@@ -2,11 +2,38 @@
 /// the operations defined in such a pipeline.
 pub mod core;
 
+/// A local cache of loaded sources and transformation outputs, keyed by their fingerprints
+pub mod cache;
+
+/// Transparent decompression of files
+pub mod compression;
+
 /// Engine implementation that can drive a transformation pipeline
 pub mod engine;
 
 /// Loader implementations
 pub mod loaders;
 
+/// Observers of the events that occur while a pipeline is run, and a reporter of the progress of a run
+pub mod observer;
+
+/// Logical plans for pipelines, and an optimizer for these plans
+pub mod plan;
+
+/// Conversions between data frames and Arrow record batches, for interoperability with Arrow-based tooling
+pub mod record_batch;
+
+/// Serializable reports of the metrics of pipeline runs
+pub mod report;
+
+/// Sink implementations
+pub mod sinks;
+
+/// External-memory algorithms, which spill to disk when the data they hold exceeds a memory budget
+pub mod spill;
+
+/// Spans of the `tracing` crate when the "tracing" feature is enabled, and stand-ins that do nothing otherwise
+mod trace;
+
 /// Transformation implementations
 pub mod transformations;
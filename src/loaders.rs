@@ -1,2 +1,11 @@
 pub mod file;
+pub mod http;
+pub mod inline;
+mod json;
+pub mod postgres;
+pub mod sqlite;
 pub use file::FileLoader;
+pub use http::HttpLoader;
+pub use inline::InlineLoader;
+pub use postgres::PostgresLoader;
+pub use sqlite::SqliteLoader;
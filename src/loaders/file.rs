@@ -1,64 +1,1088 @@
-use std::{collections::HashMap, path::Path};
-
-use crate::core::{
-    dataframe::{ColumnValue, Dataframe, Row},
-    definitions::{ColumnDefinition, DataType, Format},
-    error::RustyPipesError,
-    loader::Loader,
-    result::RustyPipesResult,
+use std::{
+    collections::{BTreeSet, HashMap},
+    ffi::OsStr,
+    fs::File,
+    io::{Read, Seek},
+    path::{Path, PathBuf},
 };
 
+use arrow::{
+    array::RecordBatch,
+    error::ArrowError,
+    ipc::reader::{FileReader, StreamReader},
+};
+use csv::StringRecord;
+use encoding_rs::Encoding;
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ProjectionMask};
+use rayon::prelude::*;
+
+use crate::{
+    compression,
+    core::{
+        cancellation::CancellationToken,
+        context::Context,
+        dataframe::{ColumnValue, Dataframe, Row},
+        definitions::{ColumnDefinition, Compression, CsvDialect, DataType, Format, Trim},
+        error::RustyPipesError,
+        loader::{Batches, Loader},
+        result::RustyPipesResult,
+    },
+    record_batch::from_record_batch,
+    trace::{info_span, Span},
+};
+
+/// A CSV reader over a decompressed file, decoded into UTF-8.
+type CsvReader = csv::Reader<DecodeReaderBytes<Box<dyn Read + Send>, Vec<u8>>>;
+
+fn dialect_byte(c: char, option: &str) -> RustyPipesResult<u8> {
+    u8::try_from(c).ok().filter(u8::is_ascii).ok_or_else(|| {
+        RustyPipesError::LoaderError(format!(
+            "The {} character must be a single ASCII character, got {}",
+            option, c
+        ))
+    })
+}
+
+fn csv_reader_builder(dialect: &CsvDialect) -> RustyPipesResult<csv::ReaderBuilder> {
+    let mut builder = csv::ReaderBuilder::new();
+    builder
+        .delimiter(dialect_byte(dialect.delimiter, "delimiter")?)
+        .quote(dialect_byte(dialect.quote, "quote")?)
+        .escape(
+            dialect
+                .escape
+                .map(|c| dialect_byte(c, "escape"))
+                .transpose()?,
+        )
+        .double_quote(dialect.escape.is_none())
+        .comment(
+            dialect
+                .comment
+                .map(|c| dialect_byte(c, "comment"))
+                .transpose()?,
+        )
+        .has_headers(dialect.has_headers)
+        .flexible(dialect.flexible)
+        .trim(match dialect.trim {
+            Trim::None => csv::Trim::None,
+            Trim::Headers => csv::Trim::Headers,
+            Trim::Fields => csv::Trim::Fields,
+            Trim::All => csv::Trim::All,
+        });
+    Ok(builder)
+}
+
+fn parse_value(value: &str, definition: &ColumnDefinition) -> RustyPipesResult<ColumnValue> {
+    let parsed = match definition.data_type {
+        DataType::Integer => ColumnValue::Integer(
+            value
+                .parse::<i64>()
+                .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
+        ),
+        DataType::Decimal => ColumnValue::Decimal(
+            value
+                .parse::<f64>()
+                .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
+        ),
+        DataType::String => ColumnValue::String(value.to_owned()),
+    };
+    Ok(parsed)
+}
+
+fn is_glob_pattern(path: &Path) -> bool {
+    path.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// The extensions of files of the given format.
+fn format_extensions(format: &Format) -> &'static [&'static str] {
+    match format {
+        Format::Csv => &["csv", "tsv"],
+        Format::Parquet => &["parquet"],
+        Format::ArrowIpc => &["arrow", "arrows", "feather", "ipc"],
+    }
+}
+
+/// Whether the extension of a file, disregarding the extension of its compression (e.g., ".gz" in "a.csv.gz"), is an
+/// extension of files of the given format.
+fn has_format_extension(path: &Path, format: &Format) -> bool {
+    let uncompressed = match compression::detect(path) {
+        Compression::None => path,
+        _ => Path::new(path.file_stem().unwrap_or_default()),
+    };
+    uncompressed
+        .extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|extension| {
+            format_extensions(format)
+                .iter()
+                .any(|candidate| extension.eq_ignore_ascii_case(candidate))
+        })
+}
+
+/// Resolve a path, which may refer to a single file, a directory or a glob pattern, into the files it refers to. Of a
+/// directory, only the files with an extension of the given format (optionally followed by the extension of a
+/// compression) are included, so that other files in the directory (e.g., a README) are skipped. The files are
+/// returned in lexicographical order so that loading them yields a deterministic ordering of rows.
+pub(crate) fn resolve_paths(path: &Path, format: &Format) -> RustyPipesResult<Vec<PathBuf>> {
+    let mut paths = if path.is_dir() {
+        std::fs::read_dir(path)
+            .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?
+            .map(|entry| {
+                entry
+                    .map(|entry| entry.path())
+                    .map_err(|e| RustyPipesError::LoaderError(e.to_string()))
+            })
+            .filter(|entry| {
+                entry.as_ref().map_or(true, |path| {
+                    path.is_file() && has_format_extension(path, format)
+                })
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?
+    } else if is_glob_pattern(path) {
+        glob::glob(&path.to_string_lossy())
+            .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?
+            .map(|entry| entry.map_err(|e| RustyPipesError::LoaderError(e.to_string())))
+            .filter(|entry| entry.as_ref().map_or(true, |path| path.is_file()))
+            .collect::<RustyPipesResult<Vec<_>>>()?
+    } else {
+        return Ok(vec![path.to_path_buf()]);
+    };
+
+    if paths.is_empty() {
+        return Err(RustyPipesError::LoaderError(format!(
+            "No files found for path {}",
+            path.display()
+        )));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[derive(Clone)]
 pub struct FileLoader<'a> {
     path: &'a Path,
     format: &'a Format,
+    dialect: &'a CsvDialect,
     schema: &'a Vec<ColumnDefinition>,
+    compression: Option<Compression>,
+    filename_column: Option<&'a str>,
+    parallel: bool,
+    projection: Option<&'a BTreeSet<String>>,
+    cancellation: CancellationToken,
 }
 
 impl<'a> FileLoader<'a> {
-    /// Construct a new file loader for the given path, format, and using the specified schema. This is a lazy
-    /// operation; until the "load" method is run, no work will be performed.
-    pub fn new(path: &'a Path, format: &'a Format, schema: &'a Vec<ColumnDefinition>) -> Self {
+    /// Construct a new file loader for the given path, format, and using the specified schema. The dialect is used to
+    /// parse CSV files. Loading stops with an error once the run is cancelled or times out; this is checked for each
+    /// CSV record and each Parquet or Arrow record batch. This is a lazy operation; until the "load" method is run, no
+    /// work will be performed.
+    pub fn new(
+        path: &'a Path,
+        format: &'a Format,
+        dialect: &'a CsvDialect,
+        schema: &'a Vec<ColumnDefinition>,
+        context: &Context,
+    ) -> Self {
         FileLoader {
             path,
             format,
+            dialect,
             schema,
+            compression: None,
+            filename_column: None,
+            parallel: false,
+            projection: None,
+            cancellation: context.cancellation().clone(),
+        }
+    }
+
+    /// Decompress the file(s) using the given compression. When not set, the compression is detected from the file
+    /// extension.
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Record the name of the file that each row originates from (e.g., "2024-01.csv") in a column with the given
+    /// name.
+    pub fn with_filename_column(mut self, filename_column: Option<&'a str>) -> Self {
+        self.filename_column = filename_column;
+        self
+    }
+
+    /// Load the files matched by a glob pattern or directory in parallel. The ordering of the resulting rows is the
+    /// same as when loading sequentially.
+    pub fn with_parallelism(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Load only the columns with the given names; the other columns of the schema are neither read nor validated.
+    /// When not set, all columns of the schema are loaded.
+    pub fn with_projection(mut self, projection: Option<&'a BTreeSet<String>>) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    fn is_projected(&self, column_name: &str) -> bool {
+        self.projection
+            .is_none_or(|projection| projection.contains(column_name))
+    }
+
+    fn projected_schema(&self) -> Vec<ColumnDefinition> {
+        self.schema
+            .iter()
+            .filter(|definition| self.is_projected(&definition.column_name))
+            .cloned()
+            .collect()
+    }
+
+    fn add_filename_column(&self, df: &mut Dataframe, path: &Path) {
+        if let Some(column_name) = self
+            .filename_column
+            .filter(|column_name| self.is_projected(column_name))
+        {
+            let filename = path.file_name().unwrap_or_default().to_string_lossy();
+            for row in df.iter_mut() {
+                row.insert(
+                    column_name.to_owned(),
+                    ColumnValue::String(filename.to_string()),
+                );
+            }
+        }
+    }
+
+    /// Parquet and Arrow IPC files compress their contents internally, and are read by seeking within the file, so
+    /// they cannot be decompressed as a stream.
+    fn check_uncompressed(&self, path: &Path) -> RustyPipesResult<()> {
+        match self
+            .compression
+            .unwrap_or_else(|| compression::detect(path))
+        {
+            Compression::None => Ok(()),
+            compression => Err(RustyPipesError::LoaderError(format!(
+                "Cannot decompress {:?} file {} using {:?}; only CSV files can be compressed",
+                self.format,
+                path.display(),
+                compression
+            ))),
         }
     }
 
-    fn load_csv(&self) -> RustyPipesResult<Dataframe> {
-        let mut reader = csv::Reader::from_path(self.path)
+    fn load_file(&self, path: &Path) -> RustyPipesResult<Dataframe> {
+        let span = info_span!("load_file", path = %path.display(), rows = tracing::field::Empty);
+        let _entered = span.enter();
+        let mut df = match self.format {
+            Format::Csv => self.load_csv(path)?,
+            Format::Parquet => self.load_parquet(path)?,
+            Format::ArrowIpc => self.load_arrow_ipc(path)?,
+        };
+        self.add_filename_column(&mut df, path);
+        span.record("rows", df.len());
+        Ok(df)
+    }
+
+    /// Yield the rows of a single file in batches of at most batch_size rows.
+    fn file_batches(self, path: PathBuf, batch_size: usize) -> RustyPipesResult<Batches<'a>> {
+        let batches: Batches<'a> = match self.format {
+            Format::Csv => {
+                let mut records = self.csv_reader(&path)?.into_records();
+                let loader = self.clone();
+                Box::new(std::iter::from_fn(move || {
+                    let batch = records
+                        .by_ref()
+                        .take(batch_size)
+                        .map(|record| loader.parse_record(record))
+                        .collect::<RustyPipesResult<Dataframe>>();
+                    match batch {
+                        Ok(batch) if batch.is_empty() => None,
+                        batch => Some(batch),
+                    }
+                }))
+            }
+            Format::Parquet => {
+                let schema = self.projected_schema();
+                let builder = self.parquet_reader_builder(&path)?;
+                let mask = parquet_projection(&builder, &path, &schema)?;
+                let reader = builder
+                    .with_projection(mask)
+                    .with_batch_size(batch_size)
+                    .build()
+                    .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                Box::new(reader.map(move |batch| {
+                    let batch = batch.map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                    from_record_batch(&batch, &schema)
+                        .map_err(|e| RustyPipesError::LoaderError(e.to_string()))
+                }))
+            }
+            Format::ArrowIpc => {
+                self.check_uncompressed(&path)?;
+                let schema = self.projected_schema();
+                Box::new(arrow_ipc_batches(&path)?.map(move |batch| {
+                    let batch = batch.map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                    from_record_batch(&batch, &schema)
+                        .map_err(|e| RustyPipesError::LoaderError(e.to_string()))
+                }))
+            }
+        };
+        Ok(Box::new(batches.map(move |batch| {
+            batch.map(|mut df| {
+                self.add_filename_column(&mut df, &path);
+                df
+            })
+        })))
+    }
+
+    fn csv_reader(&self, path: &Path) -> RustyPipesResult<CsvReader> {
+        let encoding = self
+            .dialect
+            .encoding
+            .as_ref()
+            .map(|label| {
+                Encoding::for_label(label.as_bytes()).ok_or_else(|| {
+                    RustyPipesError::LoaderError(format!("Unknown encoding {}", label))
+                })
+            })
+            .transpose()?;
+        let file = compression::open(path, self.compression)
             .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
-        let mut df = vec![];
-        for row_raw in reader.records() {
-            let mut row: Row = HashMap::new();
-            let result = row_raw.map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
-            for (i, definition) in self.schema.iter().enumerate() {
-                let value = &result[i];
-                let parsed_value = match definition.data_type {
-                    DataType::Integer => ColumnValue::Integer(
-                        value
-                            .parse::<i64>()
-                            .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
-                    ),
-                    DataType::Decimal => ColumnValue::Decimal(
-                        value
-                            .parse::<f64>()
-                            .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
-                    ),
-                    DataType::String => ColumnValue::String(value.to_owned()),
-                };
-                row.insert(definition.column_name.clone(), parsed_value);
+        let decoded = DecodeReaderBytesBuilder::new()
+            .encoding(encoding)
+            .build(file);
+        Ok(csv_reader_builder(self.dialect)?.from_reader(decoded))
+    }
+
+    fn parse_record(&self, record: csv::Result<StringRecord>) -> RustyPipesResult<Row> {
+        self.cancellation.check()?;
+        let mut row: Row = HashMap::new();
+        let result = record.map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+        for (i, definition) in self.schema.iter().enumerate() {
+            if !self.is_projected(&definition.column_name) {
+                continue;
             }
-            df.push(row);
+            let parsed_value = match result.get(i) {
+                Some(value) => parse_value(value, definition)?,
+                None if definition.required => {
+                    return Err(RustyPipesError::LoaderError(format!(
+                        "Missing value for required column {}",
+                        definition.column_name
+                    )))
+                }
+                None => ColumnValue::None,
+            };
+            row.insert(definition.column_name.clone(), parsed_value);
+        }
+        Ok(row)
+    }
+
+    fn load_csv(&self, path: &Path) -> RustyPipesResult<Dataframe> {
+        self.csv_reader(path)?
+            .into_records()
+            .map(|record| self.parse_record(record))
+            .collect()
+    }
+
+    fn parquet_reader_builder(
+        &self,
+        path: &Path,
+    ) -> RustyPipesResult<ParquetRecordBatchReaderBuilder<File>> {
+        self.check_uncompressed(path)?;
+        let file = File::open(path).map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+        ParquetRecordBatchReaderBuilder::try_new(file)
+            .map_err(|e| RustyPipesError::LoaderError(e.to_string()))
+    }
+
+    /// Load a Parquet file, reading only the projected columns in the schema. Each row group is read in parallel.
+    fn load_parquet(&self, path: &Path) -> RustyPipesResult<Dataframe> {
+        let schema = self.projected_schema();
+        let builder = self.parquet_reader_builder(path)?;
+        let mask = parquet_projection(&builder, path, &schema)?;
+        let row_groups = builder.metadata().num_row_groups();
+
+        let dfs = (0..row_groups)
+            .into_par_iter()
+            .map(|row_group| {
+                let reader = self
+                    .parquet_reader_builder(path)?
+                    .with_projection(mask.clone())
+                    .with_row_groups(vec![row_group])
+                    .build()
+                    .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                let mut df = vec![];
+                for batch in reader {
+                    self.cancellation.check()?;
+                    let batch = batch.map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                    df.extend(
+                        from_record_batch(&batch, &schema)
+                            .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
+                    );
+                }
+                Ok(df)
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+        Ok(dfs.into_iter().flatten().collect())
+    }
+
+    /// Load an Arrow IPC file, which may either use the file format or the streaming format.
+    fn load_arrow_ipc(&self, path: &Path) -> RustyPipesResult<Dataframe> {
+        let schema = self.projected_schema();
+        let mut df = vec![];
+        self.check_uncompressed(path)?;
+        for batch in arrow_ipc_batches(path)? {
+            self.cancellation.check()?;
+            let batch = batch.map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+            df.extend(
+                from_record_batch(&batch, &schema)
+                    .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
+            );
         }
         Ok(df)
     }
 }
 
+/// The projection mask that selects the columns of the schema from a Parquet file. Optional columns that the file does
+/// not contain are skipped.
+fn parquet_projection(
+    builder: &ParquetRecordBatchReaderBuilder<File>,
+    path: &Path,
+    schema: &[ColumnDefinition],
+) -> RustyPipesResult<ProjectionMask> {
+    let parquet_schema = builder.parquet_schema();
+    let fields = parquet_schema.root_schema().get_fields();
+    let projection = schema
+        .iter()
+        .filter_map(|definition| {
+            match fields
+                .iter()
+                .position(|field| field.name() == definition.column_name)
+            {
+                Some(index) => Some(Ok(index)),
+                None if definition.required => Some(Err(RustyPipesError::LoaderError(format!(
+                    "File {} does not contain column {}",
+                    path.display(),
+                    definition.column_name
+                )))),
+                None => None,
+            }
+        })
+        .collect::<RustyPipesResult<Vec<_>>>()?;
+    Ok(ProjectionMask::roots(parquet_schema, projection))
+}
+
+/// Read the record batches of an Arrow IPC file, which may either use the file format or the streaming format.
+fn arrow_ipc_batches(
+    path: &Path,
+) -> RustyPipesResult<Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>>>> {
+    let mut file = File::open(path).map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+    let mut magic = [0; 6];
+    let is_file_format = file.read_exact(&mut magic).is_ok() && &magic == b"ARROW1";
+    file.rewind()
+        .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+
+    if is_file_format {
+        Ok(Box::new(FileReader::try_new_buffered(file, None).map_err(
+            |e| RustyPipesError::LoaderError(e.to_string()),
+        )?))
+    } else {
+        Ok(Box::new(
+            StreamReader::try_new_buffered(file, None)
+                .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?,
+        ))
+    }
+}
+
 impl Loader for FileLoader<'_> {
     fn load(&self) -> RustyPipesResult<Dataframe> {
-        match self.format {
-            Format::Csv => self.load_csv(),
+        let paths = resolve_paths(self.path, self.format)?;
+        let dfs = if self.parallel {
+            // The files are loaded on other threads, which do not share the current span unless it is entered there.
+            let span = Span::current();
+            paths
+                .par_iter()
+                .map(|path| span.in_scope(|| self.load_file(path)))
+                .collect::<RustyPipesResult<Vec<_>>>()?
+        } else {
+            paths
+                .iter()
+                .map(|path| self.load_file(path))
+                .collect::<RustyPipesResult<Vec<_>>>()?
+        };
+        Ok(dfs.into_iter().flatten().collect())
+    }
+
+    /// Yield the rows of the files in batches, reading each file incrementally. The files are read one after another,
+    /// regardless of the parallelism of this loader; a batch never spans multiple files.
+    fn load_batches<'b>(self: Box<Self>, batch_size: usize) -> RustyPipesResult<Batches<'b>>
+    where
+        Self: 'b,
+    {
+        let loader = *self;
+        let paths = resolve_paths(loader.path, loader.format)?;
+        Ok(Box::new(paths.into_iter().flat_map(move |path| {
+            loader
+                .clone()
+                .file_batches(path, batch_size)
+                .unwrap_or_else(|err| Box::new(std::iter::once(Err(err))))
+        })))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{core::sink::Sink, sinks::ParquetSink};
+
+    fn write_file(dir: &Path, name: &str, contents: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn schema() -> Vec<ColumnDefinition> {
+        vec![
+            ColumnDefinition {
+                column_name: String::from("name"),
+                data_type: DataType::String,
+                required: true,
+            },
+            ColumnDefinition {
+                column_name: String::from("amount"),
+                data_type: DataType::Integer,
+                required: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn loads_with_default_dialect() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            dir.path(),
+            "default_dialect.csv",
+            b"name,amount\nfoo,1\n\"b,ar\",2\n",
+        );
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result =
+            FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("foo"))
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(1)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("b,ar"))
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(2)),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_with_custom_dialect() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            dir.path(),
+            "custom_dialect.csv",
+            b"# a comment\n'f;oo'; 1 \n'it\\'s';2\n",
+        );
+        let schema = schema();
+        let dialect: CsvDialect = serde_json::from_str(
+            r##"{"delimiter": ";", "quote": "'", "escape": "\\", "comment": "#", "has_headers": false, "trim": "All"}"##,
+        )
+        .unwrap();
+
+        let result =
+            FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("f;oo"))
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(1)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("it's"))
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(2)),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn flexible_records_yield_absent_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(dir.path(), "flexible.csv", b"name,amount\nfoo,1\nbar\n");
+        let schema = schema();
+        let dialect = CsvDialect {
+            flexible: true,
+            ..Default::default()
+        };
+
+        let result =
+            FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        assert_eq!(
+            result.unwrap()[1],
+            HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("bar"))
+                ),
+                (String::from("amount"), ColumnValue::None),
+            ])
+        );
+    }
+
+    #[test]
+    fn decodes_non_utf8_encoding() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(dir.path(), "latin1.csv", b"name,amount\ncaf\xe9,1\n");
+        let schema = schema();
+        let dialect = CsvDialect {
+            encoding: Some(String::from("windows-1252")),
+            ..Default::default()
+        };
+
+        let result =
+            FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        assert_eq!(
+            result.unwrap()[0].get("name"),
+            Some(&ColumnValue::String(String::from("café")))
+        );
+    }
+
+    #[test]
+    fn rejects_multi_byte_delimiter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(dir.path(), "multibyte.csv", b"name,amount\n");
+        let schema = schema();
+        let dialect = CsvDialect {
+            delimiter: '→',
+            ..Default::default()
+        };
+
+        let result =
+            FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) => message.contains("single ASCII character"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn loads_compressed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("compressed.csv.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        std::io::Write::write_all(&mut encoder, b"name,amount\nfoo,1\n").unwrap();
+        encoder.finish().unwrap();
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result =
+            FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("foo"))
+                ),
+                (String::from("amount"), ColumnValue::Integer(1)),
+            ])]
+        );
+    }
+
+    #[test]
+    fn loads_glob_pattern_in_order_with_filename_column() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("2024-02.csv"), b"name,amount\nbar,2\n").unwrap();
+        std::fs::write(dir.join("2024-01.csv"), b"name,amount\nfoo,1\n").unwrap();
+        std::fs::write(dir.join("2023-12.csv"), b"name,amount\nbaz,0\n").unwrap();
+        let pattern = dir.join("2024-*.csv");
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result = FileLoader::new(
+            &pattern,
+            &Format::Csv,
+            &dialect,
+            &schema,
+            &Context::default(),
+        )
+        .with_filename_column(Some("file"))
+        .with_parallelism(true)
+        .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("foo"))
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(1)),
+                    (
+                        String::from("file"),
+                        ColumnValue::String(String::from("2024-01.csv"))
+                    ),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("bar"))
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(2)),
+                    (
+                        String::from("file"),
+                        ColumnValue::String(String::from("2024-02.csv"))
+                    ),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_files_of_the_format_in_directory() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("b.csv"), b"name,amount\nbar,2\n").unwrap();
+        std::fs::write(dir.join("a.csv"), b"name,amount\nfoo,1\n").unwrap();
+        let compressed = zstd::encode_all(&b"name,amount\nbaz,3\n"[..], 0).unwrap();
+        std::fs::write(dir.join("c.CSV.zst"), compressed).unwrap();
+        std::fs::write(dir.join("README.md"), b"# Exports\n").unwrap();
+        std::fs::write(dir.join(".DS_Store"), b"\0\0\0\x01Bud1").unwrap();
+        std::fs::create_dir(dir.join("archive.csv")).unwrap();
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result =
+            FileLoader::new(dir, &Format::Csv, &dialect, &schema, &Context::default()).load();
+
+        let names: Vec<_> = result
+            .unwrap()
+            .into_iter()
+            .map(|row| row.get("name").cloned().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ColumnValue::String(String::from("foo")),
+                ColumnValue::String(String::from("bar")),
+                ColumnValue::String(String::from("baz"))
+            ]
+        );
+    }
+
+    #[test]
+    fn glob_without_matches_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let pattern = dir.path().join("no_such_dir/*.csv");
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result = FileLoader::new(
+            &pattern,
+            &Format::Csv,
+            &dialect,
+            &schema,
+            &Context::default(),
+        )
+        .load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) => message.contains("No files found"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn loads_projected_columns_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            dir.path(),
+            "projection.csv",
+            b"name,amount\nfoo,not a number\n",
+        );
+        let schema = schema();
+        let dialect = CsvDialect::default();
+        let projection = BTreeSet::from([String::from("name")]);
+
+        let result = FileLoader::new(&path, &Format::Csv, &dialect, &schema, &Context::default())
+            .with_projection(Some(&projection))
+            .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![HashMap::from([(
+                String::from("name"),
+                ColumnValue::String(String::from("foo"))
+            )])]
+        );
+    }
+
+    #[test]
+    fn loads_batches_per_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("a.csv"), b"name,amount\na,1\nb,2\nc,3\n").unwrap();
+        std::fs::write(dir.join("b.csv"), b"name,amount\nd,4\n").unwrap();
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let batches = Box::new(FileLoader::new(
+            dir,
+            &Format::Csv,
+            &dialect,
+            &schema,
+            &Context::default(),
+        ))
+        .load_batches(2)
+        .unwrap()
+        .collect::<RustyPipesResult<Vec<_>>>()
+        .unwrap();
+
+        let sizes: Vec<_> = batches.iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![2, 1, 1]);
+        assert_eq!(batches[2][0].get("amount"), Some(&ColumnValue::Integer(4)));
+    }
+
+    #[test]
+    fn yields_errors_of_invalid_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(
+            dir.path(),
+            "invalid_batches.csv",
+            b"name,amount\nfoo,1\nbar,x\n",
+        );
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let mut batches = Box::new(FileLoader::new(
+            &path,
+            &Format::Csv,
+            &dialect,
+            &schema,
+            &Context::default(),
+        ))
+        .load_batches(1)
+        .unwrap();
+
+        assert!(batches.next().unwrap().is_ok());
+        assert!(batches.next().unwrap().is_err());
+    }
+
+    #[test]
+    fn loads_parquet_file_with_projection() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("loader.parquet");
+        let dfs = vec![(0..5)
+            .map(|i| {
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(format!("name{}", i)),
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(i)),
+                    (String::from("unused"), ColumnValue::Decimal(0.5)),
+                ])
+            })
+            .collect::<Dataframe>()];
+        ParquetSink::new(&path, Default::default(), Some(2))
+            .write(&dfs)
+            .unwrap();
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result = FileLoader::new(
+            &path,
+            &Format::Parquet,
+            &dialect,
+            &schema,
+            &Context::default(),
+        )
+        .load();
+
+        let expected: Dataframe = (0..5)
+            .map(|i| {
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(format!("name{}", i)),
+                    ),
+                    (String::from("amount"), ColumnValue::Integer(i)),
+                ])
+            })
+            .collect();
+        assert_eq!(result.unwrap(), expected);
+
+        let batches = Box::new(FileLoader::new(
+            &path,
+            &Format::Parquet,
+            &dialect,
+            &schema,
+            &Context::default(),
+        ))
+        .load_batches(2)
+        .unwrap()
+        .collect::<RustyPipesResult<Vec<_>>>()
+        .unwrap();
+        assert!(batches.iter().all(|batch| batch.len() <= 2));
+        assert_eq!(batches.concat(), expected);
+    }
+
+    #[test]
+    fn loads_missing_optional_parquet_column_as_absent() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.parquet");
+        let dfs = vec![vec![HashMap::from([(
+            String::from("name"),
+            ColumnValue::String(String::from("foo")),
+        )])]];
+        ParquetSink::new(&path, Default::default(), None)
+            .write(&dfs)
+            .unwrap();
+        let mut schema = schema();
+        let dialect = CsvDialect::default();
+
+        let result = FileLoader::new(
+            &path,
+            &Format::Parquet,
+            &dialect,
+            &schema,
+            &Context::default(),
+        )
+        .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("foo"))
+                ),
+                (String::from("amount"), ColumnValue::None),
+            ])]
+        );
+
+        schema[1].required = true;
+        let result = FileLoader::new(
+            &path,
+            &Format::Parquet,
+            &dialect,
+            &schema,
+            &Context::default(),
+        )
+        .load();
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message.contains("does not contain column amount"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn loads_arrow_ipc_file_and_stream() {
+        let dfs = vec![vec![HashMap::from([
+            (
+                String::from("name"),
+                ColumnValue::String(String::from("foo")),
+            ),
+            (String::from("amount"), ColumnValue::Integer(1)),
+        ])]];
+        let batch = crate::record_batch::to_record_batch(&dfs).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("loader.arrow");
+        let mut file_writer = arrow::ipc::writer::FileWriter::try_new(
+            File::create(&file_path).unwrap(),
+            &batch.schema(),
+        )
+        .unwrap();
+        file_writer.write(&batch).unwrap();
+        file_writer.finish().unwrap();
+        let stream_path = dir.path().join("loader.arrows");
+        let mut stream_writer = arrow::ipc::writer::StreamWriter::try_new(
+            File::create(&stream_path).unwrap(),
+            &batch.schema(),
+        )
+        .unwrap();
+        stream_writer.write(&batch).unwrap();
+        stream_writer.finish().unwrap();
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        for path in [file_path, stream_path] {
+            let result = FileLoader::new(
+                &path,
+                &Format::ArrowIpc,
+                &dialect,
+                &schema,
+                &Context::default(),
+            )
+            .load();
+
+            assert_eq!(result.unwrap(), dfs[0]);
+        }
+    }
+
+    #[test]
+    fn rejects_compressed_parquet_and_arrow_ipc_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(dir.path(), "compressed.parquet.gz", b"");
+        let schema = schema();
+        let dialect = CsvDialect::default();
+
+        for format in [Format::Parquet, Format::ArrowIpc] {
+            let result =
+                FileLoader::new(&path, &format, &dialect, &schema, &Context::default()).load();
+
+            assert!(result.is_err_and(|err| match err {
+                RustyPipesError::LoaderError(message) =>
+                    message.contains("only CSV files can be compressed"),
+                _ => false,
+            }));
         }
+
+        let path = write_file(dir.path(), "explicit.parquet", b"");
+        let result = FileLoader::new(
+            &path,
+            &Format::Parquet,
+            &dialect,
+            &schema,
+            &Context::default(),
+        )
+        .with_compression(Some(Compression::Zstd))
+        .load();
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) => message.contains("using Zstd"),
+            _ => false,
+        }));
     }
 }
@@ -0,0 +1,714 @@
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use serde_json::Value;
+use ureq::{http::Response, Agent, Body};
+
+use super::json::convert_record;
+use crate::core::{
+    cancellation::CancellationToken,
+    context::Context,
+    dataframe::Dataframe,
+    definitions::{ColumnDefinition, HttpMethod, Pagination, RetryPolicy},
+    error::RustyPipesError,
+    loader::Loader,
+    result::RustyPipesResult,
+};
+
+fn substitute(template: &str, context: &Context) -> RustyPipesResult<String> {
+    context
+        .substitute(template)
+        .map_err(|key| RustyPipesError::LoaderError(format!("Unable to resolve parameter {}", key)))
+}
+
+/// Follow a dot-separated path of object keys and array indices (e.g., "data.items" or "results.0.rows") within a
+/// JSON value. A leading "$." is accepted and ignored.
+fn follow_path<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    let path = path.strip_prefix("$.").unwrap_or(path);
+    path.split('.')
+        .filter(|segment| !segment.is_empty() && *segment != "$")
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index)),
+            _ => current.get(segment),
+        })
+}
+
+/// Extract the URL with rel="next" from the value of a Link header.
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        parts
+            .any(|param| {
+                matches!(
+                    param.trim().replace(' ', "").as_str(),
+                    "rel=\"next\"" | "rel=next"
+                )
+            })
+            .then(|| url.to_owned())
+    })
+}
+
+/// Resolve a link relative to the URL of the response it was found in. Only absolute URLs and absolute paths are
+/// supported.
+fn resolve_link(current_url: &str, link: &str) -> String {
+    if !link.starts_with('/') {
+        return link.to_owned();
+    }
+    let origin_end = current_url
+        .find("://")
+        .map(|scheme_end| {
+            current_url[scheme_end + 3..]
+                .find('/')
+                .map_or(current_url.len(), |path_start| scheme_end + 3 + path_start)
+        })
+        .unwrap_or(0);
+    format!("{}{}", &current_url[..origin_end], link)
+}
+
+/// A single page of records, as retrieved from the endpoint.
+struct Page {
+    response: Value,
+    next_link: Option<String>,
+}
+
+/// A loader that retrieves records from a JSON HTTP endpoint, mapping the fields of each record onto the schema by
+/// name. Subsequent pages are requested according to the pagination, if any, and failed requests are retried
+/// according to the retry policy.
+pub struct HttpLoader<'a> {
+    url: String,
+    method: HttpMethod,
+    headers: Vec<(String, String)>,
+    body: Option<&'a Value>,
+    json_path: Option<&'a str>,
+    pagination: Option<&'a Pagination>,
+    retry: RetryPolicy,
+    schema: &'a Vec<ColumnDefinition>,
+    cancellation: CancellationToken,
+}
+
+impl<'a> HttpLoader<'a> {
+    /// Construct a new HTTP loader for the given URL, method and headers, using the specified schema. Parameters in
+    /// the context that are referenced in the URL or in header values (e.g., ":version") are substituted. Loading
+    /// stops before the next request, or during the backoff before a retry, once the run is cancelled; a request that
+    /// is in flight when the run times out is aborted. This is a lazy operation; until the "load" method is run, no
+    /// work will be performed.
+    pub fn new(
+        url: &'a str,
+        method: HttpMethod,
+        headers: &'a HashMap<String, String>,
+        schema: &'a Vec<ColumnDefinition>,
+        context: &Context,
+    ) -> RustyPipesResult<Self> {
+        Ok(HttpLoader {
+            url: substitute(url, context)?,
+            method,
+            headers: headers
+                .iter()
+                .map(|(name, value)| Ok((name.clone(), substitute(value, context)?)))
+                .collect::<RustyPipesResult<_>>()?,
+            body: None,
+            json_path: None,
+            pagination: None,
+            retry: RetryPolicy::default(),
+            schema,
+            cancellation: context.cancellation().clone(),
+        })
+    }
+
+    /// Send the given JSON body with each request. This only applies to POST requests.
+    pub fn with_body(mut self, body: Option<&'a Value>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Extract the records from the given path within each response, rather than from the response itself.
+    pub fn with_json_path(mut self, json_path: Option<&'a str>) -> Self {
+        self.json_path = json_path;
+        self
+    }
+
+    /// Request subsequent pages of records using the given pagination.
+    pub fn with_pagination(mut self, pagination: Option<&'a Pagination>) -> Self {
+        self.pagination = pagination;
+        self
+    }
+
+    /// Retry failed requests according to the given policy.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn request(
+        &self,
+        agent: &Agent,
+        url: &str,
+        query: &[(String, String)],
+    ) -> Result<Response<Body>, ureq::Error> {
+        match self.method {
+            HttpMethod::Get => {
+                let mut request = agent.get(url).query_pairs(query.to_vec());
+                for (name, value) in &self.headers {
+                    request = request.header(name, value);
+                }
+                request
+                    .config()
+                    .timeout_global(self.cancellation.remaining())
+                    .build()
+                    .call()
+            }
+            HttpMethod::Post => {
+                let mut request = agent.post(url).query_pairs(query.to_vec());
+                for (name, value) in &self.headers {
+                    request = request.header(name, value);
+                }
+                let request = request
+                    .config()
+                    .timeout_global(self.cancellation.remaining())
+                    .build();
+                match self.body {
+                    Some(body) => request
+                        .header("Content-Type", "application/json")
+                        .send(body.to_string()),
+                    None => request.send_empty(),
+                }
+            }
+        }
+    }
+
+    fn fetch(
+        &self,
+        agent: &Agent,
+        url: &str,
+        query: &[(String, String)],
+    ) -> RustyPipesResult<Page> {
+        let mut attempt = 0;
+        loop {
+            self.cancellation.check()?;
+            let failure = match self.request(agent, url, query) {
+                Ok(mut response) if response.status().is_success() => {
+                    let next_link = response
+                        .headers()
+                        .get("link")
+                        .and_then(|header| header.to_str().ok())
+                        .and_then(next_link);
+                    let body = response
+                        .body_mut()
+                        .read_to_string()
+                        .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                    let response = serde_json::from_str(&body)
+                        .map_err(|e| RustyPipesError::LoaderError(e.to_string()))?;
+                    return Ok(Page {
+                        response,
+                        next_link,
+                    });
+                }
+                Ok(response) => {
+                    let status = response.status();
+                    let message = format!("Request to {} failed with status {}", url, status);
+                    if !(status.is_server_error() || status.as_u16() == 429) {
+                        return Err(RustyPipesError::LoaderError(message));
+                    }
+                    message
+                }
+                Err(e) => format!("Request to {} failed: {}", url, e),
+            };
+
+            // A request that was aborted at the deadline of the token fails with the error of the token.
+            self.cancellation.check()?;
+            if attempt >= self.retry.max_retries {
+                return Err(RustyPipesError::LoaderError(failure));
+            }
+            self.cancellation.sleep(Duration::from_millis(
+                self.retry
+                    .initial_backoff_ms
+                    .saturating_mul(1 << attempt.min(16)),
+            ))?;
+            attempt += 1;
+        }
+    }
+
+    fn records<'v>(&self, response: &'v Value) -> RustyPipesResult<&'v Vec<Value>> {
+        let records = match self.json_path {
+            Some(path) => follow_path(response, path).ok_or_else(|| {
+                RustyPipesError::LoaderError(format!("Response does not contain path {}", path))
+            })?,
+            None => response,
+        };
+        records.as_array().ok_or_else(|| {
+            RustyPipesError::LoaderError("Expected the records to be a JSON array".to_owned())
+        })
+    }
+}
+
+impl Loader for HttpLoader<'_> {
+    fn load(&self) -> RustyPipesResult<Dataframe> {
+        let agent: Agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        let mut url = self.url.clone();
+        let mut offset = 0;
+        let mut query = match self.pagination {
+            Some(Pagination::Offset {
+                parameter,
+                limit_parameter,
+                page_size,
+            }) => {
+                let mut query = vec![(parameter.clone(), offset.to_string())];
+                if let Some(limit_parameter) = limit_parameter {
+                    query.push((limit_parameter.clone(), page_size.to_string()));
+                }
+                query
+            }
+            _ => vec![],
+        };
+
+        let mut cursors = HashSet::new();
+        let mut links = HashSet::from([url.clone()]);
+        let mut df = vec![];
+        loop {
+            let page = self.fetch(&agent, &url, &query)?;
+            let records = self.records(&page.response)?;
+            for record in records {
+                df.push(convert_record(record, self.schema)?);
+            }
+
+            match self.pagination {
+                None => break,
+                Some(Pagination::Cursor {
+                    cursor_path,
+                    parameter,
+                }) => {
+                    let cursor = match follow_path(&page.response, cursor_path) {
+                        None | Some(Value::Null) => break,
+                        Some(Value::String(cursor)) => cursor.clone(),
+                        Some(cursor) => cursor.to_string(),
+                    };
+                    // An endpoint that keeps returning a cursor it returned before would be requested forever.
+                    if !cursors.insert(cursor.clone()) {
+                        return Err(RustyPipesError::LoaderError(format!(
+                            "Cursor {} was returned more than once",
+                            cursor
+                        )));
+                    }
+                    query = vec![(parameter.clone(), cursor)];
+                }
+                Some(Pagination::Offset { page_size, .. }) => {
+                    if records.is_empty() || records.len() < *page_size {
+                        break;
+                    }
+                    offset += records.len();
+                    query[0].1 = offset.to_string();
+                }
+                Some(Pagination::LinkHeader) => match page.next_link {
+                    Some(next) => {
+                        url = resolve_link(&url, &next);
+                        // Like a repeated cursor, a link to a page that was requested before would be followed forever.
+                        if !links.insert(url.clone()) {
+                            return Err(RustyPipesError::LoaderError(format!(
+                                "Link to {} was returned more than once",
+                                url
+                            )));
+                        }
+                        query = vec![];
+                    }
+                    None => break,
+                },
+            }
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::{dataframe::ColumnValue, definitions::DataType};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    /// A response of the mock server: the status, body and optionally a Link header.
+    type MockResponse = (u16, String, Option<String>);
+
+    /// Start a mock server on a random local port, which responds to each request using the given handler. The
+    /// handler receives the URL (including the query string) and the headers of the request. Returns the base URL of
+    /// the server.
+    fn serve<F>(handler: F) -> String
+    where
+        F: Fn(&str, &[tiny_http::Header]) -> MockResponse + Send + 'static,
+    {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", server.server_addr());
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let (status, body, link) = handler(request.url(), request.headers());
+                let mut response = tiny_http::Response::from_string(body).with_status_code(status);
+                if let Some(link) = link {
+                    response.add_header(tiny_http::Header::from_bytes("Link", link).unwrap());
+                }
+                request.respond(response).unwrap();
+            }
+        });
+        base_url
+    }
+
+    fn schema() -> Vec<ColumnDefinition> {
+        vec![
+            ColumnDefinition {
+                column_name: String::from("name"),
+                data_type: DataType::String,
+                required: true,
+            },
+            ColumnDefinition {
+                column_name: String::from("salary"),
+                data_type: DataType::Decimal,
+                required: false,
+            },
+        ]
+    }
+
+    fn names(df: Dataframe) -> Vec<ColumnValue> {
+        df.into_iter()
+            .map(|row| row.get("name").cloned().unwrap())
+            .collect()
+    }
+
+    fn string(s: &str) -> ColumnValue {
+        ColumnValue::String(String::from(s))
+    }
+
+    #[test]
+    fn loads_records_from_json_path() {
+        let base_url = serve(|url, headers| {
+            let authorised = headers.iter().any(|header| {
+                header.field.equiv("Authorization") && header.value == "Bearer s3cret"
+            });
+            match (url, authorised) {
+                ("/v2/people", true) => (
+                    200,
+                    r#"{"data": {"items": [{"name": "Jen", "salary": 19319}, {"name": "Dave"}]}}"#
+                        .to_owned(),
+                    None,
+                ),
+                _ => (401, String::new(), None),
+            }
+        });
+        let url = format!("{}/:version/people", base_url);
+        let headers =
+            HashMap::from([(String::from("Authorization"), String::from("Bearer :token"))]);
+        let schema = schema();
+        let context = Context::new(HashMap::from([
+            (String::from("version"), String::from("v2")),
+            (String::from("token"), String::from("s3cret")),
+        ]));
+
+        let result = HttpLoader::new(&url, HttpMethod::Get, &headers, &schema, &context)
+            .unwrap()
+            .with_json_path(Some("data.items"))
+            .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (String::from("name"), string("Jen")),
+                    (String::from("salary"), ColumnValue::Decimal(19319.0)),
+                ]),
+                HashMap::from([
+                    (String::from("name"), string("Dave")),
+                    (String::from("salary"), ColumnValue::None),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_cursor_pagination() {
+        let base_url = serve(|url, _| match url {
+            "/people" => (
+                200,
+                r#"{"items": [{"name": "Jen"}], "next": "abc"}"#.to_owned(),
+                None,
+            ),
+            "/people?cursor=abc" => (
+                200,
+                r#"{"items": [{"name": "Dave"}], "next": null}"#.to_owned(),
+                None,
+            ),
+            _ => (404, String::new(), None),
+        });
+        let url = format!("{}/people", base_url);
+        let headers = HashMap::new();
+        let schema = schema();
+        let pagination = Pagination::Cursor {
+            cursor_path: String::from("next"),
+            parameter: String::from("cursor"),
+        };
+
+        let result = HttpLoader::new(
+            &url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_json_path(Some("items"))
+        .with_pagination(Some(&pagination))
+        .load();
+
+        assert_eq!(names(result.unwrap()), vec![string("Jen"), string("Dave")]);
+    }
+
+    #[test]
+    fn repeated_cursor_errors() {
+        let base_url = serve(|url, _| match url {
+            "/people" | "/people?cursor=abc" => (
+                200,
+                r#"{"items": [{"name": "Jen"}], "next": "abc"}"#.to_owned(),
+                None,
+            ),
+            _ => (404, String::new(), None),
+        });
+        let url = format!("{}/people", base_url);
+        let headers = HashMap::new();
+        let schema = schema();
+        let pagination = Pagination::Cursor {
+            cursor_path: String::from("next"),
+            parameter: String::from("cursor"),
+        };
+
+        let result = HttpLoader::new(
+            &url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_json_path(Some("items"))
+        .with_pagination(Some(&pagination))
+        .load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message == "Cursor abc was returned more than once",
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn follows_offset_pagination() {
+        let base_url = serve(|url, _| match url {
+            "/people?offset=0&limit=2" => (
+                200,
+                r#"[{"name": "Jen"}, {"name": "Dave"}]"#.to_owned(),
+                None,
+            ),
+            "/people?offset=2&limit=2" => (200, r#"[{"name": "Roy"}]"#.to_owned(), None),
+            _ => (404, String::new(), None),
+        });
+        let url = format!("{}/people", base_url);
+        let headers = HashMap::new();
+        let schema = schema();
+        let pagination = Pagination::Offset {
+            parameter: String::from("offset"),
+            limit_parameter: Some(String::from("limit")),
+            page_size: 2,
+        };
+
+        let result = HttpLoader::new(
+            &url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_pagination(Some(&pagination))
+        .load();
+
+        assert_eq!(
+            names(result.unwrap()),
+            vec![string("Jen"), string("Dave"), string("Roy")]
+        );
+    }
+
+    #[test]
+    fn follows_link_header_pagination() {
+        let base_url = serve(|url, _| match url {
+            "/people" => (
+                200,
+                r#"[{"name": "Jen"}]"#.to_owned(),
+                Some(String::from(
+                    r#"</people?page=1>; rel="prev", </people?page=2>; rel="next""#,
+                )),
+            ),
+            "/people?page=2" => (200, r#"[{"name": "Dave"}]"#.to_owned(), None),
+            _ => (404, String::new(), None),
+        });
+        let url = format!("{}/people", base_url);
+        let headers = HashMap::new();
+        let schema = schema();
+        let pagination = Pagination::LinkHeader;
+
+        let result = HttpLoader::new(
+            &url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_pagination(Some(&pagination))
+        .load();
+
+        assert_eq!(names(result.unwrap()), vec![string("Jen"), string("Dave")]);
+    }
+
+    #[test]
+    fn repeated_link_errors() {
+        let base_url = serve(|url, _| match url {
+            "/people" => (
+                200,
+                r#"[{"name": "Jen"}]"#.to_owned(),
+                Some(String::from(r#"</people?page=2>; rel="next""#)),
+            ),
+            "/people?page=2" => (
+                200,
+                r#"[{"name": "Dave"}]"#.to_owned(),
+                Some(String::from(r#"</people>; rel="next""#)),
+            ),
+            _ => (404, String::new(), None),
+        });
+        let url = format!("{}/people", base_url);
+        let headers = HashMap::new();
+        let schema = schema();
+        let pagination = Pagination::LinkHeader;
+
+        let result = HttpLoader::new(
+            &url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_pagination(Some(&pagination))
+        .load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message == format!("Link to {} was returned more than once", url),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn retries_failed_requests() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let base_url = serve(move |_, _| {
+            if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                (503, String::new(), None)
+            } else {
+                (200, r#"[{"name": "Jen"}]"#.to_owned(), None)
+            }
+        });
+        let headers = HashMap::new();
+        let schema = schema();
+
+        let result = HttpLoader::new(
+            &base_url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_retries: 2,
+            initial_backoff_ms: 1,
+        })
+        .load();
+
+        assert_eq!(names(result.unwrap()), vec![string("Jen")]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_retries() {
+        let base_url = serve(|_, _| (500, String::new(), None));
+        let headers = HashMap::new();
+        let schema = schema();
+
+        let result = HttpLoader::new(
+            &base_url,
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .with_retry_policy(RetryPolicy {
+            max_retries: 1,
+            initial_backoff_ms: 1,
+        })
+        .load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) => message.contains("failed with status 500"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn aborts_request_at_deadline() {
+        let base_url = serve(|_, _| {
+            std::thread::sleep(Duration::from_secs(5));
+            (200, String::from("[]"), None)
+        });
+        let headers = HashMap::new();
+        let schema = schema();
+        let context = Context::default().with_cancellation(
+            CancellationToken::new().with_timeout(Duration::from_millis(100), "Source people"),
+        );
+        let start = std::time::Instant::now();
+
+        let result = HttpLoader::new(&base_url, HttpMethod::Get, &headers, &schema, &context)
+            .unwrap()
+            .load();
+
+        assert!(matches!(result, Err(RustyPipesError::TimedOut(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn unresolved_parameter_errors() {
+        let headers = HashMap::new();
+        let schema = schema();
+
+        let result = HttpLoader::new(
+            "http://localhost:8080/:missing",
+            HttpMethod::Get,
+            &headers,
+            &schema,
+            &Default::default(),
+        );
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message.contains("Unable to resolve parameter :missing"),
+            _ => false,
+        }));
+    }
+}
@@ -0,0 +1,92 @@
+use super::json::convert_record;
+use crate::core::{
+    dataframe::Dataframe, definitions::ColumnDefinition, loader::Loader, result::RustyPipesResult,
+};
+
+/// A loader that yields rows that are specified literally as JSON objects, mapping the fields of each object onto the
+/// schema by name. This is useful for small lookup tables and for tests.
+pub struct InlineLoader<'a> {
+    rows: &'a [serde_json::Value],
+    schema: &'a Vec<ColumnDefinition>,
+}
+
+impl<'a> InlineLoader<'a> {
+    /// Construct a new inline loader for the given rows, using the specified schema.
+    pub fn new(rows: &'a [serde_json::Value], schema: &'a Vec<ColumnDefinition>) -> Self {
+        InlineLoader { rows, schema }
+    }
+}
+
+impl Loader for InlineLoader<'_> {
+    fn load(&self) -> RustyPipesResult<Dataframe> {
+        self.rows
+            .iter()
+            .map(|row| convert_record(row, self.schema))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::{dataframe::ColumnValue, definitions::DataType, error::RustyPipesError};
+    use std::collections::HashMap;
+
+    fn schema() -> Vec<ColumnDefinition> {
+        vec![
+            ColumnDefinition {
+                column_name: String::from("code"),
+                data_type: DataType::String,
+                required: true,
+            },
+            ColumnDefinition {
+                column_name: String::from("rate"),
+                data_type: DataType::Decimal,
+                required: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn loads_literal_rows() {
+        let rows: Vec<serde_json::Value> =
+            serde_json::from_str(r#"[{"code": "EUR", "rate": 1.1}, {"code": "GBP"}]"#).unwrap();
+        let schema = schema();
+
+        let result = InlineLoader::new(&rows, &schema).load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (
+                        String::from("code"),
+                        ColumnValue::String(String::from("EUR"))
+                    ),
+                    (String::from("rate"), ColumnValue::Decimal(1.1)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("code"),
+                        ColumnValue::String(String::from("GBP"))
+                    ),
+                    (String::from("rate"), ColumnValue::None),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_required_value_errors() {
+        let rows: Vec<serde_json::Value> = serde_json::from_str(r#"[{"rate": 1.1}]"#).unwrap();
+        let schema = schema();
+
+        let result = InlineLoader::new(&rows, &schema).load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message.contains("Missing value for required column code"),
+            _ => false,
+        }));
+    }
+}
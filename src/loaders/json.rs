@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::core::{
+    dataframe::{ColumnValue, Row},
+    definitions::{ColumnDefinition, DataType},
+    error::RustyPipesError,
+    result::RustyPipesResult,
+};
+
+fn convert_value(
+    value: Option<&Value>,
+    definition: &ColumnDefinition,
+) -> RustyPipesResult<ColumnValue> {
+    let mismatch = || {
+        RustyPipesError::LoaderError(format!(
+            "Unable to convert value of column {} to {:?}",
+            definition.column_name, definition.data_type
+        ))
+    };
+    let converted = match (value, &definition.data_type) {
+        (None | Some(Value::Null), _) if definition.required => {
+            return Err(RustyPipesError::LoaderError(format!(
+                "Missing value for required column {}",
+                definition.column_name
+            )))
+        }
+        (None | Some(Value::Null), _) => ColumnValue::None,
+        (Some(Value::Number(n)), DataType::Integer) => {
+            ColumnValue::Integer(n.as_i64().ok_or_else(mismatch)?)
+        }
+        (Some(Value::Number(n)), DataType::Decimal) => {
+            ColumnValue::Decimal(n.as_f64().ok_or_else(mismatch)?)
+        }
+        (Some(Value::String(s)), DataType::Integer) => {
+            ColumnValue::Integer(s.parse::<i64>().map_err(|_| mismatch())?)
+        }
+        (Some(Value::String(s)), DataType::Decimal) => {
+            ColumnValue::Decimal(s.parse::<f64>().map_err(|_| mismatch())?)
+        }
+        (Some(Value::String(s)), DataType::String) => ColumnValue::String(s.clone()),
+        (Some(v @ (Value::Number(_) | Value::Bool(_))), DataType::String) => {
+            ColumnValue::String(v.to_string())
+        }
+        _ => return Err(mismatch()),
+    };
+    Ok(converted)
+}
+
+/// Convert a JSON object into a row, mapping the fields of the object onto the schema by name.
+pub(crate) fn convert_record(record: &Value, schema: &[ColumnDefinition]) -> RustyPipesResult<Row> {
+    let mut row: Row = HashMap::new();
+    for definition in schema {
+        row.insert(
+            definition.column_name.clone(),
+            convert_value(record.get(&definition.column_name), definition)?,
+        );
+    }
+    Ok(row)
+}
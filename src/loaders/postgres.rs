@@ -0,0 +1,596 @@
+use std::{collections::HashMap, error::Error, sync::mpsc};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use postgres::{
+    types::{FromSql, ToSql, Type},
+    Client, NoTls,
+};
+
+use crate::core::{
+    cancellation::CancellationToken,
+    context::Context,
+    dataframe::{ColumnValue, Dataframe, Row},
+    definitions::{ColumnDefinition, DataType},
+    error::RustyPipesError,
+    loader::{Batches, Loader},
+    result::RustyPipesResult,
+};
+
+fn loader_err(e: postgres::Error) -> RustyPipesError {
+    RustyPipesError::LoaderError(e.to_string())
+}
+
+fn resolve(value: &str, context: &Context) -> RustyPipesResult<String> {
+    context
+        .resolve(value)
+        .map(|resolved| resolved.to_owned())
+        .ok_or_else(|| {
+            RustyPipesError::LoaderError(format!("Unable to resolve parameter {}", value))
+        })
+}
+
+/// Convert a parameter value to the type that Postgres expects for the corresponding placeholder in the query.
+fn bind_parameter(value: &str, param_type: &Type) -> RustyPipesResult<Box<dyn ToSql + Sync>> {
+    let mismatch = || {
+        RustyPipesError::LoaderError(format!(
+            "Unable to bind parameter value {} as {}",
+            value, param_type
+        ))
+    };
+    let bound: Box<dyn ToSql + Sync> = match *param_type {
+        Type::INT2 => Box::new(value.parse::<i16>().map_err(|_| mismatch())?),
+        Type::INT4 => Box::new(value.parse::<i32>().map_err(|_| mismatch())?),
+        Type::INT8 => Box::new(value.parse::<i64>().map_err(|_| mismatch())?),
+        Type::FLOAT4 => Box::new(value.parse::<f32>().map_err(|_| mismatch())?),
+        Type::FLOAT8 => Box::new(value.parse::<f64>().map_err(|_| mismatch())?),
+        Type::BOOL => Box::new(value.parse::<bool>().map_err(|_| mismatch())?),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN => {
+            Box::new(value.to_owned())
+        }
+        _ => return Err(mismatch()),
+    };
+    Ok(bound)
+}
+
+/// A NUMERIC value, converted to the nearest floating point number.
+struct Numeric(f64);
+
+impl<'a> FromSql<'a> for Numeric {
+    /// The binary format consists of the number of digits, the weight of the first digit, the sign and the display
+    /// scale, followed by the digits themselves, each of which is a base 10000 digit.
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let read_u16 = |i: usize| -> Result<u16, Box<dyn Error + Sync + Send>> {
+            raw.get(i..i + 2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .ok_or_else(|| "Invalid NUMERIC value".into())
+        };
+        let digits = read_u16(0)? as usize;
+        let weight = read_u16(2)? as i16;
+        let negative = match read_u16(4)? {
+            0x0000 => false,
+            0x4000 => true,
+            0xC000 => return Ok(Numeric(f64::NAN)),
+            0xD000 => return Ok(Numeric(f64::INFINITY)),
+            0xF000 => return Ok(Numeric(f64::NEG_INFINITY)),
+            sign => return Err(format!("Invalid NUMERIC sign {:#x}", sign).into()),
+        };
+        if digits == 0 {
+            return Ok(Numeric(0.0));
+        }
+        // Parse the digits as a decimal number, so that the value is rounded correctly.
+        let mantissa = (0..digits)
+            .map(|i| read_u16(8 + 2 * i).map(|digit| format!("{:04}", digit)))
+            .collect::<Result<String, _>>()?;
+        let value = format!("0.{}e{}", mantissa, 4 * (i32::from(weight) + 1)).parse::<f64>()?;
+        Ok(Numeric(if negative { -value } else { value }))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::NUMERIC
+    }
+}
+
+/// A value as retrieved from Postgres, before it is mapped onto the data type of its column.
+enum RawValue {
+    Integer(i64),
+    Decimal(f64),
+    Text(String),
+}
+
+/// Read a value from a row. NUMERIC values are read as decimals, BOOL values as integers (0 or 1), and dates and
+/// timestamps as ISO 8601 strings, where timestamps with a time zone are converted to UTC.
+fn read_value(row: &postgres::Row, index: usize) -> RustyPipesResult<Option<RawValue>> {
+    let column_type = row.columns()[index].type_();
+    let value = match *column_type {
+        Type::INT2 => row
+            .try_get::<_, Option<i16>>(index)
+            .map(|v| v.map(|v| RawValue::Integer(v.into()))),
+        Type::INT4 => row
+            .try_get::<_, Option<i32>>(index)
+            .map(|v| v.map(|v| RawValue::Integer(v.into()))),
+        Type::INT8 => row
+            .try_get::<_, Option<i64>>(index)
+            .map(|v| v.map(RawValue::Integer)),
+        Type::FLOAT4 => row
+            .try_get::<_, Option<f32>>(index)
+            .map(|v| v.map(|v| RawValue::Decimal(v.into()))),
+        Type::FLOAT8 => row
+            .try_get::<_, Option<f64>>(index)
+            .map(|v| v.map(RawValue::Decimal)),
+        Type::NUMERIC => row
+            .try_get::<_, Option<Numeric>>(index)
+            .map(|v| v.map(|v| RawValue::Decimal(v.0))),
+        Type::BOOL => row
+            .try_get::<_, Option<bool>>(index)
+            .map(|v| v.map(|v| RawValue::Integer(v.into()))),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => row
+            .try_get::<_, Option<String>>(index)
+            .map(|v| v.map(RawValue::Text)),
+        Type::DATE => row
+            .try_get::<_, Option<NaiveDate>>(index)
+            .map(|v| v.map(|v| RawValue::Text(v.to_string()))),
+        Type::TIMESTAMP => row
+            .try_get::<_, Option<NaiveDateTime>>(index)
+            .map(|v| v.map(|v| RawValue::Text(v.format("%Y-%m-%dT%H:%M:%S%.f").to_string()))),
+        Type::TIMESTAMPTZ => row
+            .try_get::<_, Option<DateTime<Utc>>>(index)
+            .map(|v| v.map(|v| RawValue::Text(v.to_rfc3339_opts(SecondsFormat::AutoSi, true)))),
+        _ => {
+            return Err(RustyPipesError::LoaderError(format!(
+                "Unsupported Postgres type {} for column {}; consider casting it in the query",
+                column_type,
+                row.columns()[index].name()
+            )))
+        }
+    };
+    value.map_err(loader_err)
+}
+
+fn convert_value(
+    value: Option<RawValue>,
+    definition: &ColumnDefinition,
+) -> RustyPipesResult<ColumnValue> {
+    let mismatch = || {
+        RustyPipesError::LoaderError(format!(
+            "Unable to convert value of column {} to {:?}",
+            definition.column_name, definition.data_type
+        ))
+    };
+    let converted = match (value, &definition.data_type) {
+        (None, _) if definition.required => {
+            return Err(RustyPipesError::LoaderError(format!(
+                "Missing value for required column {}",
+                definition.column_name
+            )))
+        }
+        (None, _) => ColumnValue::None,
+        (Some(RawValue::Integer(i)), DataType::Integer) => ColumnValue::Integer(i),
+        (Some(RawValue::Integer(i)), DataType::Decimal) => ColumnValue::Decimal(i as f64),
+        (Some(RawValue::Integer(i)), DataType::String) => ColumnValue::String(i.to_string()),
+        (Some(RawValue::Decimal(d)), DataType::Decimal) => ColumnValue::Decimal(d),
+        (Some(RawValue::Decimal(d)), DataType::String) => ColumnValue::String(d.to_string()),
+        (Some(RawValue::Decimal(_)), DataType::Integer) => return Err(mismatch()),
+        (Some(RawValue::Text(t)), DataType::Integer) => {
+            ColumnValue::Integer(t.parse::<i64>().map_err(|_| mismatch())?)
+        }
+        (Some(RawValue::Text(t)), DataType::Decimal) => {
+            ColumnValue::Decimal(t.parse::<f64>().map_err(|_| mismatch())?)
+        }
+        (Some(RawValue::Text(t)), DataType::String) => ColumnValue::String(t),
+    };
+    Ok(converted)
+}
+
+/// A loader that runs a SQL query against a PostgreSQL database, mapping the columns of the result onto the schema by
+/// name. The result is streamed from the database using a cursor, fetching a batch of rows at a time.
+pub struct PostgresLoader<'a> {
+    connection: String,
+    query: &'a str,
+    parameters: Vec<String>,
+    schema: &'a Vec<ColumnDefinition>,
+    batch_size: i32,
+    cancellation: CancellationToken,
+}
+
+impl<'a> PostgresLoader<'a> {
+    /// Construct a new Postgres loader for the given connection string and query, using the specified schema. The
+    /// parameters are bound to the placeholders in the query ($1, $2, ...) in order. Both the connection string and
+    /// the parameters may refer to parameters in the context (e.g., ":connection_string"). Loading stops between
+    /// batches of rows once the run is cancelled. This is a lazy operation; until the "load" method is run, no work
+    /// will be performed.
+    pub fn new(
+        connection: &'a str,
+        query: &'a str,
+        parameters: &'a [String],
+        schema: &'a Vec<ColumnDefinition>,
+        context: &Context,
+    ) -> RustyPipesResult<Self> {
+        Ok(PostgresLoader {
+            connection: resolve(connection, context)?,
+            query,
+            parameters: parameters
+                .iter()
+                .map(|parameter| resolve(parameter, context))
+                .collect::<RustyPipesResult<_>>()?,
+            schema,
+            batch_size: 10_000,
+            cancellation: context.cancellation().clone(),
+        })
+    }
+
+    /// Set the number of rows that are fetched from the cursor at a time, which is at least 1, as the cursor would
+    /// otherwise fetch all rows at once.
+    pub fn with_batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = i32::try_from(batch_size.max(1)).unwrap_or(i32::MAX);
+        self
+    }
+}
+
+impl PostgresLoader<'_> {
+    /// Run the query, passing each batch of rows fetched from the cursor to the given function, until it yields false.
+    fn fetch_batches(&self, mut on_batch: impl FnMut(Dataframe) -> bool) -> RustyPipesResult<()> {
+        let mut client = Client::connect(&self.connection, NoTls).map_err(loader_err)?;
+        let mut transaction = client.transaction().map_err(loader_err)?;
+        let statement = transaction.prepare(self.query).map_err(loader_err)?;
+
+        if statement.params().len() != self.parameters.len() {
+            return Err(RustyPipesError::LoaderError(format!(
+                "Query expects {} parameters, but {} were given",
+                statement.params().len(),
+                self.parameters.len()
+            )));
+        }
+        let bound = self
+            .parameters
+            .iter()
+            .zip(statement.params())
+            .map(|(value, param_type)| bind_parameter(value, param_type))
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+        let params: Vec<&(dyn ToSql + Sync)> = bound.iter().map(|param| param.as_ref()).collect();
+
+        let indices = self
+            .schema
+            .iter()
+            .map(|definition| {
+                statement
+                    .columns()
+                    .iter()
+                    .position(|column| column.name() == definition.column_name)
+                    .ok_or_else(|| {
+                        RustyPipesError::LoaderError(format!(
+                            "Query does not return column {}",
+                            definition.column_name
+                        ))
+                    })
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+
+        let portal = transaction.bind(&statement, &params).map_err(loader_err)?;
+        loop {
+            self.cancellation.check()?;
+            let rows = transaction
+                .query_portal(&portal, self.batch_size)
+                .map_err(loader_err)?;
+            if rows.is_empty() {
+                break;
+            }
+            let mut df = Vec::with_capacity(rows.len());
+            for result in rows {
+                let mut row: Row = HashMap::new();
+                for (definition, index) in self.schema.iter().zip(&indices) {
+                    let value = read_value(&result, *index)?;
+                    row.insert(
+                        definition.column_name.clone(),
+                        convert_value(value, definition)?,
+                    );
+                }
+                df.push(row);
+            }
+            if !on_batch(df) {
+                break;
+            }
+        }
+        transaction.commit().map_err(loader_err)?;
+        Ok(())
+    }
+}
+
+impl Loader for PostgresLoader<'_> {
+    fn load(&self) -> RustyPipesResult<Dataframe> {
+        let mut df = vec![];
+        self.fetch_batches(|batch| {
+            df.extend(batch);
+            true
+        })?;
+        Ok(df)
+    }
+
+    /// Yield a batch for each fetch from the cursor, fetching at most batch_size rows at a time (or fewer, when the
+    /// batch size of this loader is smaller), and at least one row at a time. The rows are fetched on another thread,
+    /// which holds the connection, and which fetches the next batch only once the previous one has been taken. When the
+    /// batches are dropped before they are exhausted, the query is abandoned after the current fetch.
+    fn load_batches<'b>(self: Box<Self>, batch_size: usize) -> RustyPipesResult<Batches<'b>>
+    where
+        Self: 'b,
+    {
+        let PostgresLoader {
+            connection,
+            query,
+            parameters,
+            schema,
+            batch_size: fetch_size,
+            cancellation,
+        } = *self;
+        let (query, schema) = (query.to_owned(), schema.clone());
+        let batch_size = fetch_size.min(i32::try_from(batch_size.max(1)).unwrap_or(i32::MAX));
+        let (sender, receiver) = mpsc::sync_channel(0);
+        std::thread::spawn(move || {
+            let loader = PostgresLoader {
+                connection,
+                query: &query,
+                parameters,
+                schema: &schema,
+                batch_size,
+                cancellation,
+            };
+            if let Err(err) = loader.fetch_batches(|batch| sender.send(Ok(batch)).is_ok()) {
+                // The receiver may have been dropped already, in which case nobody is interested in the error.
+                let _ = sender.send(Err(err));
+            }
+        });
+        Ok(Box::new(receiver.into_iter()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The tests that require a database are ignored by default, and run against the Postgres instance identified by
+    /// this environment variable.
+    const DATABASE_URL: &str = "RUSTY_PIPES_POSTGRES_URL";
+
+    fn database_url() -> String {
+        std::env::var(DATABASE_URL).unwrap_or_else(|_| panic!("{} is not set", DATABASE_URL))
+    }
+
+    fn schema() -> Vec<ColumnDefinition> {
+        vec![
+            ColumnDefinition {
+                column_name: String::from("name"),
+                data_type: DataType::String,
+                required: true,
+            },
+            ColumnDefinition {
+                column_name: String::from("salary"),
+                data_type: DataType::Decimal,
+                required: false,
+            },
+        ]
+    }
+
+    #[test]
+    fn resolves_connection_from_context() {
+        let schema = schema();
+        let context = Context::new(HashMap::from([(
+            String::from("pg"),
+            String::from("host=localhost"),
+        )]));
+
+        let loader = PostgresLoader::new(":pg", "SELECT 1", &[], &schema, &context).unwrap();
+
+        assert_eq!(loader.connection, "host=localhost");
+    }
+
+    #[test]
+    fn unresolved_parameter_errors() {
+        let schema = schema();
+        let parameters = vec![String::from(":missing")];
+
+        let loader = PostgresLoader::new(
+            "host=localhost",
+            "SELECT 1",
+            &parameters,
+            &schema,
+            &Default::default(),
+        );
+
+        assert!(loader.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message.contains("Unable to resolve parameter :missing"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn reads_numeric_values() {
+        let numeric = |raw: &[u8]| Numeric::from_sql(&Type::NUMERIC, raw).unwrap().0;
+
+        // 12345.678: digits 1, 2345 and 6780, with a weight of 1.
+        assert_eq!(
+            numeric(&[0, 3, 0, 1, 0, 0, 0, 3, 0, 1, 0x09, 0x29, 0x1A, 0x7C]),
+            12345.678
+        );
+        // -0.0005: the single digit 5, with a weight of -1.
+        assert_eq!(numeric(&[0, 1, 0xFF, 0xFF, 0x40, 0, 0, 4, 0, 5]), -0.0005);
+        assert_eq!(numeric(&[0, 0, 0, 0, 0, 0, 0, 0]), 0.0);
+        assert!(numeric(&[0, 0, 0, 0, 0xC0, 0, 0, 0]).is_nan());
+        assert!(Numeric::from_sql(&Type::NUMERIC, &[0, 1, 0, 0, 0, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    #[ignore = "requires a Postgres instance at RUSTY_PIPES_POSTGRES_URL"]
+    fn loads_query_result_in_batches() {
+        let url = database_url();
+        let mut client = Client::connect(&url, NoTls).unwrap();
+        client
+            .batch_execute(
+                "DROP TABLE IF EXISTS rusty_pipes_people;
+                 CREATE TABLE rusty_pipes_people (name TEXT, salary INTEGER);
+                 INSERT INTO rusty_pipes_people VALUES ('Jen', 19319), ('Dave', 100), ('Roy', NULL);",
+            )
+            .unwrap();
+        let schema = schema();
+        let parameters = vec![String::from(":minimum")];
+        let context = Context::new(HashMap::from([
+            (String::from("url"), url),
+            (String::from("minimum"), String::from("0")),
+        ]));
+
+        let result = PostgresLoader::new(
+            ":url",
+            "SELECT name, salary FROM rusty_pipes_people WHERE salary > $1 OR salary IS NULL ORDER BY name",
+            &parameters,
+            &schema,
+            &context,
+        )
+        .unwrap()
+        .with_batch_size(1)
+        .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Dave"))
+                    ),
+                    (String::from("salary"), ColumnValue::Decimal(100.0)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Jen"))
+                    ),
+                    (String::from("salary"), ColumnValue::Decimal(19319.0)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Roy"))
+                    ),
+                    (String::from("salary"), ColumnValue::None),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a Postgres instance at RUSTY_PIPES_POSTGRES_URL"]
+    fn yields_a_batch_per_fetch() {
+        let url = database_url();
+        let schema = vec![ColumnDefinition {
+            column_name: String::from("n"),
+            data_type: DataType::Integer,
+            required: true,
+        }];
+        let loader = |batch_size| {
+            Box::new(
+                PostgresLoader::new(
+                    &url,
+                    "SELECT n FROM generate_series(1, 5) AS n",
+                    &[],
+                    &schema,
+                    &Default::default(),
+                )
+                .unwrap()
+                .with_batch_size(batch_size),
+            )
+        };
+        let sizes = |batches: Batches| {
+            batches
+                .map(|batch| batch.unwrap().len())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sizes(loader(10_000).load_batches(2).unwrap()),
+            vec![2, 2, 1]
+        );
+        assert_eq!(sizes(loader(1).load_batches(2).unwrap()), vec![1; 5]);
+        assert_eq!(sizes(loader(0).load_batches(2).unwrap()), vec![1; 5]);
+        assert_eq!(sizes(loader(10_000).load_batches(0).unwrap()), vec![1; 5]);
+        let mut batches = loader(10_000).load_batches(2).unwrap();
+        assert_eq!(
+            batches.next().unwrap().unwrap()[0],
+            HashMap::from([(String::from("n"), ColumnValue::Integer(1))])
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a Postgres instance at RUSTY_PIPES_POSTGRES_URL"]
+    fn maps_numeric_boolean_and_temporal_types() {
+        let url = database_url();
+        let column = |name: &str, data_type: DataType| ColumnDefinition {
+            column_name: String::from(name),
+            data_type,
+            required: true,
+        };
+        let schema = vec![
+            column("amount", DataType::Decimal),
+            column("active", DataType::Integer),
+            column("day", DataType::String),
+            column("created", DataType::String),
+            column("updated", DataType::String),
+        ];
+
+        let result = PostgresLoader::new(
+            &url,
+            "SELECT 1234.5::NUMERIC AS amount, true AS active, DATE '2024-01-31' AS day,
+                    TIMESTAMP '2024-01-31 12:30:00.25' AS created,
+                    TIMESTAMPTZ '2024-01-31 12:30:00+02' AS updated",
+            &[],
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![HashMap::from([
+                (String::from("amount"), ColumnValue::Decimal(1234.5)),
+                (String::from("active"), ColumnValue::Integer(1)),
+                (
+                    String::from("day"),
+                    ColumnValue::String(String::from("2024-01-31"))
+                ),
+                (
+                    String::from("created"),
+                    ColumnValue::String(String::from("2024-01-31T12:30:00.250"))
+                ),
+                (
+                    String::from("updated"),
+                    ColumnValue::String(String::from("2024-01-31T10:30:00Z"))
+                ),
+            ])]
+        );
+    }
+
+    #[test]
+    #[ignore = "requires a Postgres instance at RUSTY_PIPES_POSTGRES_URL"]
+    fn unsupported_type_errors() {
+        let url = database_url();
+        let schema = vec![ColumnDefinition {
+            column_name: String::from("created"),
+            data_type: DataType::String,
+            required: true,
+        }];
+
+        let result = PostgresLoader::new(
+            &url,
+            "SELECT INTERVAL '1 day' AS created",
+            &[],
+            &schema,
+            &Default::default(),
+        )
+        .unwrap()
+        .load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) => message.contains("Unsupported Postgres type"),
+            _ => false,
+        }));
+    }
+}
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, path::Path};
+
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+
+use crate::core::{
+    cancellation::CancellationToken,
+    context::Context,
+    dataframe::{ColumnValue, Dataframe, Row},
+    definitions::{ColumnDefinition, DataType},
+    error::RustyPipesError,
+    loader::Loader,
+    result::RustyPipesResult,
+};
+
+fn loader_err(e: rusqlite::Error) -> RustyPipesError {
+    RustyPipesError::LoaderError(e.to_string())
+}
+
+fn convert_value(value: ValueRef, definition: &ColumnDefinition) -> RustyPipesResult<ColumnValue> {
+    let mismatch = || {
+        RustyPipesError::LoaderError(format!(
+            "Unable to convert value of column {} to {:?}",
+            definition.column_name, definition.data_type
+        ))
+    };
+    let converted = match (value, &definition.data_type) {
+        (ValueRef::Null, _) if definition.required => {
+            return Err(RustyPipesError::LoaderError(format!(
+                "Missing value for required column {}",
+                definition.column_name
+            )))
+        }
+        (ValueRef::Null, _) => ColumnValue::None,
+        (ValueRef::Integer(i), DataType::Integer) => ColumnValue::Integer(i),
+        (ValueRef::Integer(i), DataType::Decimal) => ColumnValue::Decimal(i as f64),
+        (ValueRef::Integer(i), DataType::String) => ColumnValue::String(i.to_string()),
+        (ValueRef::Real(r), DataType::Decimal) => ColumnValue::Decimal(r),
+        (ValueRef::Real(r), DataType::String) => ColumnValue::String(r.to_string()),
+        (ValueRef::Text(t), data_type) => {
+            let text = std::str::from_utf8(t).map_err(|_| mismatch())?;
+            match data_type {
+                DataType::Integer => {
+                    ColumnValue::Integer(text.parse::<i64>().map_err(|_| mismatch())?)
+                }
+                DataType::Decimal => {
+                    ColumnValue::Decimal(text.parse::<f64>().map_err(|_| mismatch())?)
+                }
+                DataType::String => ColumnValue::String(text.to_owned()),
+            }
+        }
+        _ => return Err(mismatch()),
+    };
+    Ok(converted)
+}
+
+/// A loader that runs a SQL query against a SQLite database, mapping the columns of the result onto the schema by
+/// name.
+pub struct SqliteLoader<'a> {
+    path: &'a Path,
+    query: &'a str,
+    schema: &'a Vec<ColumnDefinition>,
+    cancellation: CancellationToken,
+}
+
+impl<'a> SqliteLoader<'a> {
+    /// Construct a new SQLite loader for the given database path and query, using the specified schema. Loading stops
+    /// with an error once the run is cancelled or times out; this is checked for each row. This is a lazy operation;
+    /// until the "load" method is run, no work will be performed.
+    pub fn new(
+        path: &'a Path,
+        query: &'a str,
+        schema: &'a Vec<ColumnDefinition>,
+        context: &Context,
+    ) -> Self {
+        SqliteLoader {
+            path,
+            query,
+            schema,
+            cancellation: context.cancellation().clone(),
+        }
+    }
+}
+
+impl Loader for SqliteLoader<'_> {
+    fn load(&self) -> RustyPipesResult<Dataframe> {
+        let connection = Connection::open_with_flags(self.path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(loader_err)?;
+        let mut statement = connection.prepare(self.query).map_err(loader_err)?;
+        let indices = self
+            .schema
+            .iter()
+            .map(|definition| {
+                statement
+                    .column_index(&definition.column_name)
+                    .map_err(|_| {
+                        RustyPipesError::LoaderError(format!(
+                            "Query does not return column {}",
+                            definition.column_name
+                        ))
+                    })
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+
+        let mut rows = statement.query([]).map_err(loader_err)?;
+        let mut df = vec![];
+        while let Some(result) = rows.next().map_err(loader_err)? {
+            self.cancellation.check()?;
+            let mut row: Row = HashMap::new();
+            for (definition, index) in self.schema.iter().zip(&indices) {
+                let value = result.get_ref(*index).map_err(loader_err)?;
+                row.insert(
+                    definition.column_name.clone(),
+                    convert_value(value, definition)?,
+                );
+            }
+            df.push(row);
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn database(dir: &Path) -> PathBuf {
+        let path = dir.join("database.sqlite");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE people (name TEXT, age INTEGER, salary REAL);
+                 INSERT INTO people VALUES ('Jen', 30, 19319.5), ('Dave', NULL, 100);",
+            )
+            .unwrap();
+        path
+    }
+
+    fn schema(age_required: bool) -> Vec<ColumnDefinition> {
+        vec![
+            ColumnDefinition {
+                column_name: String::from("name"),
+                data_type: DataType::String,
+                required: true,
+            },
+            ColumnDefinition {
+                column_name: String::from("age"),
+                data_type: DataType::Integer,
+                required: age_required,
+            },
+            ColumnDefinition {
+                column_name: String::from("salary"),
+                data_type: DataType::Decimal,
+                required: true,
+            },
+        ]
+    }
+
+    #[test]
+    fn loads_query_result() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = database(dir.path());
+        let schema = schema(false);
+
+        let result = SqliteLoader::new(
+            &path,
+            "SELECT salary, age, name FROM people ORDER BY name",
+            &schema,
+            &Context::default(),
+        )
+        .load();
+
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Dave"))
+                    ),
+                    (String::from("age"), ColumnValue::None),
+                    (String::from("salary"), ColumnValue::Decimal(100.0)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Jen"))
+                    ),
+                    (String::from("age"), ColumnValue::Integer(30)),
+                    (String::from("salary"), ColumnValue::Decimal(19319.5)),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_required_value_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = database(dir.path());
+        let schema = schema(true);
+
+        let result =
+            SqliteLoader::new(&path, "SELECT * FROM people", &schema, &Context::default()).load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) => message.contains("Missing value"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn missing_column_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = database(dir.path());
+        let schema = schema(false);
+
+        let result = SqliteLoader::new(
+            &path,
+            "SELECT name FROM people",
+            &schema,
+            &Context::default(),
+        )
+        .load();
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::LoaderError(message) =>
+                message.contains("Query does not return column age"),
+            _ => false,
+        }));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::core::error::RustyPipesError;
+
+/// An event that occurs while the engine runs a pipeline. Events of different sources and transformations may be
+/// interleaved, as these are loaded and run in parallel.
+#[derive(Debug, Clone)]
+pub enum Event<'a> {
+    /// A run is started, which loads the given number of sources and runs the given number of transformations.
+    RunStarted {
+        sources: usize,
+        transformations: usize,
+    },
+    /// A run is finished.
+    RunFinished { duration: Duration },
+    /// A source is started to be loaded.
+    SourceStarted { source: &'a str },
+    /// A source is loaded, yielding the given number of rows, of the given estimated size in memory (for a streamed
+    /// source, that of its largest batch). A cached source is read from the cache rather than loaded by its loader.
+    SourceFinished {
+        source: &'a str,
+        rows: usize,
+        bytes: usize,
+        duration: Duration,
+        cached: bool,
+    },
+    /// A source failed to load.
+    SourceFailed {
+        source: &'a str,
+        error: &'a RustyPipesError,
+        duration: Duration,
+    },
+    /// A transformation is started to be run.
+    TransformationStarted { transformation: &'a str },
+    /// A transformation is finished, yielding data frames with the given number of rows in total. The output of a
+    /// cached transformation is read from the cache rather than computed.
+    TransformationFinished {
+        transformation: &'a str,
+        rows: usize,
+        duration: Duration,
+        cached: bool,
+    },
+    /// A transformation failed, either in one of its operations or when writing its output to its destination.
+    TransformationFailed {
+        transformation: &'a str,
+        error: &'a RustyPipesError,
+        duration: Duration,
+    },
+    /// An operation of a transformation (e.g., "Filter") is started.
+    OperationStarted {
+        transformation: &'a str,
+        operation: &'a str,
+    },
+    /// An operation of a transformation is finished, having processed input data frames with the given number of rows
+    /// in total into output data frames with the given number of rows, and estimated size in memory, in total. For
+    /// operations that process a streamed source, these are the totals over all batches.
+    OperationFinished {
+        transformation: &'a str,
+        operation: &'a str,
+        rows_in: usize,
+        rows_out: usize,
+        bytes_out: usize,
+        duration: Duration,
+    },
+}
+
+/// An observer of the events that occur while the engine runs a pipeline (see [`Engine::register_observer`]). Events
+/// are delivered from the threads on which they occur, so observers must be thread-safe, and should return quickly.
+///
+/// [`Engine::register_observer`]: crate::engine::Engine::register_observer
+pub trait Observer: Send + Sync {
+    fn on_event(&self, event: &Event<'_>);
+}
+
+impl<O: Observer + ?Sized> Observer for Arc<O> {
+    fn on_event(&self, event: &Event<'_>) {
+        (**self).on_event(event);
+    }
+}
+
+#[derive(Default)]
+struct Progress {
+    sources: usize,
+    transformations: usize,
+    sources_done: usize,
+    transformations_done: usize,
+}
+
+/// An observer that reports the progress of a run as lines of text, to the standard error stream by default: each line
+/// describes a source or transformation that finished or failed, prefixed by the number of sources and transformations
+/// that are done out of the total.
+pub struct ProgressReporter {
+    writer: Mutex<Box<dyn Write + Send>>,
+    progress: Mutex<Progress>,
+}
+
+impl Default for ProgressReporter {
+    fn default() -> Self {
+        ProgressReporter::new()
+    }
+}
+
+impl ProgressReporter {
+    /// Construct a reporter that writes to the standard error stream.
+    pub fn new() -> Self {
+        ProgressReporter::with_writer(std::io::stderr())
+    }
+
+    /// Construct a reporter that writes to the given writer instead.
+    pub fn with_writer(writer: impl Write + Send + 'static) -> Self {
+        ProgressReporter {
+            writer: Mutex::new(Box::new(writer)),
+            progress: Mutex::new(Progress::default()),
+        }
+    }
+
+    fn report(&self, progress: &Progress, message: std::fmt::Arguments) {
+        let mut writer = self.writer.lock().unwrap();
+        // Progress is reported on a best-effort basis; a failure to write it does not affect the run.
+        let _ = writeln!(
+            writer,
+            "[sources {}/{}, transformations {}/{}] {}",
+            progress.sources_done,
+            progress.sources,
+            progress.transformations_done,
+            progress.transformations,
+            message
+        );
+    }
+}
+
+fn cached(cached: bool) -> &'static str {
+    if cached {
+        " (cached)"
+    } else {
+        ""
+    }
+}
+
+impl Observer for ProgressReporter {
+    fn on_event(&self, event: &Event<'_>) {
+        let mut progress = self.progress.lock().unwrap();
+        match event {
+            Event::RunStarted {
+                sources,
+                transformations,
+            } => {
+                *progress = Progress {
+                    sources: *sources,
+                    transformations: *transformations,
+                    ..Progress::default()
+                };
+            }
+            Event::RunFinished { duration } => {
+                self.report(&progress, format_args!("finished in {:.2?}", duration))
+            }
+            Event::SourceFinished {
+                source,
+                rows,
+                duration,
+                cached: is_cached,
+                ..
+            } => {
+                progress.sources_done += 1;
+                self.report(
+                    &progress,
+                    format_args!(
+                        "loaded {}: {} rows in {:.2?}{}",
+                        source,
+                        rows,
+                        duration,
+                        cached(*is_cached)
+                    ),
+                );
+            }
+            Event::SourceFailed { source, error, .. } => {
+                progress.sources_done += 1;
+                self.report(
+                    &progress,
+                    format_args!("failed to load {}: {}", source, error),
+                );
+            }
+            Event::TransformationFinished {
+                transformation,
+                rows,
+                duration,
+                cached: is_cached,
+            } => {
+                progress.transformations_done += 1;
+                self.report(
+                    &progress,
+                    format_args!(
+                        "ran {}: {} rows in {:.2?}{}",
+                        transformation,
+                        rows,
+                        duration,
+                        cached(*is_cached)
+                    ),
+                );
+            }
+            Event::TransformationFailed {
+                transformation,
+                error,
+                ..
+            } => {
+                progress.transformations_done += 1;
+                self.report(
+                    &progress,
+                    format_args!("failed to run {}: {}", transformation, error),
+                );
+            }
+            Event::SourceStarted { .. }
+            | Event::TransformationStarted { .. }
+            | Event::OperationStarted { .. }
+            | Event::OperationFinished { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reports_progress() {
+        let buffer = Buffer::default();
+        let reporter = ProgressReporter::with_writer(buffer.clone());
+        let error = RustyPipesError::TransformationError(String::from("Unknown source people"));
+
+        reporter.on_event(&Event::RunStarted {
+            sources: 1,
+            transformations: 2,
+        });
+        reporter.on_event(&Event::SourceStarted { source: "people" });
+        reporter.on_event(&Event::SourceFinished {
+            source: "people",
+            rows: 3,
+            bytes: 300,
+            duration: Duration::from_millis(5),
+            cached: false,
+        });
+        reporter.on_event(&Event::TransformationFinished {
+            transformation: "adults",
+            rows: 2,
+            duration: Duration::from_millis(1),
+            cached: true,
+        });
+        reporter.on_event(&Event::TransformationFailed {
+            transformation: "joined",
+            error: &error,
+            duration: Duration::ZERO,
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            output.lines().collect::<Vec<_>>(),
+            vec![
+                "[sources 1/1, transformations 0/2] loaded people: 3 rows in 5.00ms",
+                "[sources 1/1, transformations 1/2] ran adults: 2 rows in 1.00ms (cached)",
+                "[sources 1/1, transformations 2/2] failed to run joined: Unknown source people",
+            ]
+        );
+    }
+}
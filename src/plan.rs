@@ -0,0 +1,712 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::PathBuf,
+};
+
+use crate::core::{
+    dataframe::Dataframe,
+    definitions::{
+        JoinStrategy, KeyCoercion, Operation, PipelineDefinition, ScriptLanguage, ScriptMode,
+        Source,
+    },
+};
+
+/// Explanations of logical plans, in human-readable, JSON and Graphviz DOT form
+pub mod explain;
+/// Rules that rewrite a logical plan into an equivalent plan that performs less work
+pub mod optimizer;
+
+/// The names of the columns of a data frame, or None when they cannot be determined (e.g., for the output of a script)
+/// or, when describing the columns that are required of a data frame, when all of its columns are required.
+pub type Columns = Option<BTreeSet<String>>;
+
+/// A node in the logical plan of a transformation. Each node yields a list of data frames, and takes the data frames
+/// yielded by its inputs, in order, as the input for its operation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlanNode {
+    /// The data frame of a source.
+    Scan { source: String },
+    /// The rows of the first input data frame for which all predicates are true.
+    Filter {
+        predicates: Vec<String>,
+        inputs: Vec<PlanNode>,
+    },
+    /// The given columns of the rows of the first input data frame.
+    Select {
+        columns: Vec<String>,
+        inputs: Vec<PlanNode>,
+    },
+    /// The inner join of the first two input data frames.
+    InnerJoin {
+        on: String,
+        preserve_order: bool,
+        strategy: JoinStrategy,
+        coercion: KeyCoercion,
+        match_nulls: bool,
+        inputs: Vec<PlanNode>,
+    },
+    /// Any other operation, which the optimizer treats as opaque.
+    Operation {
+        operation: OpaqueOperation,
+        inputs: Vec<PlanNode>,
+    },
+}
+
+/// An operation without a dedicated plan node, of which the optimizer does not know which rows or columns it yields.
+/// The fields are those of the operation of the same name (see [`Operation`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpaqueOperation {
+    Script {
+        language: ScriptLanguage,
+        code: String,
+        mode: ScriptMode,
+        max_operations: u64,
+    },
+    Wasm {
+        path: PathBuf,
+        config: serde_json::Value,
+        fuel: u64,
+        max_memory_bytes: usize,
+    },
+    Custom {
+        name: String,
+        config: serde_json::Value,
+    },
+}
+
+impl PlanNode {
+    fn from_operation(operation: &Operation, inputs: Vec<PlanNode>) -> Self {
+        match operation {
+            Operation::Filter { predicate } => PlanNode::Filter {
+                predicates: vec![predicate.clone()],
+                inputs,
+            },
+            Operation::Select { columns } => PlanNode::Select {
+                columns: columns.clone(),
+                inputs,
+            },
+            Operation::InnerJoin {
+                on,
+                preserve_order,
+                strategy,
+                coercion,
+                match_nulls,
+            } => PlanNode::InnerJoin {
+                on: on.clone(),
+                preserve_order: *preserve_order,
+                strategy: *strategy,
+                coercion: *coercion,
+                match_nulls: *match_nulls,
+                inputs,
+            },
+            Operation::Script {
+                language,
+                code,
+                mode,
+                max_operations,
+            } => PlanNode::Operation {
+                operation: OpaqueOperation::Script {
+                    language: *language,
+                    code: code.clone(),
+                    mode: *mode,
+                    max_operations: *max_operations,
+                },
+                inputs,
+            },
+            Operation::Wasm {
+                path,
+                config,
+                fuel,
+                max_memory_bytes,
+            } => PlanNode::Operation {
+                operation: OpaqueOperation::Wasm {
+                    path: path.clone(),
+                    config: config.clone(),
+                    fuel: *fuel,
+                    max_memory_bytes: *max_memory_bytes,
+                },
+                inputs,
+            },
+            Operation::Custom { name, config } => PlanNode::Operation {
+                operation: OpaqueOperation::Custom {
+                    name: name.clone(),
+                    config: config.clone(),
+                },
+                inputs,
+            },
+        }
+    }
+
+    /// The inputs of this node.
+    pub fn inputs(&self) -> &[PlanNode] {
+        match self {
+            PlanNode::Scan { .. } => &[],
+            PlanNode::Filter { inputs, .. }
+            | PlanNode::Select { inputs, .. }
+            | PlanNode::InnerJoin { inputs, .. }
+            | PlanNode::Operation { inputs, .. } => inputs,
+        }
+    }
+
+    fn inputs_mut(&mut self) -> Option<&mut Vec<PlanNode>> {
+        match self {
+            PlanNode::Scan { .. } => None,
+            PlanNode::Filter { inputs, .. }
+            | PlanNode::Select { inputs, .. }
+            | PlanNode::InnerJoin { inputs, .. }
+            | PlanNode::Operation { inputs, .. } => Some(inputs),
+        }
+    }
+
+    /// The name of the operation of this node, e.g., "Filter".
+    pub fn operation_name(&self) -> &'static str {
+        match self {
+            PlanNode::Scan { .. } => "Scan",
+            PlanNode::Filter { .. } => "Filter",
+            PlanNode::Select { .. } => "Select",
+            PlanNode::InnerJoin { .. } => "InnerJoin",
+            PlanNode::Operation { operation, .. } => match operation {
+                OpaqueOperation::Script { .. } => "Script",
+                OpaqueOperation::Wasm { .. } => "Wasm",
+                OpaqueOperation::Custom { .. } => "Custom",
+            },
+        }
+    }
+
+    /// The number of data frames that this node yields, or None if it cannot be determined.
+    pub fn output_count(&self) -> Option<usize> {
+        match self {
+            PlanNode::Operation {
+                operation: OpaqueOperation::Script { .. },
+                ..
+            } => Some(1),
+            PlanNode::Operation { .. } => None,
+            _ => Some(1),
+        }
+    }
+
+    /// Whether the operation of this node processes the rows of its first input independently of each other, so that
+    /// it can process that input in batches as they are loaded. This holds for Filters, Selects, InnerJoins (of which
+    /// the first input is the probe side) and Scripts that map or filter rows. The other operations are blocking: they
+    /// need all rows of their inputs at once. The inputs beyond the first, such as the build side of a join, are
+    /// always loaded in full.
+    pub fn is_streaming(&self) -> bool {
+        match self {
+            PlanNode::Filter { .. } | PlanNode::Select { .. } | PlanNode::InnerJoin { .. } => true,
+            PlanNode::Operation {
+                operation: OpaqueOperation::Script { mode, .. },
+                ..
+            } => matches!(mode, ScriptMode::Map | ScriptMode::Filter),
+            PlanNode::Scan { .. } | PlanNode::Operation { .. } => false,
+        }
+    }
+
+    /// The source whose rows can be streamed through this node: the source of a scan, or the source that can be
+    /// streamed through the first input of a streaming node.
+    pub fn streamed_source(&self) -> Option<&str> {
+        match self {
+            PlanNode::Scan { source } => Some(source),
+            node if node.is_streaming() => node.inputs().first()?.streamed_source(),
+            _ => None,
+        }
+    }
+
+    /// The source that this node scans directly as the build side (the second input) of a hash join, if any.
+    pub fn build_source(&self) -> Option<&str> {
+        match self {
+            PlanNode::InnerJoin {
+                strategy: JoinStrategy::Hash,
+                inputs,
+                ..
+            } => match inputs.get(1)? {
+                PlanNode::Scan { source } => Some(source),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The names of the sources that this node scans, either itself or through its inputs.
+    pub fn sources(&self) -> BTreeSet<&str> {
+        match self {
+            PlanNode::Scan { source } => BTreeSet::from([source.as_str()]),
+            node => node.inputs().iter().flat_map(PlanNode::sources).collect(),
+        }
+    }
+
+    /// The names of the columns of the data frame that this node yields, based on the columns of each source as
+    /// given by the catalog. None if they cannot be determined, or if this node does not yield a single data frame.
+    pub fn columns(&self, catalog: &HashMap<String, BTreeSet<String>>) -> Columns {
+        match self {
+            PlanNode::Scan { source } => catalog.get(source).cloned(),
+            PlanNode::Filter { inputs, .. } => first_input_columns(inputs, catalog),
+            PlanNode::Select { columns, inputs } => {
+                let selected = columns.iter().cloned().collect::<BTreeSet<_>>();
+                match first_input_columns(inputs, catalog) {
+                    Some(input_columns) => Some(&selected & &input_columns),
+                    None => Some(selected),
+                }
+            }
+            PlanNode::InnerJoin { inputs, .. } => match inputs.as_slice() {
+                [left, right]
+                    if left.output_count() == Some(1) && right.output_count() == Some(1) =>
+                {
+                    Some(&left.columns(catalog)? | &right.columns(catalog)?)
+                }
+                _ => None,
+            },
+            PlanNode::Operation { .. } => None,
+        }
+    }
+}
+
+fn first_input_columns(
+    inputs: &[PlanNode],
+    catalog: &HashMap<String, BTreeSet<String>>,
+) -> Columns {
+    match inputs.first() {
+        Some(input) if input.output_count() == Some(1) => input.columns(catalog),
+        _ => None,
+    }
+}
+
+/// The logical plan for a pipeline: the sources that need to be loaded, and a tree of operations for each
+/// transformation. Building a plan does not perform any work; the plan describes what the engine will do when it is
+/// run, which allows it to be optimized beforehand (see [`LogicalPlan::optimize`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalPlan {
+    /// The sources that need to be loaded, along with the columns that are required of each.
+    pub sources: BTreeMap<String, Columns>,
+    /// The root node of the plan of each transformation, keyed by the name of the transformation. None for a
+    /// transformation without any operations.
+    pub transformations: BTreeMap<String, Option<PlanNode>>,
+    /// The names of the columns of each source, as far as they are known.
+    pub catalog: HashMap<String, BTreeSet<String>>,
+}
+
+impl LogicalPlan {
+    /// Build the (unoptimized) logical plan for a pipeline definition. Registered data frames take the place of the
+    /// sources with the same name, which then need not be loaded.
+    pub fn new(
+        definition: &PipelineDefinition,
+        registered_dataframes: &HashMap<String, Dataframe>,
+    ) -> Self {
+        let mut catalog = HashMap::new();
+        for (name, source) in &definition.sources {
+            // Custom loaders are not bound to yield exactly the columns of their schema.
+            if let Source::Custom { .. } = source.source {
+                continue;
+            }
+            let mut columns = source
+                .schema
+                .iter()
+                .map(|column| column.column_name.clone())
+                .collect::<BTreeSet<_>>();
+            if let Source::File {
+                filename_column: Some(filename_column),
+                ..
+            } = &source.source
+            {
+                columns.insert(filename_column.clone());
+            }
+            catalog.insert(name.clone(), columns);
+        }
+        for (name, df) in registered_dataframes {
+            let columns = df.iter().flat_map(|row| row.keys().cloned()).collect();
+            catalog.insert(name.clone(), columns);
+        }
+
+        let transformations = definition
+            .transformations
+            .iter()
+            .map(|(name, transformation)| {
+                let mut root = None;
+                for operation in &transformation.operations {
+                    let inputs = match root.take() {
+                        Some(node) => vec![node],
+                        None => transformation
+                            .sources
+                            .iter()
+                            .map(|source| PlanNode::Scan {
+                                source: source.clone(),
+                            })
+                            .collect(),
+                    };
+                    root = Some(PlanNode::from_operation(operation, inputs));
+                }
+                (name.clone(), root)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        let sources = transformations
+            .values()
+            .flatten()
+            .flat_map(PlanNode::sources)
+            .filter(|source| {
+                definition.sources.contains_key(*source)
+                    && !registered_dataframes.contains_key(*source)
+            })
+            .map(|source| (source.to_owned(), None))
+            .collect();
+
+        LogicalPlan {
+            sources,
+            transformations,
+            catalog,
+        }
+    }
+
+    /// The sources that can be streamed, i.e., the sources to be loaded that are only ever scanned as the first input
+    /// of a chain of streaming operations (see [`PlanNode::is_streaming`]). Such sources need not be loaded in full.
+    pub fn streamed_sources(&self) -> BTreeSet<String> {
+        let mut streamed = BTreeSet::new();
+        let mut blocked = BTreeSet::new();
+        for root in self.transformations.values().flatten() {
+            classify_scans(root, false, &mut streamed, &mut blocked);
+        }
+        streamed
+            .difference(&blocked)
+            .filter(|source| self.sources.contains_key(*source))
+            .cloned()
+            .collect()
+    }
+
+    /// The sources that can be spilled, i.e., the sources to be loaded that are only ever scanned as the build side of
+    /// a hash join (see [`PlanNode::build_source`]). Such sources need not be loaded in full either: they can be loaded
+    /// in batches that are partitioned to disk (see [`crate::engine::Engine::with_memory_budget`]).
+    pub fn spilled_sources(&self) -> BTreeSet<String> {
+        let mut spilled = BTreeSet::new();
+        let mut blocked = BTreeSet::new();
+        for root in self.transformations.values().flatten() {
+            classify_build_scans(root, false, &mut spilled, &mut blocked);
+        }
+        spilled
+            .difference(&blocked)
+            .filter(|source| self.sources.contains_key(*source))
+            .cloned()
+            .collect()
+    }
+
+    /// Restrict this plan to the transformations with the given names, and to the sources that these scan. Names that
+    /// do not refer to a transformation of this plan are ignored.
+    pub fn select(mut self, names: &[&str]) -> Self {
+        self.transformations
+            .retain(|name, _| names.contains(&name.as_str()));
+        let scanned = self
+            .transformations
+            .values()
+            .flatten()
+            .flat_map(PlanNode::sources)
+            .collect::<BTreeSet<_>>();
+        self.sources
+            .retain(|source, _| scanned.contains(source.as_str()));
+        self
+    }
+
+    /// Optimize this plan, yielding an equivalent plan that performs less work (see the [`optimizer`] module).
+    pub fn optimize(self) -> Self {
+        optimizer::optimize(self)
+    }
+}
+
+/// Record each scanned source as streamed when it is scanned as the first input of a streaming node, and as blocked
+/// otherwise.
+fn classify_scans(
+    node: &PlanNode,
+    streamed_input: bool,
+    streamed: &mut BTreeSet<String>,
+    blocked: &mut BTreeSet<String>,
+) {
+    if let PlanNode::Scan { source } = node {
+        if streamed_input {
+            streamed.insert(source.clone());
+        } else {
+            blocked.insert(source.clone());
+        }
+    }
+    for (index, input) in node.inputs().iter().enumerate() {
+        let streamed_input = index == 0 && node.is_streaming();
+        classify_scans(input, streamed_input, streamed, blocked);
+    }
+}
+
+/// Record each scanned source as spilled when it is scanned as the build side of a hash join, and as blocked
+/// otherwise.
+fn classify_build_scans(
+    node: &PlanNode,
+    build_input: bool,
+    spilled: &mut BTreeSet<String>,
+    blocked: &mut BTreeSet<String>,
+) {
+    if let PlanNode::Scan { source } = node {
+        if build_input {
+            spilled.insert(source.clone());
+        } else {
+            blocked.insert(source.clone());
+        }
+    }
+    for (index, input) in node.inputs().iter().enumerate() {
+        let build_input = index == 1 && node.build_source().is_some();
+        classify_build_scans(input, build_input, spilled, blocked);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scan(source: &str) -> PlanNode {
+        PlanNode::Scan {
+            source: String::from(source),
+        }
+    }
+
+    #[test]
+    fn builds_plan_from_definition() {
+        let raw_definition = r#"{
+            "sources": {
+                "people": {
+                    "schema": [{ "column_name": "id", "data_type": "Integer", "required": true }],
+                    "source": { "type": "File", "format": "Csv", "path": "people.csv", "filename_column": "file" }
+                },
+                "unused": {
+                    "schema": [],
+                    "source": { "type": "Inline", "rows": [] }
+                }
+            },
+            "transformations": {
+                "joined": {
+                    "sources": ["people", "ages"],
+                    "operations": [
+                        { "type": "InnerJoin", "on": "id = id" },
+                        { "type": "Filter", "predicate": "id > 1" }
+                    ]
+                },
+                "empty": { "sources": ["people"], "operations": [] }
+            }
+        }"#;
+        let definition: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let registered = HashMap::from([(String::from("ages"), vec![])]);
+
+        let plan = LogicalPlan::new(&definition, &registered);
+
+        assert_eq!(
+            plan.sources,
+            BTreeMap::from([(String::from("people"), None)])
+        );
+        assert_eq!(
+            plan.transformations,
+            BTreeMap::from([
+                (
+                    String::from("joined"),
+                    Some(PlanNode::Filter {
+                        predicates: vec![String::from("id > 1")],
+                        inputs: vec![PlanNode::InnerJoin {
+                            on: String::from("id = id"),
+                            preserve_order: false,
+                            strategy: JoinStrategy::Hash,
+                            coercion: KeyCoercion::Text,
+                            match_nulls: false,
+                            inputs: vec![scan("people"), scan("ages")],
+                        }],
+                    })
+                ),
+                (String::from("empty"), None),
+            ])
+        );
+        assert_eq!(
+            plan.catalog.get("people"),
+            Some(&BTreeSet::from([String::from("file"), String::from("id")]))
+        );
+    }
+
+    #[test]
+    fn selects_transformations_and_their_sources() {
+        let plan = LogicalPlan {
+            sources: BTreeMap::from([
+                (String::from("events"), None),
+                (String::from("people"), None),
+                (String::from("log"), None),
+            ]),
+            transformations: BTreeMap::from([
+                (
+                    String::from("joined"),
+                    Some(PlanNode::Select {
+                        columns: vec![String::from("id")],
+                        inputs: vec![scan("events"), scan("people")],
+                    }),
+                ),
+                (String::from("empty"), None),
+                (String::from("logged"), Some(scan("log"))),
+            ]),
+            catalog: HashMap::new(),
+        };
+
+        let selected = plan.select(&["joined", "empty", "unknown"]);
+
+        assert_eq!(
+            selected.transformations.keys().collect::<Vec<_>>(),
+            vec!["empty", "joined"]
+        );
+        assert_eq!(
+            selected.sources.keys().collect::<Vec<_>>(),
+            vec!["events", "people"]
+        );
+    }
+
+    #[test]
+    fn determines_streamed_sources() {
+        let script = |mode| PlanNode::Operation {
+            operation: OpaqueOperation::Script {
+                language: Default::default(),
+                code: String::new(),
+                mode,
+                max_operations: 0,
+            },
+            inputs: vec![PlanNode::InnerJoin {
+                on: String::from("id = id"),
+                preserve_order: false,
+                strategy: JoinStrategy::Hash,
+                coercion: KeyCoercion::Text,
+                match_nulls: false,
+                inputs: vec![scan("events"), scan("people")],
+            }],
+        };
+        let plan = LogicalPlan {
+            sources: BTreeMap::from([
+                (String::from("events"), None),
+                (String::from("people"), None),
+                (String::from("log"), None),
+            ]),
+            transformations: BTreeMap::from([
+                (String::from("mapped"), Some(script(ScriptMode::Map))),
+                (
+                    String::from("aggregated"),
+                    Some(script(ScriptMode::Dataframe)),
+                ),
+                (
+                    String::from("filtered"),
+                    Some(PlanNode::Filter {
+                        predicates: vec![String::from("level == error")],
+                        inputs: vec![scan("log")],
+                    }),
+                ),
+            ]),
+            catalog: HashMap::new(),
+        };
+
+        assert_eq!(
+            plan.streamed_sources(),
+            BTreeSet::from([String::from("events"), String::from("log")])
+        );
+        assert_eq!(
+            plan.transformations["mapped"]
+                .as_ref()
+                .unwrap()
+                .streamed_source(),
+            Some("events")
+        );
+        assert_eq!(
+            plan.transformations["aggregated"]
+                .as_ref()
+                .unwrap()
+                .streamed_source(),
+            None
+        );
+    }
+
+    #[test]
+    fn determines_spilled_sources() {
+        let join = |strategy, inputs| PlanNode::InnerJoin {
+            on: String::from("id = id"),
+            preserve_order: false,
+            strategy,
+            coercion: KeyCoercion::Text,
+            match_nulls: false,
+            inputs,
+        };
+        let filtered = PlanNode::Filter {
+            predicates: vec![String::from("level == error")],
+            inputs: vec![scan("log")],
+        };
+        let plan = LogicalPlan {
+            sources: BTreeMap::from([
+                (String::from("events"), None),
+                (String::from("people"), None),
+                (String::from("salaries"), None),
+                (String::from("log"), None),
+            ]),
+            transformations: BTreeMap::from([
+                (
+                    String::from("hashed"),
+                    Some(join(
+                        JoinStrategy::Hash,
+                        vec![scan("events"), scan("people")],
+                    )),
+                ),
+                (
+                    String::from("merged"),
+                    Some(join(
+                        JoinStrategy::SortMerge,
+                        vec![scan("events"), scan("salaries")],
+                    )),
+                ),
+                (
+                    String::from("nested"),
+                    Some(join(JoinStrategy::Hash, vec![scan("events"), filtered])),
+                ),
+            ]),
+            catalog: HashMap::new(),
+        };
+
+        assert_eq!(
+            plan.spilled_sources(),
+            BTreeSet::from([String::from("people")])
+        );
+        assert_eq!(
+            plan.transformations["hashed"]
+                .as_ref()
+                .unwrap()
+                .build_source(),
+            Some("people")
+        );
+        assert_eq!(
+            plan.transformations["nested"]
+                .as_ref()
+                .unwrap()
+                .build_source(),
+            None
+        );
+    }
+
+    #[test]
+    fn infers_columns() {
+        let catalog = HashMap::from([
+            (String::from("left"), BTreeSet::from([String::from("a")])),
+            (String::from("right"), BTreeSet::from([String::from("b")])),
+        ]);
+        let node = PlanNode::Select {
+            columns: vec![String::from("a"), String::from("c")],
+            inputs: vec![PlanNode::InnerJoin {
+                on: String::from("a = b"),
+                preserve_order: false,
+                strategy: JoinStrategy::Hash,
+                coercion: KeyCoercion::Text,
+                match_nulls: false,
+                inputs: vec![scan("left"), scan("right")],
+            }],
+        };
+
+        assert_eq!(
+            node.columns(&catalog),
+            Some(BTreeSet::from([String::from("a")]))
+        );
+        assert_eq!(
+            node.inputs()[0].inputs()[0]
+                .columns(&catalog)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,456 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use serde::Serialize;
+
+use super::{LogicalPlan, OpaqueOperation, PlanNode};
+use crate::core::{
+    dataframe::Dataframe,
+    definitions::{Destination, PipelineDefinition, Source},
+};
+
+/// How a source is loaded, as part of an explanation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SourceExplanation {
+    pub name: String,
+    /// A description of the loader (e.g., "File ./salaries.csv (Csv)").
+    pub loader: String,
+    /// The columns that are loaded, or None if all columns of the schema are loaded.
+    pub columns: Option<Vec<String>>,
+    /// Whether the files of the source are loaded in parallel.
+    pub parallel: bool,
+}
+
+/// A node in the operation tree of a transformation, as part of an explanation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NodeExplanation {
+    /// The kind of operation (e.g., "Filter" or "Scan").
+    pub operation: String,
+    /// The details of the operation (e.g., its predicates).
+    pub detail: String,
+    /// The number of data frames the operation operates on, if known.
+    pub arity: Option<usize>,
+    /// The columns of the data frame the node yields, if known.
+    pub columns: Option<Vec<String>>,
+    /// The join strategy, for joins.
+    pub strategy: Option<String>,
+    /// Whether the node can process its first input in batches in streaming mode. Nodes other than scans that cannot
+    /// are blocking: they need all rows of their inputs at once.
+    pub streaming: bool,
+    pub inputs: Vec<NodeExplanation>,
+}
+
+/// How a transformation is run, as part of an explanation.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TransformationExplanation {
+    pub name: String,
+    /// The root of the operation tree, or None for a transformation without operations.
+    pub root: Option<NodeExplanation>,
+    /// A description of the destination, if any.
+    pub destination: Option<String>,
+}
+
+/// The resolved execution plan of a pipeline: the sources that are loaded and the tree of operations of each
+/// transformation, after optimization. Sources are loaded in parallel, after which the transformations are run in
+/// parallel.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub sources: Vec<SourceExplanation>,
+    pub transformations: Vec<TransformationExplanation>,
+}
+
+fn describe_source(source: &Source) -> (String, bool) {
+    match source {
+        Source::File {
+            path,
+            format,
+            parallel,
+            ..
+        } => (format!("File {} ({:?})", path, format), *parallel),
+        Source::Sqlite { path, .. } => (format!("Sqlite {}", path), false),
+        Source::Postgres { .. } => (String::from("Postgres"), false),
+        Source::Http { url, method, .. } => (format!("Http {:?} {}", method, url), false),
+        Source::Inline { .. } => (String::from("Inline"), false),
+        Source::Custom { name, .. } => (format!("Custom {}", name), false),
+    }
+}
+
+fn describe_destination(destination: &Destination) -> String {
+    match destination {
+        Destination::Sqlite { path, table, mode } => {
+            format!("Sqlite table {} in {} ({:?})", table, path, mode)
+        }
+        Destination::Parquet { path, .. } => format!("Parquet {}", path),
+    }
+}
+
+fn sorted(columns: BTreeSet<String>) -> Vec<String> {
+    columns.into_iter().collect()
+}
+
+fn explain_node(node: &PlanNode, catalog: &HashMap<String, BTreeSet<String>>) -> NodeExplanation {
+    let (detail, arity, strategy) = match node {
+        PlanNode::Scan { source } => (source.clone(), Some(0), None),
+        PlanNode::Filter { predicates, .. } => (predicates.join(" and "), Some(1), None),
+        PlanNode::Select { columns, .. } => (columns.join(", "), Some(1), None),
+        PlanNode::InnerJoin { on, strategy, .. } => {
+            (on.clone(), Some(2), Some(format!("{:?}", strategy)))
+        }
+        PlanNode::Operation { operation, .. } => match operation {
+            OpaqueOperation::Script { language, mode, .. } => {
+                (format!("{:?}, {:?}", language, mode), Some(1), None)
+            }
+            OpaqueOperation::Wasm { path, .. } => (path.display().to_string(), None, None),
+            OpaqueOperation::Custom { name, .. } => (name.clone(), None, None),
+        },
+    };
+    NodeExplanation {
+        operation: node.operation_name().to_owned(),
+        detail,
+        arity,
+        columns: node.columns(catalog).map(sorted),
+        strategy,
+        streaming: node.is_streaming(),
+        inputs: node
+            .inputs()
+            .iter()
+            .map(|input| explain_node(input, catalog))
+            .collect(),
+    }
+}
+
+fn scanned_sources(node: &PlanNode, sources: &mut BTreeSet<String>) {
+    if let PlanNode::Scan { source } = node {
+        sources.insert(source.clone());
+    }
+    for input in node.inputs() {
+        scanned_sources(input, sources);
+    }
+}
+
+/// Explain a logical plan for the given pipeline definition.
+pub fn explain(
+    plan: &LogicalPlan,
+    definition: &PipelineDefinition,
+    registered_dataframes: &HashMap<String, Dataframe>,
+) -> Explanation {
+    let mut sources = plan
+        .sources
+        .iter()
+        .filter_map(|(name, columns)| {
+            let (loader, parallel) = describe_source(&definition.sources.get(name)?.source);
+            Some(SourceExplanation {
+                name: name.clone(),
+                loader,
+                columns: columns.clone().map(sorted),
+                parallel,
+            })
+        })
+        .collect::<Vec<_>>();
+    let mut scanned_names = BTreeSet::new();
+    for node in plan.transformations.values().flatten() {
+        scanned_sources(node, &mut scanned_names);
+    }
+    for name in scanned_names {
+        if registered_dataframes.contains_key(&name) {
+            sources.push(SourceExplanation {
+                name,
+                loader: String::from("Registered data frame"),
+                columns: None,
+                parallel: false,
+            });
+        }
+    }
+    sources.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let transformations = plan
+        .transformations
+        .iter()
+        .map(|(name, root)| TransformationExplanation {
+            name: name.clone(),
+            root: root.as_ref().map(|node| explain_node(node, &plan.catalog)),
+            destination: definition
+                .transformations
+                .get(name)
+                .and_then(|transformation| transformation.destination.as_ref())
+                .map(describe_destination),
+        })
+        .collect();
+
+    Explanation {
+        sources,
+        transformations,
+    }
+}
+
+/// Describe a join strategy in lowercase words, e.g., "sort-merge" for "SortMerge".
+fn describe_strategy(strategy: &str) -> String {
+    let mut description = String::new();
+    for (index, c) in strategy.char_indices() {
+        if c.is_uppercase() && index > 0 {
+            description.push('-');
+        }
+        description.extend(c.to_lowercase());
+    }
+    description
+}
+
+fn write_node(out: &mut String, node: &NodeExplanation, prefix: &str, last: bool) {
+    let (branch, continuation) = if last {
+        ("└── ", "    ")
+    } else {
+        ("├── ", "│   ")
+    };
+    let mut annotations = vec![];
+    if node.operation != "Scan" {
+        if let Some(arity) = node.arity {
+            annotations.push(format!("arity {}", arity));
+        }
+    }
+    if let Some(strategy) = &node.strategy {
+        annotations.push(format!("{} join", describe_strategy(strategy)));
+    }
+    if node.operation != "Scan" && !node.streaming {
+        annotations.push(String::from("blocking"));
+    }
+    if let Some(columns) = &node.columns {
+        annotations.push(format!("columns [{}]", columns.join(", ")));
+    }
+    let _ = write!(
+        out,
+        "{}{}{} {}",
+        prefix, branch, node.operation, node.detail
+    );
+    if !annotations.is_empty() {
+        let _ = write!(out, " ({})", annotations.join("; "));
+    }
+    out.push('\n');
+    let prefix = format!("{}{}", prefix, continuation);
+    for (index, input) in node.inputs.iter().enumerate() {
+        write_node(out, input, &prefix, index + 1 == node.inputs.len());
+    }
+}
+
+fn dot_id(kind: &str, name: &str) -> String {
+    format!(
+        "\"{}:{}\"",
+        kind,
+        name.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+fn dot_label(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Explanation {
+    /// Render the explanation as a human-readable tree.
+    pub fn to_tree(&self) -> String {
+        let mut out = String::from("Sources\n");
+        for (index, source) in self.sources.iter().enumerate() {
+            let branch = if index + 1 == self.sources.len() {
+                "└── "
+            } else {
+                "├── "
+            };
+            let columns = source
+                .columns
+                .as_ref()
+                .map_or(String::from("all columns"), |columns| {
+                    format!("columns [{}]", columns.join(", "))
+                });
+            let parallel = if source.parallel { "; parallel" } else { "" };
+            let _ = writeln!(
+                out,
+                "{}{}: {} ({}{})",
+                branch, source.name, source.loader, columns, parallel
+            );
+        }
+        out.push_str("Transformations\n");
+        for (index, transformation) in self.transformations.iter().enumerate() {
+            let last = index + 1 == self.transformations.len();
+            let (branch, continuation) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            let _ = write!(out, "{}{}", branch, transformation.name);
+            if let Some(destination) = &transformation.destination {
+                let _ = write!(out, " -> {}", destination);
+            }
+            out.push('\n');
+            if let Some(root) = &transformation.root {
+                write_node(&mut out, root, continuation, true);
+            }
+        }
+        out
+    }
+
+    /// Render the explanation as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("an explanation can always be serialized")
+    }
+
+    /// Render the graph of sources and transformations, along with their destinations, in the Graphviz DOT language.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph pipeline {\n    rankdir=LR;\n");
+        for source in &self.sources {
+            let _ = writeln!(
+                out,
+                "    {} [shape=cylinder, label={}];",
+                dot_id("source", &source.name),
+                dot_label(&format!("{}\n{}", source.name, source.loader))
+            );
+        }
+        for transformation in &self.transformations {
+            let id = dot_id("transformation", &transformation.name);
+            let _ = writeln!(
+                out,
+                "    {} [shape=box, label={}];",
+                id,
+                dot_label(&transformation.name)
+            );
+            let mut sources = BTreeSet::new();
+            if let Some(root) = &transformation.root {
+                scanned(root, &mut sources);
+            }
+            for source in sources {
+                let _ = writeln!(out, "    {} -> {};", dot_id("source", source), id);
+            }
+            if let Some(destination) = &transformation.destination {
+                let destination_id = dot_id("destination", &transformation.name);
+                let _ = writeln!(
+                    out,
+                    "    {} [shape=note, label={}];",
+                    destination_id,
+                    dot_label(destination)
+                );
+                let _ = writeln!(out, "    {} -> {};", id, destination_id);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn scanned<'a>(node: &'a NodeExplanation, sources: &mut BTreeSet<&'a str>) {
+    if node.operation == "Scan" {
+        sources.insert(&node.detail);
+    }
+    for input in &node.inputs {
+        scanned(input, sources);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn explanation() -> Explanation {
+        let raw_definition = r#"{
+            "sources": {
+                "people": {
+                    "schema": [
+                        { "column_name": "id", "data_type": "Integer", "required": true },
+                        { "column_name": "name", "data_type": "String", "required": true },
+                        { "column_name": "age", "data_type": "Integer", "required": false }
+                    ],
+                    "source": { "type": "File", "format": "Csv", "path": "people.csv", "parallel": true }
+                }
+            },
+            "transformations": {
+                "adults": {
+                    "sources": ["people", "salaries"],
+                    "operations": [
+                        { "type": "InnerJoin", "on": "id = person_id", "strategy": "SortMerge" },
+                        { "type": "Filter", "predicate": "age >= 18" },
+                        { "type": "Select", "columns": ["name", "salary"] }
+                    ],
+                    "destination": { "type": "Parquet", "path": "adults.parquet" }
+                }
+            }
+        }"#;
+        let definition: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let registered = HashMap::from([(
+            String::from("salaries"),
+            vec![HashMap::from([
+                (
+                    String::from("person_id"),
+                    crate::core::dataframe::ColumnValue::Integer(1),
+                ),
+                (
+                    String::from("salary"),
+                    crate::core::dataframe::ColumnValue::Integer(100),
+                ),
+            ])],
+        )]);
+        let plan = LogicalPlan::new(&definition, &registered).optimize();
+        explain(&plan, &definition, &registered)
+    }
+
+    #[test]
+    fn renders_tree() {
+        assert_eq!(
+            explanation().to_tree(),
+            "Sources
+├── people: File people.csv (Csv) (columns [age, id, name]; parallel)
+└── salaries: Registered data frame (all columns)
+Transformations
+└── adults -> Parquet adults.parquet
+    └── Select name, salary (arity 1; columns [name, salary])
+        └── InnerJoin id = person_id (arity 2; sort-merge join; columns [age, id, name, person_id, salary])
+            ├── Filter age >= 18 (arity 1; columns [age, id, name])
+            │   └── Scan people (columns [age, id, name])
+            └── Scan salaries (columns [person_id, salary])
+"
+        );
+    }
+
+    #[test]
+    fn renders_json() {
+        let json: serde_json::Value = serde_json::from_str(&explanation().to_json()).unwrap();
+
+        assert_eq!(
+            json["transformations"][0]["root"]["inputs"][0]["strategy"],
+            "SortMerge"
+        );
+        assert_eq!(json["sources"][0]["parallel"], true);
+        assert_eq!(json["transformations"][0]["root"]["streaming"], true);
+    }
+
+    #[test]
+    fn marks_blocking_operations() {
+        let raw_definition = r#"{
+            "sources": {},
+            "transformations": {
+                "totals": {
+                    "sources": ["amounts"],
+                    "operations": [
+                        { "type": "Filter", "predicate": "amount > 0" },
+                        { "type": "Script", "mode": "Dataframe", "code": "rows" }
+                    ]
+                }
+            }
+        }"#;
+        let definition: PipelineDefinition = serde_json::from_str(raw_definition).unwrap();
+        let registered = HashMap::from([(String::from("amounts"), vec![])]);
+        let plan = LogicalPlan::new(&definition, &registered).optimize();
+
+        let tree = explain(&plan, &definition, &registered).to_tree();
+
+        assert!(tree.contains("Script Rhai, Dataframe (arity 1; blocking)\n"));
+        assert!(tree.contains("Filter amount > 0 (arity 1; columns [])\n"));
+    }
+
+    #[test]
+    fn renders_dot() {
+        let dot = explanation().to_dot();
+
+        assert!(dot.starts_with("digraph pipeline {"));
+        assert!(dot.contains("\"source:people\" -> \"transformation:adults\";"));
+        assert!(dot.contains("\"source:salaries\" -> \"transformation:adults\";"));
+        assert!(dot.contains("\"transformation:adults\" -> \"destination:adults\";"));
+    }
+}
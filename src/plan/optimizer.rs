@@ -0,0 +1,412 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::{Columns, LogicalPlan, PlanNode};
+use crate::transformations::filter::predicate_column;
+
+type Catalog = HashMap<String, BTreeSet<String>>;
+
+/// Optimize a logical plan by applying all rules:
+/// - input pruning: inputs that an operation does not use (e.g., the second source of a Filter) are removed.
+/// - filter fusion: consecutive Filters are combined into a single Filter that checks all predicates in one pass.
+/// - predicate pushdown: predicates of a Filter that follows an InnerJoin, and that only refer to columns of one side
+///   of the join, are applied to that side before the join.
+/// - projection pushdown: only the columns that are required by the operations of the transformations are loaded.
+/// - source pruning: sources that are not used by any transformation are not loaded.
+pub fn optimize(mut plan: LogicalPlan) -> LogicalPlan {
+    for root in plan.transformations.values_mut() {
+        *root = root.take().map(|node| optimize_node(node, &plan.catalog));
+    }
+    plan.sources = required_columns(&plan);
+    plan
+}
+
+fn optimize_node(mut node: PlanNode, catalog: &Catalog) -> PlanNode {
+    if let Some(inputs) = node.inputs_mut() {
+        *inputs = std::mem::take(inputs)
+            .into_iter()
+            .map(|input| optimize_node(input, catalog))
+            .collect();
+    }
+    push_down_predicates(fuse_filters(prune_inputs(node)), catalog)
+}
+
+/// Remove the inputs beyond those that yield the data frames that a Filter, Select or InnerJoin operates on.
+fn prune_inputs(mut node: PlanNode) -> PlanNode {
+    let (used, inputs) = match &mut node {
+        PlanNode::Filter { inputs, .. } | PlanNode::Select { inputs, .. } => (1, inputs),
+        PlanNode::InnerJoin { inputs, .. } => (2, inputs),
+        _ => return node,
+    };
+    let mut available = 0;
+    for (index, input) in inputs.iter().enumerate() {
+        match input.output_count() {
+            Some(count) => available += count,
+            None => break,
+        }
+        if available >= used {
+            inputs.truncate(index + 1);
+            break;
+        }
+    }
+    node
+}
+
+fn fuse_filters(node: PlanNode) -> PlanNode {
+    match node {
+        PlanNode::Filter { predicates, inputs }
+            if matches!(inputs.as_slice(), [PlanNode::Filter { .. }]) =>
+        {
+            let Some(PlanNode::Filter {
+                predicates: mut fused,
+                inputs,
+            }) = inputs.into_iter().next()
+            else {
+                unreachable!()
+            };
+            fused.extend(predicates);
+            PlanNode::Filter {
+                predicates: fused,
+                inputs,
+            }
+        }
+        node => node,
+    }
+}
+
+fn filter(input: PlanNode, predicates: Vec<String>, catalog: &Catalog) -> PlanNode {
+    if predicates.is_empty() {
+        return input;
+    }
+    let node = PlanNode::Filter {
+        predicates,
+        inputs: vec![input],
+    };
+    push_down_predicates(fuse_filters(node), catalog)
+}
+
+fn push_down_predicates(node: PlanNode, catalog: &Catalog) -> PlanNode {
+    let PlanNode::Filter { predicates, inputs } = node else {
+        return node;
+    };
+    let sides = match inputs.as_slice() {
+        [PlanNode::InnerJoin {
+            inputs: join_inputs,
+            ..
+        }] => match join_inputs.as_slice() {
+            [left, right] if left.output_count() == Some(1) && right.output_count() == Some(1) => {
+                left.columns(catalog).zip(right.columns(catalog))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    let Some((left_columns, right_columns)) = sides else {
+        return PlanNode::Filter { predicates, inputs };
+    };
+
+    let (mut left, mut right, mut remaining) = (vec![], vec![], vec![]);
+    for predicate in predicates {
+        match predicate_column(&predicate) {
+            Some(column) if left_columns.contains(column) && !right_columns.contains(column) => {
+                left.push(predicate)
+            }
+            Some(column) if right_columns.contains(column) && !left_columns.contains(column) => {
+                right.push(predicate)
+            }
+            _ => remaining.push(predicate),
+        }
+    }
+    let Some(PlanNode::InnerJoin {
+        on,
+        preserve_order,
+        strategy,
+        coercion,
+        match_nulls,
+        inputs: join_inputs,
+    }) = inputs.into_iter().next()
+    else {
+        unreachable!()
+    };
+    let [left_input, right_input]: [PlanNode; 2] = join_inputs.try_into().unwrap();
+    let join = PlanNode::InnerJoin {
+        on,
+        preserve_order,
+        strategy,
+        coercion,
+        match_nulls,
+        inputs: vec![
+            filter(left_input, left, catalog),
+            filter(right_input, right, catalog),
+        ],
+    };
+    if remaining.is_empty() {
+        join
+    } else {
+        PlanNode::Filter {
+            predicates: remaining,
+            inputs: vec![join],
+        }
+    }
+}
+
+fn union(columns: &mut Columns, other: Columns) {
+    match (columns.as_mut(), other) {
+        (Some(columns), Some(other)) => columns.extend(other),
+        _ => *columns = None,
+    }
+}
+
+/// Determine the columns that are required of each source that needs to be loaded.
+fn required_columns(plan: &LogicalPlan) -> BTreeMap<String, Columns> {
+    let mut required = BTreeMap::new();
+    for root in plan.transformations.values().flatten() {
+        require(root, None, &plan.catalog, &mut required);
+    }
+    required.retain(|source, _| plan.sources.contains_key(source));
+    required
+}
+
+fn require_all(inputs: &[PlanNode], catalog: &Catalog, required: &mut BTreeMap<String, Columns>) {
+    for input in inputs {
+        require(input, None, catalog, required);
+    }
+}
+
+fn require_first(
+    inputs: &[PlanNode],
+    columns: Columns,
+    catalog: &Catalog,
+    required: &mut BTreeMap<String, Columns>,
+) {
+    match inputs {
+        [input] => require(input, columns, catalog, required),
+        inputs => require_all(inputs, catalog, required),
+    }
+}
+
+/// Record that the given columns are required of the output of a node, and hence that the columns its operation needs
+/// are required of its inputs.
+fn require(
+    node: &PlanNode,
+    columns: Columns,
+    catalog: &Catalog,
+    required: &mut BTreeMap<String, Columns>,
+) {
+    match node {
+        PlanNode::Scan { source } => union(
+            required
+                .entry(source.clone())
+                .or_insert_with(|| Some(BTreeSet::new())),
+            columns,
+        ),
+        PlanNode::Filter { predicates, inputs } => {
+            let columns = columns.map(|mut columns| {
+                columns.extend(
+                    predicates
+                        .iter()
+                        .filter_map(|predicate| predicate_column(predicate))
+                        .map(String::from),
+                );
+                columns
+            });
+            require_first(inputs, columns, catalog, required);
+        }
+        PlanNode::Select {
+            columns: selected,
+            inputs,
+        } => {
+            let selected = selected
+                .iter()
+                .filter(|column| columns.as_ref().is_none_or(|c| c.contains(*column)))
+                .cloned()
+                .collect();
+            require_first(inputs, Some(selected), catalog, required);
+        }
+        PlanNode::InnerJoin { on, inputs, .. } => {
+            let keys = on
+                .split_once('=')
+                .map(|(left, right)| (left.trim(), right.trim()));
+            let sides = match inputs.as_slice() {
+                [left, right]
+                    if left.output_count() == Some(1) && right.output_count() == Some(1) =>
+                {
+                    left.columns(catalog).zip(right.columns(catalog))
+                }
+                _ => None,
+            };
+            match (columns, keys, sides) {
+                (
+                    Some(columns),
+                    Some((left_key, right_key)),
+                    Some((left_columns, right_columns)),
+                ) => {
+                    let mut left = &columns & &left_columns;
+                    left.insert(left_key.to_owned());
+                    let mut right = &columns & &right_columns;
+                    right.insert(right_key.to_owned());
+                    require(&inputs[0], Some(left), catalog, required);
+                    require(&inputs[1], Some(right), catalog, required);
+                }
+                _ => require_all(inputs, catalog, required),
+            }
+        }
+        PlanNode::Operation { inputs, .. } => require_all(inputs, catalog, required),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn scan(source: &str) -> PlanNode {
+        PlanNode::Scan {
+            source: String::from(source),
+        }
+    }
+
+    fn filter(predicates: &[&str], inputs: Vec<PlanNode>) -> PlanNode {
+        PlanNode::Filter {
+            predicates: predicates.iter().map(|p| String::from(*p)).collect(),
+            inputs,
+        }
+    }
+
+    fn join(inputs: Vec<PlanNode>) -> PlanNode {
+        PlanNode::InnerJoin {
+            on: String::from("id = person_id"),
+            preserve_order: false,
+            strategy: crate::core::definitions::JoinStrategy::Hash,
+            coercion: crate::core::definitions::KeyCoercion::Numeric,
+            match_nulls: false,
+            inputs,
+        }
+    }
+
+    fn columns(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| String::from(*name)).collect()
+    }
+
+    fn plan(root: PlanNode) -> LogicalPlan {
+        LogicalPlan {
+            sources: BTreeMap::from([
+                (String::from("people"), None),
+                (String::from("salaries"), None),
+                (String::from("unused"), None),
+            ]),
+            transformations: BTreeMap::from([(String::from("output"), Some(root))]),
+            catalog: HashMap::from([
+                (String::from("people"), columns(&["id", "name", "age"])),
+                (
+                    String::from("salaries"),
+                    columns(&["person_id", "salary", "name"]),
+                ),
+            ]),
+        }
+    }
+
+    #[test]
+    fn pushes_predicates_below_joins() {
+        let root = filter(
+            &["age > 30", "salary > 100", "name == Jen"],
+            vec![join(vec![scan("people"), scan("salaries")])],
+        );
+
+        let optimized = plan(root).optimize();
+
+        assert_eq!(
+            optimized.transformations["output"],
+            Some(filter(
+                &["name == Jen"],
+                vec![join(vec![
+                    filter(&["age > 30"], vec![scan("people")]),
+                    filter(&["salary > 100"], vec![scan("salaries")]),
+                ])]
+            ))
+        );
+    }
+
+    #[test]
+    fn fuses_filters() {
+        let root = filter(
+            &["age < 60"],
+            vec![filter(
+                &["age > 30"],
+                vec![join(vec![
+                    filter(&["age != 40"], vec![scan("people")]),
+                    scan("salaries"),
+                ])],
+            )],
+        );
+
+        let optimized = plan(root).optimize();
+
+        assert_eq!(
+            optimized.transformations["output"],
+            Some(join(vec![
+                filter(&["age != 40", "age > 30", "age < 60"], vec![scan("people")]),
+                scan("salaries"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn prunes_unused_inputs_and_sources() {
+        let root = filter(&["age > 30"], vec![scan("people"), scan("salaries")]);
+
+        let optimized = plan(root).optimize();
+
+        assert_eq!(
+            optimized.transformations["output"],
+            Some(filter(&["age > 30"], vec![scan("people")]))
+        );
+        assert_eq!(optimized.sources.keys().collect::<Vec<_>>(), vec!["people"]);
+    }
+
+    #[test]
+    fn pushes_projections_into_sources() {
+        let root = PlanNode::Select {
+            columns: vec![String::from("age"), String::from("salary")],
+            inputs: vec![filter(
+                &["name == Jen"],
+                vec![join(vec![scan("people"), scan("salaries")])],
+            )],
+        };
+
+        let optimized = plan(root).optimize();
+
+        assert_eq!(
+            optimized.sources,
+            BTreeMap::from([
+                (
+                    String::from("people"),
+                    Some(columns(&["age", "id", "name"]))
+                ),
+                (
+                    String::from("salaries"),
+                    Some(columns(&["name", "person_id", "salary"]))
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn requires_all_columns_of_opaque_operations() {
+        let root = PlanNode::Select {
+            columns: vec![String::from("age")],
+            inputs: vec![PlanNode::Operation {
+                operation: crate::plan::OpaqueOperation::Custom {
+                    name: String::from("custom"),
+                    config: Default::default(),
+                },
+                inputs: vec![scan("people")],
+            }],
+        };
+
+        let optimized = plan(root).optimize();
+
+        assert_eq!(
+            optimized.sources,
+            BTreeMap::from([(String::from("people"), None)])
+        );
+    }
+}
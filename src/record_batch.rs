@@ -0,0 +1,374 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray},
+    compute::{cast_with_options, CastOptions},
+    datatypes::{DataType as ArrowType, Field, Schema},
+    error::ArrowError,
+};
+
+use crate::core::{
+    dataframe::{ColumnValue, Dataframe, Row},
+    definitions::{ColumnDefinition, DataType},
+};
+
+/// The Arrow type corresponding to a data type.
+pub fn arrow_type(data_type: &DataType) -> ArrowType {
+    match data_type {
+        DataType::Integer => ArrowType::Int64,
+        DataType::Decimal => ArrowType::Float64,
+        DataType::String => ArrowType::Utf8,
+    }
+}
+
+fn value_at(array: &ArrayRef, data_type: &DataType, index: usize) -> ColumnValue {
+    if array.is_null(index) {
+        return ColumnValue::None;
+    }
+    // The array has been cast to the Arrow type of the data type, so these downcasts cannot fail.
+    match data_type {
+        DataType::Integer => ColumnValue::Integer(
+            array
+                .as_any()
+                .downcast_ref::<Int64Array>()
+                .unwrap()
+                .value(index),
+        ),
+        DataType::Decimal => ColumnValue::Decimal(
+            array
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap()
+                .value(index),
+        ),
+        DataType::String => ColumnValue::String(
+            array
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .unwrap()
+                .value(index)
+                .to_owned(),
+        ),
+    }
+}
+
+/// The Arrow schema corresponding to a schema. Required columns are not nullable.
+pub fn arrow_schema(schema: &[ColumnDefinition]) -> Schema {
+    Schema::new(
+        schema
+            .iter()
+            .map(|definition| {
+                Field::new(
+                    &definition.column_name,
+                    arrow_type(&definition.data_type),
+                    !definition.required,
+                )
+            })
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Convert a record batch into a data frame, mapping the columns of the record batch onto the schema by name. Columns
+/// are cast to the Arrow type corresponding to the data type in the schema. Optional columns that the record batch does
+/// not contain are absent in all rows.
+pub fn from_record_batch(
+    batch: &RecordBatch,
+    schema: &[ColumnDefinition],
+) -> Result<Dataframe, ArrowError> {
+    let cast_options = CastOptions {
+        safe: false,
+        ..Default::default()
+    };
+    let columns = schema
+        .iter()
+        .map(|definition| {
+            let column = match batch.column_by_name(&definition.column_name) {
+                Some(column) => column,
+                None if definition.required => {
+                    return Err(ArrowError::SchemaError(format!(
+                        "Record batch does not contain column {}",
+                        definition.column_name
+                    )))
+                }
+                None => return Ok(None),
+            };
+            if definition.required && column.null_count() > 0 {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "Missing value for required column {}",
+                    definition.column_name
+                )));
+            }
+            cast_with_options(column, &arrow_type(&definition.data_type), &cast_options).map(Some)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let df = (0..batch.num_rows())
+        .map(|index| {
+            schema
+                .iter()
+                .zip(&columns)
+                .map(|(definition, column)| {
+                    (
+                        definition.column_name.clone(),
+                        column.as_ref().map_or(ColumnValue::None, |column| {
+                            value_at(column, &definition.data_type, index)
+                        }),
+                    )
+                })
+                .collect::<Row>()
+        })
+        .collect();
+    Ok(df)
+}
+
+fn build_column<'r>(
+    rows: impl Iterator<Item = &'r Row>,
+    name: &str,
+    data_type: &DataType,
+) -> ArrayRef {
+    match data_type {
+        DataType::Integer => Arc::new(
+            rows.map(|row| match row.get(name) {
+                Some(ColumnValue::Integer(i)) => Some(*i),
+                _ => None,
+            })
+            .collect::<Int64Array>(),
+        ),
+        DataType::Decimal => Arc::new(
+            rows.map(|row| match row.get(name) {
+                Some(ColumnValue::Integer(i)) => Some(*i as f64),
+                Some(ColumnValue::Decimal(d)) => Some(*d),
+                _ => None,
+            })
+            .collect::<Float64Array>(),
+        ),
+        DataType::String => Arc::new(
+            rows.map(|row| match row.get(name) {
+                Some(ColumnValue::String(s)) => Some(s.as_str()),
+                _ => None,
+            })
+            .collect::<StringArray>(),
+        ),
+    }
+}
+
+/// Derive the Arrow type of each column from the rows of the data frames, in lexicographical order of the column
+/// names. Columns holding both integers and decimals are widened to decimals; columns without any values are strings.
+fn infer_types(dfs: &[Dataframe]) -> Result<BTreeMap<&str, Option<DataType>>, ArrowError> {
+    let mut types: BTreeMap<&str, Option<DataType>> = BTreeMap::new();
+    for (name, value) in dfs.iter().flatten().flatten() {
+        let value_type = match value {
+            ColumnValue::Integer(_) => Some(DataType::Integer),
+            ColumnValue::Decimal(_) => Some(DataType::Decimal),
+            ColumnValue::String(_) => Some(DataType::String),
+            ColumnValue::None => None,
+        };
+        let entry = types.entry(name.as_str()).or_insert(None);
+        *entry = match (entry.take(), value_type) {
+            (current, None) => current,
+            (None, new) => new,
+            (Some(DataType::Integer), Some(DataType::Integer)) => Some(DataType::Integer),
+            (
+                Some(DataType::Integer | DataType::Decimal),
+                Some(DataType::Integer | DataType::Decimal),
+            ) => Some(DataType::Decimal),
+            (Some(DataType::String), Some(DataType::String)) => Some(DataType::String),
+            _ => {
+                return Err(ArrowError::SchemaError(format!(
+                    "Column {} contains values of incompatible types",
+                    name
+                )))
+            }
+        };
+    }
+    Ok(types)
+}
+
+/// Convert data frames into a single record batch. The schema of the record batch is derived from the rows of the
+/// data frames; rows that lack a column have a null value for that column.
+pub fn to_record_batch(dfs: &[Dataframe]) -> Result<RecordBatch, ArrowError> {
+    let types = infer_types(dfs)?;
+    let rows = || dfs.iter().flatten();
+
+    let mut fields = vec![];
+    let mut columns: Vec<ArrayRef> = vec![];
+    for (name, data_type) in types {
+        let data_type = data_type.unwrap_or(DataType::String);
+        fields.push(Field::new(name, arrow_type(&data_type), true));
+        columns.push(build_column(rows(), name, &data_type));
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+}
+
+/// Convert data frames into a single record batch with the Arrow schema corresponding to the given schema. Integers
+/// are accepted in decimal columns; any other value that does not match the data type of its column is an error.
+pub fn to_record_batch_with_schema(
+    dfs: &[Dataframe],
+    schema: &[ColumnDefinition],
+) -> Result<RecordBatch, ArrowError> {
+    for row in dfs.iter().flatten() {
+        for definition in schema {
+            let matches = matches!(
+                (row.get(&definition.column_name), &definition.data_type),
+                (None | Some(ColumnValue::None), _)
+                    | (
+                        Some(ColumnValue::Integer(_)),
+                        DataType::Integer | DataType::Decimal
+                    )
+                    | (Some(ColumnValue::Decimal(_)), DataType::Decimal)
+                    | (Some(ColumnValue::String(_)), DataType::String)
+            );
+            if !matches {
+                return Err(ArrowError::InvalidArgumentError(format!(
+                    "Value of column {} does not match data type {:?}",
+                    definition.column_name, definition.data_type
+                )));
+            }
+        }
+    }
+
+    let columns = schema
+        .iter()
+        .map(|definition| {
+            build_column(
+                dfs.iter().flatten(),
+                &definition.column_name,
+                &definition.data_type,
+            )
+        })
+        .collect();
+    // Creating the record batch fails if a required (i.e., non-nullable) column contains nulls.
+    RecordBatch::try_new(Arc::new(arrow_schema(schema)), columns)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn round_trips_data_frames() {
+        let dfs = vec![vec![
+            HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("Jen")),
+                ),
+                (String::from("age"), ColumnValue::Integer(30)),
+                (String::from("salary"), ColumnValue::Integer(19319)),
+            ]),
+            HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("Dave")),
+                ),
+                (String::from("salary"), ColumnValue::Decimal(100.5)),
+            ]),
+        ]];
+        let schema = vec![
+            ColumnDefinition {
+                column_name: String::from("name"),
+                data_type: DataType::String,
+                required: true,
+            },
+            ColumnDefinition {
+                column_name: String::from("age"),
+                data_type: DataType::Integer,
+                required: false,
+            },
+            ColumnDefinition {
+                column_name: String::from("salary"),
+                data_type: DataType::Decimal,
+                required: true,
+            },
+        ];
+
+        let batch = to_record_batch(&dfs).unwrap();
+        let result = from_record_batch(&batch, &schema).unwrap();
+
+        assert_eq!(
+            batch
+                .schema()
+                .field_with_name("salary")
+                .unwrap()
+                .data_type(),
+            &ArrowType::Float64
+        );
+        assert_eq!(
+            result,
+            vec![
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Jen"))
+                    ),
+                    (String::from("age"), ColumnValue::Integer(30)),
+                    (String::from("salary"), ColumnValue::Decimal(19319.0)),
+                ]),
+                HashMap::from([
+                    (
+                        String::from("name"),
+                        ColumnValue::String(String::from("Dave"))
+                    ),
+                    (String::from("age"), ColumnValue::None),
+                    (String::from("salary"), ColumnValue::Decimal(100.5)),
+                ]),
+            ]
+        );
+    }
+
+    #[test]
+    fn converts_using_declared_schema() {
+        let dfs = vec![vec![
+            HashMap::from([(String::from("id"), ColumnValue::Integer(1))]),
+            HashMap::from([(String::from("id"), ColumnValue::None)]),
+        ]];
+        let schema = vec![ColumnDefinition {
+            column_name: String::from("id"),
+            data_type: DataType::Decimal,
+            required: false,
+        }];
+
+        let batch = to_record_batch_with_schema(&dfs, &schema).unwrap();
+
+        assert_eq!(batch.schema().as_ref(), &arrow_schema(&schema));
+        assert_eq!(
+            batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap(),
+            &Float64Array::from(vec![Some(1.0), None])
+        );
+    }
+
+    #[test]
+    fn declared_schema_rejects_missing_required_values() {
+        let dfs = vec![vec![HashMap::from([(
+            String::from("id"),
+            ColumnValue::None,
+        )])]];
+        let schema = vec![ColumnDefinition {
+            column_name: String::from("id"),
+            data_type: DataType::Integer,
+            required: true,
+        }];
+
+        let result = to_record_batch_with_schema(&dfs, &schema);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn incompatible_types_error() {
+        let dfs = vec![vec![
+            HashMap::from([(String::from("id"), ColumnValue::Integer(1))]),
+            HashMap::from([(String::from("id"), ColumnValue::String(String::from("two")))]),
+        ]];
+
+        let result = to_record_batch(&dfs);
+
+        assert!(result.is_err_and(|err| err.to_string().contains("incompatible types")));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{collections::BTreeMap, sync::Mutex, time::Duration};
+
+use serde::Serialize;
+
+use crate::{
+    core::error::RustyPipesError,
+    observer::{Event, Observer},
+};
+
+fn milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The metrics of loading a source. A streamed source that is scanned by multiple transformations is loaded by each of
+/// them, in which case its metrics are the totals over these loads.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SourceMetrics {
+    /// The time spent loading the source, in milliseconds.
+    pub load_ms: f64,
+    /// The number of rows that were loaded.
+    pub rows: usize,
+    /// The estimated size of the loaded rows in memory, in bytes (for a streamed source, that of its largest batch).
+    pub bytes: usize,
+    /// Whether the source was read from the cache.
+    pub cached: bool,
+    /// The error that loading the source failed with, if any.
+    pub error: Option<RustyPipesError>,
+}
+
+/// The metrics of a single operation of a transformation.
+#[derive(Debug, Clone, Serialize)]
+pub struct OperationMetrics {
+    /// The name of the operation, e.g., "Filter".
+    pub operation: String,
+    /// The time spent performing the operation, in milliseconds.
+    pub duration_ms: f64,
+    /// The number of rows of the input data frames of the operation.
+    pub rows_in: usize,
+    /// The number of rows of the output data frames of the operation.
+    pub rows_out: usize,
+    /// The ratio of output rows to input rows, or None if there were no input rows.
+    pub selectivity: Option<f64>,
+}
+
+/// The metrics of running a transformation.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TransformationMetrics {
+    /// The time spent running the transformation (including writing its output to its destination), in milliseconds.
+    pub duration_ms: f64,
+    /// The number of rows of the output of the transformation.
+    pub rows: usize,
+    /// Whether the output was read from the cache, in which case no operations were performed.
+    pub cached: bool,
+    /// The operations that were performed, in the order in which they finished.
+    pub operations: Vec<OperationMetrics>,
+    /// The error that the transformation failed with, if any.
+    pub error: Option<RustyPipesError>,
+}
+
+/// A report of a run of a pipeline (see [`Engine::run_with_report`]), which can be serialized (e.g., to JSON).
+///
+/// [`Engine::run_with_report`]: crate::engine::Engine::run_with_report
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RunReport {
+    /// The wall-clock time of the run, in milliseconds.
+    pub wall_clock_ms: f64,
+    /// The metrics of each source that was loaded, keyed by the name of the source.
+    pub sources: BTreeMap<String, SourceMetrics>,
+    /// The metrics of each transformation that was run, keyed by the name of the transformation.
+    pub transformations: BTreeMap<String, TransformationMetrics>,
+    /// A rough estimate of the peak memory used by the data frames of the run, in bytes: the estimated size of all
+    /// loaded sources, which are held for the duration of the run, plus that of the largest output of any single
+    /// operation.
+    pub peak_memory_bytes: usize,
+    /// The number of sources and transformations that failed.
+    pub errors: usize,
+}
+
+/// An observer that collects the metrics of a run into a report.
+#[derive(Default)]
+pub(crate) struct ReportCollector {
+    report: Mutex<RunReport>,
+    largest_output: Mutex<usize>,
+}
+
+impl ReportCollector {
+    /// The report of the run observed so far.
+    pub(crate) fn report(&self) -> RunReport {
+        let mut report = self.report.lock().unwrap().clone();
+        report.peak_memory_bytes = report
+            .sources
+            .values()
+            .map(|source| source.bytes)
+            .sum::<usize>()
+            + *self.largest_output.lock().unwrap();
+        report.errors = report
+            .sources
+            .values()
+            .filter(|source| source.error.is_some())
+            .count()
+            + report
+                .transformations
+                .values()
+                .filter(|transformation| transformation.error.is_some())
+                .count();
+        report
+    }
+}
+
+impl Observer for ReportCollector {
+    fn on_event(&self, event: &Event<'_>) {
+        let mut report = self.report.lock().unwrap();
+        match event {
+            Event::RunFinished { duration } => report.wall_clock_ms = milliseconds(*duration),
+            Event::SourceFinished {
+                source,
+                rows,
+                bytes,
+                duration,
+                cached,
+            } => {
+                let metrics = report.sources.entry(source.to_string()).or_default();
+                metrics.load_ms += milliseconds(*duration);
+                metrics.rows += rows;
+                metrics.bytes = metrics.bytes.max(*bytes);
+                metrics.cached = *cached;
+            }
+            Event::SourceFailed {
+                source,
+                error,
+                duration,
+            } => {
+                let metrics = report.sources.entry(source.to_string()).or_default();
+                metrics.load_ms += milliseconds(*duration);
+                metrics.error = Some((*error).clone());
+            }
+            Event::OperationFinished {
+                transformation,
+                operation,
+                rows_in,
+                rows_out,
+                bytes_out,
+                duration,
+            } => {
+                let mut largest_output = self.largest_output.lock().unwrap();
+                *largest_output = (*largest_output).max(*bytes_out);
+                report
+                    .transformations
+                    .entry(transformation.to_string())
+                    .or_default()
+                    .operations
+                    .push(OperationMetrics {
+                        operation: operation.to_string(),
+                        duration_ms: milliseconds(*duration),
+                        rows_in: *rows_in,
+                        rows_out: *rows_out,
+                        selectivity: (*rows_in > 0).then(|| *rows_out as f64 / *rows_in as f64),
+                    });
+            }
+            Event::TransformationFinished {
+                transformation,
+                rows,
+                duration,
+                cached,
+            } => {
+                let metrics = report
+                    .transformations
+                    .entry(transformation.to_string())
+                    .or_default();
+                metrics.duration_ms = milliseconds(*duration);
+                metrics.rows = *rows;
+                metrics.cached = *cached;
+            }
+            Event::TransformationFailed {
+                transformation,
+                error,
+                duration,
+            } => {
+                let metrics = report
+                    .transformations
+                    .entry(transformation.to_string())
+                    .or_default();
+                metrics.duration_ms = milliseconds(*duration);
+                metrics.error = Some((*error).clone());
+            }
+            Event::RunStarted { .. }
+            | Event::SourceStarted { .. }
+            | Event::TransformationStarted { .. }
+            | Event::OperationStarted { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn collects_metrics_into_report() {
+        let collector = ReportCollector::default();
+        let error = RustyPipesError::LoaderError(String::from("No files found for path missing"));
+
+        collector.on_event(&Event::SourceFinished {
+            source: "people",
+            rows: 4,
+            bytes: 400,
+            duration: Duration::from_millis(3),
+            cached: false,
+        });
+        collector.on_event(&Event::SourceFailed {
+            source: "missing",
+            error: &error,
+            duration: Duration::from_millis(1),
+        });
+        collector.on_event(&Event::OperationFinished {
+            transformation: "adults",
+            operation: "Filter",
+            rows_in: 4,
+            rows_out: 1,
+            bytes_out: 100,
+            duration: Duration::from_millis(2),
+        });
+        collector.on_event(&Event::TransformationFinished {
+            transformation: "adults",
+            rows: 1,
+            duration: Duration::from_millis(2),
+            cached: false,
+        });
+        collector.on_event(&Event::RunFinished {
+            duration: Duration::from_millis(6),
+        });
+
+        let report = collector.report();
+        assert_eq!(report.wall_clock_ms, 6.0);
+        assert_eq!(report.peak_memory_bytes, 500);
+        assert_eq!(report.errors, 1);
+        assert_eq!(
+            report.transformations["adults"].operations[0].selectivity,
+            Some(0.25)
+        );
+        assert_eq!(
+            serde_json::to_value(&report).unwrap()["sources"]["missing"]["error"],
+            serde_json::json!({ "LoaderError": "No files found for path missing" })
+        );
+    }
+}
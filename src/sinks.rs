@@ -0,0 +1,4 @@
+pub mod parquet;
+pub mod sqlite;
+pub use parquet::ParquetSink;
+pub use sqlite::SqliteSink;
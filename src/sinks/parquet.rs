@@ -0,0 +1,108 @@
+use std::{fs::File, path::Path};
+
+use parquet::{
+    arrow::ArrowWriter,
+    basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel},
+    file::properties::WriterProperties,
+};
+
+use crate::{
+    core::{
+        dataframe::Dataframe, definitions::ParquetCompression, error::RustyPipesError,
+        result::RustyPipesResult, sink::Sink,
+    },
+    record_batch::to_record_batch,
+};
+
+fn sink_err<E: ToString>(e: E) -> RustyPipesError {
+    RustyPipesError::SinkError(e.to_string())
+}
+
+/// A sink that writes to an Apache Parquet file. The schema of the file is derived from the columns of the rows that
+/// are written; rows that lack a column are written with a null value for that column.
+pub struct ParquetSink<'a> {
+    path: &'a Path,
+    compression: ParquetCompression,
+    row_group_size: Option<usize>,
+}
+
+impl<'a> ParquetSink<'a> {
+    /// Construct a new Parquet sink for the given path, using the given compression codec and, if set, maximum number
+    /// of rows per row group. This is a lazy operation; until the "write" method is run, no work will be performed.
+    pub fn new(
+        path: &'a Path,
+        compression: ParquetCompression,
+        row_group_size: Option<usize>,
+    ) -> Self {
+        ParquetSink {
+            path,
+            compression,
+            row_group_size,
+        }
+    }
+
+    fn properties(&self) -> WriterProperties {
+        let compression = match self.compression {
+            ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Gzip => Compression::GZIP(GzipLevel::default()),
+            ParquetCompression::Zstd => Compression::ZSTD(ZstdLevel::default()),
+            ParquetCompression::Lz4 => Compression::LZ4_RAW,
+            ParquetCompression::Brotli => Compression::BROTLI(BrotliLevel::default()),
+        };
+        let mut builder = WriterProperties::builder().set_compression(compression);
+        if let Some(row_group_size) = self.row_group_size {
+            builder = builder.set_max_row_group_size(row_group_size);
+        }
+        builder.build()
+    }
+}
+
+impl Sink for ParquetSink<'_> {
+    fn write(&self, dfs: &[Dataframe]) -> RustyPipesResult<()> {
+        let batch = to_record_batch(dfs).map_err(sink_err)?;
+        if batch.num_columns() == 0 {
+            return Err(RustyPipesError::SinkError(format!(
+                "Unable to write {} without any columns",
+                self.path.display()
+            )));
+        }
+
+        let file = File::create(self.path).map_err(sink_err)?;
+        let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(self.properties()))
+            .map_err(sink_err)?;
+        writer.write(&batch).map_err(sink_err)?;
+        writer.close().map_err(sink_err)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::dataframe::ColumnValue;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use std::collections::HashMap;
+
+    #[test]
+    fn writes_row_groups_with_compression() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sink.parquet");
+        let dfs = vec![(0..5)
+            .map(|i| HashMap::from([(String::from("id"), ColumnValue::Integer(i))]))
+            .collect::<Dataframe>()];
+
+        ParquetSink::new(&path, ParquetCompression::Zstd, Some(2))
+            .write(&dfs)
+            .unwrap();
+
+        let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+        let metadata = reader.metadata();
+        assert_eq!(metadata.file_metadata().num_rows(), 5);
+        assert_eq!(metadata.num_row_groups(), 3);
+        assert_eq!(
+            metadata.row_group(0).column(0).compression(),
+            Compression::ZSTD(ZstdLevel::default())
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::{collections::BTreeMap, path::Path};
+
+use rusqlite::{params_from_iter, types::Value, Connection};
+
+use crate::core::{
+    dataframe::{ColumnValue, Dataframe},
+    definitions::WriteMode,
+    error::RustyPipesError,
+    result::RustyPipesResult,
+    sink::Sink,
+};
+
+fn sink_err(e: rusqlite::Error) -> RustyPipesError {
+    RustyPipesError::SinkError(e.to_string())
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn to_sql(value: &ColumnValue) -> Value {
+    match value {
+        ColumnValue::Integer(i) => Value::Integer(*i),
+        ColumnValue::Decimal(d) => Value::Real(*d),
+        ColumnValue::String(s) => Value::Text(s.clone()),
+        ColumnValue::None => Value::Null,
+    }
+}
+
+/// Derive the columns of the table from the rows that are to be written, in lexicographical order of their names.
+/// The declared type of each column is based on the first value in that column that is not absent.
+fn columns(dfs: &[Dataframe]) -> BTreeMap<&str, &'static str> {
+    let mut columns = BTreeMap::new();
+    for (name, value) in dfs.iter().flatten().flatten() {
+        let declared_type = match value {
+            ColumnValue::Integer(_) => "INTEGER",
+            ColumnValue::Decimal(_) => "REAL",
+            ColumnValue::String(_) => "TEXT",
+            ColumnValue::None => "",
+        };
+        let entry = columns.entry(name.as_str()).or_insert(declared_type);
+        if entry.is_empty() {
+            *entry = declared_type;
+        }
+    }
+    columns
+}
+
+/// A sink that writes to a table in a SQLite database. The table is created based on the columns of the rows that are
+/// written; rows that lack a column are written with a NULL value for that column. When no rows are written, an
+/// existing table is emptied in Replace mode, since its columns cannot be derived.
+pub struct SqliteSink<'a> {
+    path: &'a Path,
+    table: &'a str,
+    mode: WriteMode,
+}
+
+impl<'a> SqliteSink<'a> {
+    /// Construct a new SQLite sink for the given database path and table, using the given write mode. This is a lazy
+    /// operation; until the "write" method is run, no work will be performed.
+    pub fn new(path: &'a Path, table: &'a str, mode: WriteMode) -> Self {
+        SqliteSink { path, table, mode }
+    }
+}
+
+impl Sink for SqliteSink<'_> {
+    fn write(&self, dfs: &[Dataframe]) -> RustyPipesResult<()> {
+        let columns = columns(dfs);
+        if columns.is_empty() && self.mode == WriteMode::Create {
+            return Err(RustyPipesError::SinkError(format!(
+                "Unable to create table {} without any columns",
+                self.table
+            )));
+        }
+
+        let mut connection = Connection::open(self.path).map_err(sink_err)?;
+        let transaction = connection.transaction().map_err(sink_err)?;
+        let table = quote_identifier(self.table);
+
+        if columns.is_empty() {
+            // Without any rows, the columns of the table are unknown, so an existing table is kept and emptied.
+            if self.mode == WriteMode::Replace {
+                let exists = transaction
+                    .query_row(
+                        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                        [self.table],
+                        |row| row.get::<_, bool>(0),
+                    )
+                    .map_err(sink_err)?;
+                if exists {
+                    transaction
+                        .execute(&format!("DELETE FROM {}", table), [])
+                        .map_err(sink_err)?;
+                }
+            }
+        } else {
+            if self.mode == WriteMode::Replace {
+                transaction
+                    .execute(&format!("DROP TABLE IF EXISTS {}", table), [])
+                    .map_err(sink_err)?;
+            }
+            let column_definitions = columns
+                .iter()
+                .map(|(name, declared_type)| {
+                    format!("{} {}", quote_identifier(name), declared_type)
+                        .trim_end()
+                        .to_owned()
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let if_not_exists = if self.mode == WriteMode::Append {
+                "IF NOT EXISTS "
+            } else {
+                ""
+            };
+            transaction
+                .execute(
+                    &format!(
+                        "CREATE TABLE {}{} ({})",
+                        if_not_exists, table, column_definitions
+                    ),
+                    [],
+                )
+                .map_err(sink_err)?;
+
+            let column_names = columns
+                .keys()
+                .map(|name| quote_identifier(name))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let placeholders = vec!["?"; columns.len()].join(", ");
+            let mut statement = transaction
+                .prepare(&format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table, column_names, placeholders
+                ))
+                .map_err(sink_err)?;
+            for row in dfs.iter().flatten() {
+                let values = columns
+                    .keys()
+                    .map(|name| row.get(*name).map_or(Value::Null, to_sql));
+                statement
+                    .execute(params_from_iter(values))
+                    .map_err(sink_err)?;
+            }
+        }
+
+        transaction.commit().map_err(sink_err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn dfs() -> Vec<Dataframe> {
+        vec![vec![
+            HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("Jen")),
+                ),
+                (String::from("salary"), ColumnValue::Decimal(19319.0)),
+            ]),
+            HashMap::from([
+                (
+                    String::from("name"),
+                    ColumnValue::String(String::from("Dave")),
+                ),
+                (String::from("age"), ColumnValue::Integer(40)),
+            ]),
+        ]]
+    }
+
+    fn read_table(path: &Path) -> Vec<(String, Option<i64>, Option<f64>)> {
+        let connection = Connection::open(path).unwrap();
+        let mut statement = connection
+            .prepare("SELECT name, age, salary FROM output ORDER BY rowid")
+            .unwrap();
+        statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn creates_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.sqlite");
+
+        SqliteSink::new(&path, "output", WriteMode::Create)
+            .write(&dfs())
+            .unwrap();
+
+        assert_eq!(
+            read_table(&path),
+            vec![
+                (String::from("Jen"), None, Some(19319.0)),
+                (String::from("Dave"), Some(40), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn create_fails_when_table_exists() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.sqlite");
+        let sink = SqliteSink::new(&path, "output", WriteMode::Create);
+        sink.write(&dfs()).unwrap();
+
+        let result = sink.write(&dfs());
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::SinkError(message) => message.contains("already exists"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn replaces_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.sqlite");
+        SqliteSink::new(&path, "output", WriteMode::Create)
+            .write(&dfs())
+            .unwrap();
+
+        SqliteSink::new(&path, "output", WriteMode::Replace)
+            .write(&dfs())
+            .unwrap();
+
+        assert_eq!(read_table(&path).len(), 2);
+    }
+
+    #[test]
+    fn replaces_table_without_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.sqlite");
+        let sink = SqliteSink::new(&path, "output", WriteMode::Replace);
+
+        sink.write(&[vec![]]).unwrap();
+        sink.write(&dfs()).unwrap();
+        sink.write(&[vec![]]).unwrap();
+
+        assert_eq!(read_table(&path), vec![]);
+        assert!(SqliteSink::new(&path, "output", WriteMode::Create)
+            .write(&[vec![]])
+            .is_err());
+    }
+
+    #[test]
+    fn appends_to_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.sqlite");
+        let sink = SqliteSink::new(&path, "output", WriteMode::Append);
+
+        sink.write(&dfs()).unwrap();
+        sink.write(&dfs()).unwrap();
+
+        assert_eq!(read_table(&path).len(), 4);
+    }
+}
@@ -0,0 +1,573 @@
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, BinaryHeap, HashMap},
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    mem::size_of,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use tempfile::TempDir;
+
+use crate::core::{
+    dataframe::{ColumnValue, Dataframe, Row},
+    error::RustyPipesError,
+    result::RustyPipesResult,
+};
+
+/// The number of partitions that the partial aggregates of a spilled aggregation are spread over.
+const AGGREGATE_PARTITIONS: usize = 16;
+
+/// The maximum number of runs that an external sort merges at once, which bounds the number of files it reads at once.
+pub(crate) const MERGE_FAN_IN: usize = 16;
+
+fn spill_err(e: impl std::fmt::Display) -> RustyPipesError {
+    RustyPipesError::TransformationError(format!("Unable to spill to disk: {}", e))
+}
+
+/// A budget for the memory that a single operation may use to hold its intermediate state, such as the build side of a
+/// join. Operations that exceed their budget spill that state to files in a temporary directory, which is removed
+/// once the operation is done. Sizes are estimated (see [`estimated_size`]), so the budget is approximate. The budget
+/// only covers this intermediate state, not the rows that an operation consumes or yields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryBudget {
+    bytes: usize,
+    directory: Option<PathBuf>,
+}
+
+impl MemoryBudget {
+    /// Construct a budget of the given number of bytes, which spills to the temporary directory of the system.
+    pub fn new(bytes: usize) -> Self {
+        MemoryBudget {
+            bytes,
+            directory: None,
+        }
+    }
+
+    /// Spill to a temporary directory within the given directory instead.
+    pub fn with_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// The number of bytes of this budget.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Whether the estimated size of the given rows exceeds this budget.
+    pub fn is_exceeded_by<'r>(&self, rows: impl IntoIterator<Item = &'r Row>) -> bool {
+        let mut size = 0;
+        rows.into_iter().any(|row| {
+            size += estimated_size(row);
+            size > self.bytes
+        })
+    }
+
+    /// Create a temporary directory to spill to, which is removed when it is dropped.
+    pub(crate) fn spill_directory(&self) -> RustyPipesResult<TempDir> {
+        let directory = self.directory.clone().unwrap_or_else(std::env::temp_dir);
+        tempfile::Builder::new()
+            .prefix("rusty_pipes_spill")
+            .tempdir_in(directory)
+            .map_err(spill_err)
+    }
+}
+
+/// An estimate of the number of bytes that a row occupies in memory.
+pub fn estimated_size(row: &Row) -> usize {
+    size_of::<Row>()
+        + row
+            .iter()
+            .map(|(key, value)| {
+                let value_size = match value {
+                    ColumnValue::String(s) => s.len(),
+                    _ => 0,
+                };
+                size_of::<(String, ColumnValue)>() + key.len() + value_size
+            })
+            .sum::<usize>()
+}
+
+/// The partition, out of the given number of partitions, that a key is assigned to.
+pub(crate) fn partition<K: Hash + ?Sized>(key: &K, count: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() % count as u64) as usize
+}
+
+pub(crate) fn write_u64(writer: &mut impl Write, value: u64) -> std::io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn write_str(writer: &mut impl Write, value: &str) -> std::io::Result<()> {
+    write_u64(writer, value.len() as u64)?;
+    writer.write_all(value.as_bytes())
+}
+
+fn read_string(reader: &mut impl Read) -> std::io::Result<String> {
+    let len = read_u64(reader)?;
+    let mut bytes = vec![0; len as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+/// A record that can be written to a spill file and read back.
+pub(crate) trait Spillable: Sized {
+    fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()>;
+    fn read_from(reader: &mut impl Read) -> std::io::Result<Self>;
+}
+
+impl Spillable for Row {
+    fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        write_u64(writer, self.len() as u64)?;
+        for (key, value) in self {
+            write_str(writer, key)?;
+            match value {
+                ColumnValue::None => writer.write_all(&[0])?,
+                ColumnValue::Integer(i) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&i.to_le_bytes())?;
+                }
+                ColumnValue::Decimal(d) => {
+                    writer.write_all(&[2])?;
+                    writer.write_all(&d.to_le_bytes())?;
+                }
+                ColumnValue::String(s) => {
+                    writer.write_all(&[3])?;
+                    write_str(writer, s)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        let len = read_u64(reader)?;
+        let mut row = HashMap::with_capacity(len as usize);
+        for _ in 0..len {
+            let key = read_string(reader)?;
+            let mut tag = [0];
+            reader.read_exact(&mut tag)?;
+            let value = match tag[0] {
+                0 => ColumnValue::None,
+                1 => ColumnValue::Integer(read_u64(reader)? as i64),
+                2 => ColumnValue::Decimal(f64::from_bits(read_u64(reader)?)),
+                3 => ColumnValue::String(read_string(reader)?),
+                tag => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("invalid value tag {}", tag),
+                    ))
+                }
+            };
+            row.insert(key, value);
+        }
+        Ok(row)
+    }
+}
+
+impl Spillable for (String, Row) {
+    fn write_to(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        write_str(writer, &self.0)?;
+        self.1.write_to(writer)
+    }
+
+    fn read_from(reader: &mut impl Read) -> std::io::Result<Self> {
+        Ok((read_string(reader)?, Row::read_from(reader)?))
+    }
+}
+
+/// A writer of records to a new spill file.
+pub(crate) struct SpillWriter<T> {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    records: PhantomData<T>,
+}
+
+impl<T: Spillable> SpillWriter<T> {
+    /// Create a spill file with the given name in the given directory.
+    pub(crate) fn create(directory: &Path, name: impl std::fmt::Display) -> RustyPipesResult<Self> {
+        let path = directory.join(name.to_string());
+        let file = File::create(&path).map_err(spill_err)?;
+        Ok(SpillWriter {
+            path,
+            writer: BufWriter::new(file),
+            records: PhantomData,
+        })
+    }
+
+    pub(crate) fn write(&mut self, record: &T) -> RustyPipesResult<()> {
+        record.write_to(&mut self.writer).map_err(spill_err)
+    }
+
+    /// Flush the records to the spill file, after which they can be read back.
+    pub(crate) fn finish(mut self) -> RustyPipesResult<SpillFile<T>> {
+        self.writer.flush().map_err(spill_err)?;
+        Ok(SpillFile {
+            path: self.path,
+            records: PhantomData,
+        })
+    }
+}
+
+/// A spill file, of which the records can be read back in the order they were written.
+pub(crate) struct SpillFile<T> {
+    path: PathBuf,
+    records: PhantomData<T>,
+}
+
+impl<T: Spillable> SpillFile<T> {
+    pub(crate) fn read(&self) -> RustyPipesResult<impl Iterator<Item = RustyPipesResult<T>>> {
+        let mut reader = BufReader::new(File::open(&self.path).map_err(spill_err)?);
+        Ok(std::iter::from_fn(move || match reader.fill_buf() {
+            Ok([]) => None,
+            Ok(_) => Some(T::read_from(&mut reader).map_err(spill_err)),
+            Err(e) => Some(Err(spill_err(e))),
+        }))
+    }
+
+    /// Remove the spill file, once its records are no longer needed.
+    pub(crate) fn remove(self) -> RustyPipesResult<()> {
+        std::fs::remove_file(&self.path).map_err(spill_err)
+    }
+}
+
+/// Rows that are yielded in sorted order by [`external_sort`].
+pub(crate) type SortedRows<'a> = Box<dyn Iterator<Item = RustyPipesResult<Row>> + 'a>;
+
+/// The comparison of rows by an external sort, which is shared by the heads of the runs that are merged.
+type Compare<'a> = Rc<dyn Fn(&Row, &Row) -> Ordering + 'a>;
+
+/// The next row of a run that is merged. Heads are ordered such that a heap of them yields the least row first, and of
+/// equal rows the one of the earliest run, which keeps the merge stable.
+struct MergeHead<'a> {
+    row: Row,
+    run: usize,
+    compare: Compare<'a>,
+}
+
+impl PartialEq for MergeHead<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MergeHead<'_> {}
+
+impl PartialOrd for MergeHead<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MergeHead<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // A BinaryHeap yields the greatest element first, so both orders are reversed.
+        (self.compare)(&other.row, &self.row).then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+/// Merge sorted runs into a single sorted run, resolving ties in favour of the earliest run.
+fn merge<'a>(
+    mut runs: Vec<SortedRows<'a>>,
+    compare: &Compare<'a>,
+) -> RustyPipesResult<SortedRows<'a>> {
+    let mut heads = BinaryHeap::with_capacity(runs.len());
+    for (run, rows) in runs.iter_mut().enumerate() {
+        if let Some(row) = rows.next().transpose()? {
+            heads.push(MergeHead {
+                row,
+                run,
+                compare: compare.clone(),
+            });
+        }
+    }
+    Ok(Box::new(std::iter::from_fn(move || {
+        let MergeHead { row, run, compare } = heads.pop()?;
+        match runs[run].next().transpose() {
+            Ok(Some(next)) => heads.push(MergeHead {
+                row: next,
+                run,
+                compare,
+            }),
+            Ok(None) => {}
+            Err(err) => return Some(Err(err)),
+        }
+        Some(Ok(row))
+    })))
+}
+
+fn read_run<'a>(run: &SpillFile<Row>) -> RustyPipesResult<SortedRows<'a>> {
+    Ok(Box::new(run.read()?))
+}
+
+/// Sort rows by the given comparison, holding at most about the budget of rows in memory: the rows are split into runs
+/// that fit the budget, each of which is sorted and spilled to disk, after which the runs are merged as the sorted
+/// rows are yielded. At most [`MERGE_FAN_IN`] runs are merged at once; more runs are first merged into fewer, longer
+/// runs on disk, one group at a time. When all rows fit the budget, they are sorted in memory. The sort is stable.
+///
+/// No operation sorts rows yet; this is the building block for one that sorts in bounded memory.
+#[allow(dead_code)]
+pub(crate) fn external_sort<'a>(
+    rows: impl IntoIterator<Item = Row>,
+    compare: impl Fn(&Row, &Row) -> Ordering + 'a,
+    budget: &MemoryBudget,
+) -> RustyPipesResult<SortedRows<'a>> {
+    let compare: Compare<'a> = Rc::new(compare);
+    let mut directory = None;
+    let mut files = 0;
+    let mut runs = vec![];
+    let mut run = vec![];
+    let mut size = 0;
+    for row in rows {
+        size += estimated_size(&row);
+        run.push(row);
+        if size > budget.bytes {
+            let directory = match &directory {
+                Some(directory) => directory,
+                None => directory.insert(budget.spill_directory()?),
+            };
+            run.sort_by(|a, b| compare(a, b));
+            let mut writer = SpillWriter::create(directory.path(), files)?;
+            files += 1;
+            for row in run.drain(..) {
+                writer.write(&row)?;
+            }
+            runs.push(writer.finish()?);
+            size = 0;
+        }
+    }
+    run.sort_by(|a, b| compare(a, b));
+    let Some(directory) = directory else {
+        return Ok(Box::new(run.into_iter().map(Ok)));
+    };
+
+    // Consecutive runs are merged, such that the merged runs remain in the order of the rows they hold.
+    while runs.len() > MERGE_FAN_IN {
+        let mut merged = Vec::with_capacity(runs.len().div_ceil(MERGE_FAN_IN));
+        for group in runs.chunks(MERGE_FAN_IN) {
+            let sources = group
+                .iter()
+                .map(read_run)
+                .collect::<RustyPipesResult<_>>()?;
+            let mut writer = SpillWriter::create(directory.path(), files)?;
+            files += 1;
+            for row in merge(sources, &compare)? {
+                writer.write(&row?)?;
+            }
+            merged.push(writer.finish()?);
+            for run in group {
+                std::fs::remove_file(&run.path).map_err(spill_err)?;
+            }
+        }
+        runs = merged;
+    }
+
+    let mut sources = runs
+        .iter()
+        .map(read_run)
+        .collect::<RustyPipesResult<Vec<_>>>()?;
+    sources.push(Box::new(run.into_iter().map(Ok)));
+    let mut rows = merge(sources, &compare)?;
+    Ok(Box::new(std::iter::from_fn(move || {
+        // The directory holds the runs, so it is only removed once the rows have been merged.
+        let _ = &directory;
+        rows.next()
+    })))
+}
+
+/// Aggregate rows into one row per group, holding the partial aggregates of at most about the budget of groups in
+/// memory. The group of a row is given by `key`; `update` folds a row into the partial aggregate of its group (which is
+/// None for the first row of the group), and `merge` combines two partial aggregates of the same group, the first of
+/// which covers the earlier rows. When the partial aggregates exceed the budget, they are spilled to partitions on
+/// disk by their key, after which each partition is merged in turn. The aggregates are yielded in the order of their
+/// keys.
+///
+/// No operation aggregates rows yet; this is the building block for one that aggregates in bounded memory.
+#[allow(dead_code)]
+pub(crate) fn aggregate(
+    rows: impl IntoIterator<Item = Row>,
+    key: impl Fn(&Row) -> String,
+    update: impl Fn(Option<Row>, &Row) -> Row,
+    merge: impl Fn(Row, Row) -> Row,
+    budget: &MemoryBudget,
+) -> RustyPipesResult<Dataframe> {
+    let mut groups: HashMap<String, Row> = HashMap::new();
+    let mut size = 0;
+    // The directory holds the spilled partitions, so it is only removed once they have been merged.
+    let mut directory: Option<TempDir> = None;
+    let mut writers: Vec<SpillWriter<(String, Row)>> = vec![];
+    for row in rows {
+        let key = key(&row);
+        let partial = groups.remove(&key);
+        let previous_size = partial.as_ref().map_or(0, estimated_size);
+        let updated = update(partial, &row);
+        size = size - previous_size + estimated_size(&updated);
+        if previous_size == 0 {
+            size += key.len();
+        }
+        groups.insert(key, updated);
+
+        if size > budget.bytes {
+            if directory.is_none() {
+                let created = directory.insert(budget.spill_directory()?);
+                writers = (0..AGGREGATE_PARTITIONS)
+                    .map(|index| SpillWriter::create(created.path(), index))
+                    .collect::<RustyPipesResult<Vec<_>>>()?;
+            }
+            for record in groups.drain() {
+                writers[partition(&record.0, AGGREGATE_PARTITIONS)].write(&record)?;
+            }
+            size = 0;
+        }
+    }
+
+    let mut aggregates: Vec<(String, Row)> = if directory.is_none() {
+        groups.into_iter().collect()
+    } else {
+        let mut remaining: Vec<Vec<(String, Row)>> = vec![vec![]; AGGREGATE_PARTITIONS];
+        for (key, partial) in groups {
+            remaining[partition(&key, AGGREGATE_PARTITIONS)].push((key, partial));
+        }
+        let mut aggregates = vec![];
+        for (writer, remaining) in writers.into_iter().zip(remaining) {
+            let mut merged: HashMap<String, Row> = HashMap::new();
+            let spilled = writer.finish()?;
+            for record in spilled.read()?.chain(remaining.into_iter().map(Ok)) {
+                let (key, partial) = record?;
+                let partial = match merged.remove(&key) {
+                    Some(earlier) => merge(earlier, partial),
+                    None => partial,
+                };
+                merged.insert(key, partial);
+            }
+            aggregates.extend(merged);
+        }
+        aggregates
+    };
+    aggregates.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(aggregates.into_iter().map(|(_, row)| row).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn row(group: &str, value: i64) -> Row {
+        HashMap::from([
+            (
+                String::from("group"),
+                ColumnValue::String(String::from(group)),
+            ),
+            (String::from("value"), ColumnValue::Integer(value)),
+        ])
+    }
+
+    fn value(row: &Row) -> i64 {
+        match row.get("value") {
+            Some(ColumnValue::Integer(i)) => *i,
+            _ => panic!("expected an integer value"),
+        }
+    }
+
+    #[test]
+    fn round_trips_rows_through_spill_files() {
+        let directory = MemoryBudget::new(0).spill_directory().unwrap();
+        let rows = vec![
+            HashMap::from([
+                (String::from("i"), ColumnValue::Integer(-3)),
+                (String::from("d"), ColumnValue::Decimal(0.25)),
+                (String::from("s"), ColumnValue::String(String::from("é"))),
+                (String::from("n"), ColumnValue::None),
+            ]),
+            HashMap::new(),
+        ];
+        let mut writer = SpillWriter::create(directory.path(), "rows").unwrap();
+        for row in &rows {
+            writer.write(row).unwrap();
+        }
+
+        let read = writer
+            .finish()
+            .unwrap()
+            .read()
+            .unwrap()
+            .collect::<RustyPipesResult<Vec<Row>>>();
+
+        assert_eq!(read.unwrap(), rows);
+    }
+
+    #[test]
+    fn sorts_rows_stably_in_external_memory() {
+        let rows = (0..500)
+            .map(|i| row(&format!("{}", i % 7), i))
+            .collect::<Vec<_>>();
+        let compare = |a: &Row, b: &Row| value(a).rem_euclid(10).cmp(&value(b).rem_euclid(10));
+        let mut expected = rows.clone();
+        expected.sort_by(compare);
+
+        let sorted = external_sort(rows, compare, &MemoryBudget::new(1_000))
+            .unwrap()
+            .collect::<RustyPipesResult<Vec<_>>>();
+
+        assert_eq!(sorted.unwrap(), expected);
+    }
+
+    #[test]
+    fn merges_runs_in_multiple_passes() {
+        // Every row is a run of its own, which takes more than two passes to merge.
+        let count = (MERGE_FAN_IN * MERGE_FAN_IN + 1) as i64;
+        let rows = (0..count)
+            .map(|i| row("", (i * 7919) % count))
+            .collect::<Vec<_>>();
+        let compare = |a: &Row, b: &Row| value(a).rem_euclid(3).cmp(&value(b).rem_euclid(3));
+        let mut expected = rows.clone();
+        expected.sort_by(compare);
+
+        let sorted = external_sort(rows, compare, &MemoryBudget::new(0))
+            .unwrap()
+            .collect::<RustyPipesResult<Vec<_>>>();
+
+        assert_eq!(sorted.unwrap(), expected);
+    }
+
+    #[test]
+    fn aggregates_with_spilled_partial_aggregates() {
+        let rows = (0..1_000).map(|i| row(&format!("{:03}", i % 100), i));
+        let sum = |partial: Option<Row>, row: &Row| {
+            let total = partial.as_ref().map_or(0, value) + value(row);
+            let mut aggregate = row.clone();
+            aggregate.insert(String::from("value"), ColumnValue::Integer(total));
+            aggregate
+        };
+        let merge = |mut earlier: Row, later: Row| {
+            let total = value(&earlier) + value(&later);
+            earlier.insert(String::from("value"), ColumnValue::Integer(total));
+            earlier
+        };
+        let key = |row: &Row| match row.get("group") {
+            Some(ColumnValue::String(group)) => group.clone(),
+            _ => String::new(),
+        };
+
+        let spilled = aggregate(rows.clone(), key, sum, merge, &MemoryBudget::new(2_000)).unwrap();
+        let in_memory = aggregate(rows, key, sum, merge, &MemoryBudget::new(usize::MAX)).unwrap();
+
+        assert_eq!(spilled.len(), 100);
+        assert_eq!(spilled, in_memory);
+        assert_eq!(
+            value(&spilled[1]),
+            (0..10).map(|i| i * 100 + 1).sum::<i64>()
+        );
+    }
+}
@@ -0,0 +1,49 @@
+/// A span of the `tracing` crate.
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::Span;
+
+/// Create a span at the info level, as `tracing::info_span!` does.
+#[cfg(feature = "tracing")]
+macro_rules! info_span {
+    ($($arg:tt)*) => {
+        tracing::info_span!($($arg)*)
+    };
+}
+
+/// A stand-in for a span of the `tracing` crate, which does nothing, for when the "tracing" feature is disabled.
+#[cfg(not(feature = "tracing"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Span;
+
+/// A stand-in for the guard of an entered span.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Entered;
+
+#[cfg(not(feature = "tracing"))]
+impl Span {
+    pub(crate) fn current() -> Self {
+        Span
+    }
+
+    pub(crate) fn enter(&self) -> Entered {
+        Entered
+    }
+
+    pub(crate) fn in_scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+
+    pub(crate) fn record<V>(&self, _field: &str, _value: V) -> &Self {
+        self
+    }
+}
+
+/// Create a stand-in for a span, ignoring its fields.
+#[cfg(not(feature = "tracing"))]
+macro_rules! info_span {
+    ($($arg:tt)*) => {
+        $crate::trace::Span
+    };
+}
+
+pub(crate) use info_span;
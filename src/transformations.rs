@@ -1,5 +1,11 @@
 pub mod inner_join;
 pub mod filter;
+pub mod script;
+pub mod select;
+pub mod wasm;
 
 pub use inner_join::InnerJoin;
 pub use filter::Filter;
+pub use script::Script;
+pub use select::Select;
+pub use wasm::WasmPlugin;
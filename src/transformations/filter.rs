@@ -1,8 +1,13 @@
+use crate::core::cancellation::CancellationToken;
 use crate::core::context::Context;
-use crate::core::dataframe::{ColumnValue, Dataframe};
+use crate::core::dataframe::{ColumnValue, Dataframe, Row};
 use crate::core::error::RustyPipesError;
 use crate::core::result::RustyPipesResult;
 use crate::core::transformation::Transformation;
+use rayon::prelude::*;
+
+/// The number of rows that are evaluated between checks of the cancellation token.
+const CANCELLATION_INTERVAL: usize = 4096;
 
 macro_rules! compare {
     ($cmp:ident,$value:expr,$target:expr) => {
@@ -15,23 +20,19 @@ macro_rules! compare {
                 let parsed = $target.parse::<i64>().unwrap();
                 v.$cmp(&parsed)
             }
-            ColumnValue::String(v) => v.as_str().$cmp($target),
+            ColumnValue::String(v) => v.as_str().$cmp($target.as_str()),
             _ => false,
         }
     };
 }
 
 fn resolve_target(key: &str, context: &Context) -> RustyPipesResult<String> {
-    if key.starts_with(':') {
-        context
-            .parameter_value(key.chars().skip(1).collect::<String>().as_str())
-            .map(|value| value.to_owned())
-            .ok_or_else(|| {
-                RustyPipesError::TransformationError(format!("Unable to resolve parameter {}", key))
-            })
-    } else {
-        Ok(key.to_owned())
-    }
+    context
+        .resolve(key)
+        .map(|value| value.to_owned())
+        .ok_or_else(|| {
+            RustyPipesError::TransformationError(format!("Unable to resolve parameter {}", key))
+        })
 }
 
 fn contains_text(value: &ColumnValue, target: &str) -> bool {
@@ -78,59 +79,106 @@ fn parse_err(predicate: &str) -> RustyPipesError {
     RustyPipesError::TransformationError(format!("Unable to parse predicate {}", predicate))
 }
 
-/// Filter a Dataframe based on a given predicate. Only those rows for which the predicate is true are retained.
-/// This operation has an arity of one: it requires a single dataframe to be provided as its input.
-pub struct Filter<'a> {
+/// The name of the column that a predicate refers to, if the predicate is well-formed enough to have one.
+pub(crate) fn predicate_column(predicate: &str) -> Option<&str> {
+    predicate.split_whitespace().next()
+}
+
+struct Condition<'a> {
     field_name: &'a str,
     operation: Operation,
     resolved_target: String,
 }
 
-impl<'a> Filter<'a> {
-    /// Construct a new Filter from the given predicate. The expected format of this predicate is
-    /// "column_name operation literal" where operation is one of >, >=, <, <=, ==, or != and the literal is
-    /// an integer, decimal, or string. E.g., "column_one >= 100.5".
-    pub fn new(predicate: &'a str, context: &Context) -> RustyPipesResult<Self> {
+impl<'a> Condition<'a> {
+    fn parse(predicate: &'a str, context: &Context) -> RustyPipesResult<Self> {
         let mut s = predicate.split_whitespace();
         let field_name = s.next().ok_or_else(|| parse_err(predicate))?;
         let operation = s.next().ok_or_else(|| parse_err(predicate))?.try_into()?;
         let target = s.next().ok_or_else(|| parse_err(predicate))?;
         let resolved_target = resolve_target(target, context)?;
 
-        Ok(Filter {
+        Ok(Condition {
             field_name,
             operation,
             resolved_target,
         })
     }
+
+    fn evaluate(&self, row: &Row) -> bool {
+        match row.get(self.field_name) {
+            Some(value) => match self.operation {
+                Operation::Gt => compare!(gt, value, &self.resolved_target),
+                Operation::Ge => compare!(ge, value, &self.resolved_target),
+                Operation::Lt => compare!(lt, value, &self.resolved_target),
+                Operation::Le => compare!(le, value, &self.resolved_target),
+                Operation::Eq => compare!(eq, value, &self.resolved_target),
+                Operation::Ne => compare!(ne, value, &self.resolved_target),
+                Operation::Contains => contains_text(value, self.resolved_target.as_str()),
+                Operation::NotContains => !contains_text(value, self.resolved_target.as_str()),
+            },
+            None => false,
+        }
+    }
+}
+
+/// Filter a Dataframe based on a given predicate. Only those rows for which the predicate is true are retained.
+/// This operation has an arity of one: it requires a single dataframe to be provided as its input. The rows are
+/// evaluated in parallel, and retained in their original order. Filtering stops once the run is cancelled.
+pub struct Filter<'a> {
+    conditions: Vec<Condition<'a>>,
+    cancellation: CancellationToken,
+}
+
+impl<'a> Filter<'a> {
+    /// Construct a new Filter from the given predicate. The expected format of this predicate is
+    /// "column_name operation literal" where operation is one of >, >=, <, <=, ==, or != and the literal is
+    /// an integer, decimal, or string. E.g., "column_one >= 100.5".
+    pub fn new(predicate: &'a str, context: &Context) -> RustyPipesResult<Self> {
+        Self::all([predicate], context)
+    }
+
+    /// Construct a new Filter that retains only those rows for which all of the given predicates are true. This is
+    /// equivalent to, but cheaper than, applying a Filter for each predicate in turn.
+    pub fn all(
+        predicates: impl IntoIterator<Item = &'a str>,
+        context: &Context,
+    ) -> RustyPipesResult<Self> {
+        let conditions = predicates
+            .into_iter()
+            .map(|predicate| Condition::parse(predicate, context))
+            .collect::<RustyPipesResult<_>>()?;
+
+        Ok(Filter {
+            conditions,
+            cancellation: context.cancellation().clone(),
+        })
+    }
 }
 
 impl Transformation for Filter<'_> {
     fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
-        let mut filtered = vec![];
-        for row in dfs[0] {
-            if let Some(value) = row.get(self.field_name) {
-                let evaluated = match self.operation {
-                    Operation::Gt => compare!(gt, value, &self.resolved_target),
-                    Operation::Ge => compare!(ge, value, &self.resolved_target),
-                    Operation::Lt => compare!(lt, value, &self.resolved_target),
-                    Operation::Le => compare!(le, value, &self.resolved_target),
-                    Operation::Eq => compare!(eq, value, &self.resolved_target),
-                    Operation::Ne => compare!(ne, value, &self.resolved_target),
-                    Operation::Contains => contains_text(value, self.resolved_target.as_str()),
-                    Operation::NotContains => !contains_text(value, self.resolved_target.as_str()),
-                };
-
-                if evaluated {
-                    filtered.push(row.clone());
-                }
-            }
-        }
-        Ok(vec![filtered])
+        let chunks = dfs[0]
+            .par_chunks(CANCELLATION_INTERVAL)
+            .map(|chunk| {
+                self.cancellation.check()?;
+                Ok(chunk
+                    .iter()
+                    .filter(|row| {
+                        self.conditions
+                            .iter()
+                            .all(|condition| condition.evaluate(row))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>())
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+        Ok(vec![chunks.into_iter().flatten().collect()])
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod test {
     use super::*;
     use std::collections::HashMap;
@@ -323,6 +371,23 @@ mod test {
         )
     }
 
+    #[test]
+    fn filter_all() {
+        let op = Filter::all(["foo > 0", "foo != 2"], &Default::default()).unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert_eq!(
+            result.unwrap()[0],
+            vec![HashMap::from([(
+                String::from("foo"),
+                ColumnValue::Integer(1)
+            )])]
+        )
+    }
+
     #[test]
     fn filter_nonsense_predicate() {
         let op = Filter::new("foo ==", &Default::default());
@@ -1,53 +1,227 @@
+use crate::core::cancellation::CancellationToken;
+use crate::core::context::Context;
 use crate::core::dataframe::{ColumnValue, Dataframe, Row};
+use crate::core::definitions::{JoinStrategy, KeyCoercion};
 use crate::core::error::RustyPipesError;
 use crate::core::result::RustyPipesResult;
 use crate::core::transformation::Transformation;
+use crate::spill::{estimated_size, partition, MemoryBudget, SpillFile, SpillWriter};
+use rayon::prelude::*;
+use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::fmt::{self, Display, Write};
+use std::hash::{Hash, Hasher};
+use tempfile::TempDir;
 
-fn extract_identifier(from: &ColumnValue) -> RustyPipesResult<String> {
-    match from {
-        ColumnValue::String(s) => Ok(s.clone()),
-        ColumnValue::Integer(i) => Ok(i.to_string()),
-        _ => Err(RustyPipesError::TransformationError(
-            "Only ints or strings can be used as identifiers".to_owned(),
-        )),
+/// The number of rows, of both data frames combined, from which a join is performed in parallel. Smaller joins are not
+/// worth the overhead of partitioning.
+const PARALLEL_THRESHOLD: usize = 10_000;
+
+/// The number of partitions that the build side of a grace hash join is spread over, at each level of partitioning.
+const GRACE_PARTITIONS: usize = 16;
+
+/// The number of times that a partition of a grace hash join may be partitioned further. Partitions that still exceed
+/// the memory budget after this, e.g., as most of their rows have the same key, are joined in chunks instead.
+const MAX_GRACE_DEPTH: usize = 4;
+
+/// A decimal join key, which is neither NaN nor negative zero, such that decimals are equal exactly when their bits
+/// are.
+#[derive(Debug, Clone, Copy)]
+struct Decimal(f64);
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Decimal {}
+
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_bits().hash(state);
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// The typed value of a join key, borrowed from the row it identifies, after coercion. Keys only match when they have
+/// the same variant and value. Keys of different variants are ordered by variant, which suffices for a sort-merge join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum JoinKey<'r> {
+    Null,
+    Integer(i64),
+    Decimal(Decimal),
+    /// The number of days since 1970-01-01.
+    Date(i64),
+    Text(&'r str),
+}
+
+/// The key of a decimal, unless it is NaN: NaN is not equal to anything, so rows keyed by it never match.
+fn decimal_key<'r>(d: f64) -> Option<JoinKey<'r>> {
+    // Adding zero turns negative zero into zero, leaving all other decimals as they are.
+    (!d.is_nan()).then_some(JoinKey::Decimal(Decimal(d + 0.0)))
+}
+
+/// The key of a decimal when compared as a number: the integer it is equal to, if any, and the decimal itself
+/// otherwise.
+fn numeric_key<'r>(d: f64) -> Option<JoinKey<'r>> {
+    if d.fract() == 0.0 && d >= i64::MIN as f64 && d < i64::MAX as f64 {
+        Some(JoinKey::Integer(d as i64))
+    } else {
+        decimal_key(d)
     }
 }
 
-fn group_rows<'b>(key: &str, df: &'b Dataframe) -> RustyPipesResult<HashMap<String, Vec<&'b Row>>> {
-    let mut grouped: Vec<(String, Vec<&Row>)> = vec![];
-    for row in df {
-        let value = row.get(key);
-        if let Some(v) = value {
-            let identifier = extract_identifier(v)?;
-            grouped.push((identifier, vec![row]));
+/// Whether a value is displayed as the given text, which is determined without allocating.
+fn displays_as(value: impl Display, text: &str) -> bool {
+    struct Remainder<'t>(&'t str);
+
+    impl Write for Remainder<'_> {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0 = self.0.strip_prefix(s).ok_or(fmt::Error)?;
+            Ok(())
         }
     }
 
-    let mut result: HashMap<String, Vec<&Row>> = HashMap::new();
-    for (identifier, rows) in grouped {
-        if let Some(existing_rows) = result.get_mut(&identifier) {
-            existing_rows.extend(rows);
-        } else {
-            result.insert(identifier, rows);
+    let mut remainder = Remainder(text);
+    write!(remainder, "{}", value).is_ok() && remainder.0.is_empty()
+}
+
+/// The key of a string when compared by its textual representation: strings that are displayed numbers are keyed by
+/// those numbers, such that they match them.
+fn textual_key(s: &str) -> JoinKey<'_> {
+    if let Ok(i) = s.parse::<i64>() {
+        if displays_as(i, s) {
+            return JoinKey::Integer(i);
+        }
+    } else if let Ok(d) = s.parse::<f64>() {
+        if let Some(key) = numeric_key(d).filter(|_| displays_as(d, s)) {
+            return key;
+        }
+    }
+    JoinKey::Text(s)
+}
+
+/// The number of days since 1970-01-01 of an ISO 8601 date (e.g., "2024-01-31"), which may be followed by a time
+/// (e.g., "2024-01-31T12:00:00Z") that is ignored.
+fn parse_date(s: &str) -> Option<i64> {
+    if !s.is_ascii() {
+        return None;
+    }
+    let (date, time) = (s.get(..10)?, &s[10..]);
+    if !time.is_empty() && !time.starts_with(['T', ' ']) {
+        return None;
+    }
+    let digits = |range: std::ops::Range<usize>| {
+        let part = &date[range];
+        part.bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| part.parse::<i64>().ok())
+            .flatten()
+    };
+    if &date[4..5] != "-" || &date[7..8] != "-" {
+        return None;
+    }
+    let (year, month, day) = (digits(0..4)?, digits(5..7)?, digits(8..10)?);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+
+    // The days since 0000-03-01, counting years from March such that leap days fall at the end of a year.
+    let year = if month <= 2 { year - 1 } else { year };
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let days =
+        year * 365 + year.div_euclid(4) - year.div_euclid(100) + year.div_euclid(400) + day_of_year;
+    Some(days - 719_468)
+}
+
+/// The key of a value, coerced according to the given rule, unless it is a NaN decimal, which never matches.
+fn join_key(value: &ColumnValue, coercion: KeyCoercion) -> RustyPipesResult<Option<JoinKey<'_>>> {
+    Ok(Some(match (value, coercion) {
+        (ColumnValue::None, _) => JoinKey::Null,
+        (ColumnValue::Integer(i), _) => JoinKey::Integer(*i),
+        (ColumnValue::Decimal(d), KeyCoercion::Strict) => return Ok(decimal_key(*d)),
+        (ColumnValue::Decimal(d), _) => return Ok(numeric_key(*d)),
+        (ColumnValue::String(s), KeyCoercion::Text) => textual_key(s),
+        (ColumnValue::String(s), KeyCoercion::Date) => {
+            JoinKey::Date(parse_date(s).ok_or_else(|| {
+                RustyPipesError::TransformationError(format!(
+                    "Unable to parse join key {} as a date",
+                    s
+                ))
+            })?)
         }
+        (ColumnValue::String(s), _) => JoinKey::Text(s),
+    }))
+}
+
+/// Sort keyed rows by their keys, unless they are sorted already. The sort is stable. Yields whether the rows had to
+/// be sorted.
+fn sort_keyed_rows(rows: &mut [(usize, JoinKey)]) -> bool {
+    let sorted = rows.windows(2).all(|pair| pair[0].1 <= pair[1].1);
+    if !sorted {
+        rows.par_sort_by_key(|(_, key)| *key);
     }
+    !sorted
+}
 
-    Ok(result)
+fn join_rows(left: &Row, right: &Row) -> Row {
+    left.iter()
+        .chain(right.iter())
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
 }
 
 /// Inner Join two data frames. This operation has an arity of two: it requires two dataframes to be provided as its
-/// inputs.
+/// inputs. The rows of the smaller (build) data frame are grouped in a hash table, which the rows of the other (probe)
+/// data frame are looked up in.
+///
+/// Rows are matched on the typed values of their keys, which are coerced according to a configurable rule (see
+/// [`InnerJoin::with_key_coercion`]). Null keys never match, unless configured otherwise (see
+/// [`InnerJoin::with_matching_nulls`]), and rows without the key or with a NaN key are never matched.
+///
+/// Large joins, of 10,000 rows or more (of both data frames combined), are performed in parallel: both data frames are
+/// partitioned by the hash of their keys, after which each pair of partitions is joined on its own thread, building the
+/// hash table on the smaller data frame. The order of the joined rows is only guaranteed when the order is preserved
+/// (see [`InnerJoin::with_preserve_order`]), or when a hash join is smaller than that, in which case the joined rows
+/// are yielded in the order of the left rows they originate from, and then of the matching right rows.
+#[derive(Clone)]
 pub struct InnerJoin<'a> {
     left_key: &'a str,
     right_key: &'a str,
+    preserve_order: bool,
+    strategy: JoinStrategy,
+    coercion: KeyCoercion,
+    match_nulls: bool,
+    cancellation: CancellationToken,
 }
 
 impl<'a> InnerJoin<'a> {
     /// Construct a new InnerJoin from the given join clause.
     /// The expected format of this clause is "left_column_name = right_column_name" where left_column_name
     /// and right_column_name refer to the names of the identifying columns in the left and right dataframes.
-    pub fn new(join_on: &'a str) -> RustyPipesResult<Self> {
+    /// Joining stops with an error once the run is cancelled or times out; this is checked after the hash table is
+    /// built or the keys are sorted, and for each partition of a partitioned join.
+    pub fn new(join_on: &'a str, context: &Context) -> RustyPipesResult<Self> {
         let (left_key, right_key) =
             join_on
                 .split_once('=')
@@ -59,42 +233,539 @@ impl<'a> InnerJoin<'a> {
         Ok(InnerJoin {
             left_key: left_key.trim(),
             right_key: right_key.trim(),
+            preserve_order: false,
+            strategy: JoinStrategy::Hash,
+            coercion: KeyCoercion::Text,
+            match_nulls: false,
+            cancellation: context.cancellation().clone(),
         })
     }
+
+    /// Yield the joined rows in the order of the left rows they originate from, and then of the matching right rows,
+    /// also when the join is performed in parallel. This requires the joined rows to be sorted.
+    pub fn with_preserve_order(mut self, preserve_order: bool) -> Self {
+        self.preserve_order = preserve_order;
+        self
+    }
+
+    /// Match the rows using the given strategy, which defaults to a hash join. A sort-merge join sorts the identifiers
+    /// of both data frames, unless they are sorted already, and merges them; it yields the same rows as a hash join.
+    /// The joined rows are yielded in the order of their keys, which is the order of the left data frame when it is
+    /// sorted already, unless the order is preserved.
+    pub fn with_strategy(mut self, strategy: JoinStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Coerce the values of the keys according to the given rule before comparing them, which defaults to comparing
+    /// keys by their textual representation. Keys of different types never match, unless they are coerced to the same
+    /// type.
+    pub fn with_key_coercion(mut self, coercion: KeyCoercion) -> Self {
+        self.coercion = coercion;
+        self
+    }
+
+    /// Match rows of which the keys are null with each other. By default, null keys never match.
+    pub fn with_matching_nulls(mut self, match_nulls: bool) -> Self {
+        self.match_nulls = match_nulls;
+        self
+    }
+
+    /// The key of a row in the given column, unless the row is never matched: when it lacks the column, when its key
+    /// is NaN, or when its key is null and nulls do not match.
+    fn key<'r>(&self, row: &'r Row, column: &str) -> RustyPipesResult<Option<JoinKey<'r>>> {
+        let Some(value) = row.get(column) else {
+            return Ok(None);
+        };
+        let key = join_key(value, self.coercion)?;
+        Ok(key.filter(|key| self.match_nulls || *key != JoinKey::Null))
+    }
+
+    /// The indices and keys of the rows of a data frame that can be matched on the given column, in order.
+    fn key_rows<'r>(
+        &self,
+        column: &str,
+        df: &'r Dataframe,
+    ) -> RustyPipesResult<Vec<(usize, JoinKey<'r>)>> {
+        df.iter()
+            .enumerate()
+            .filter_map(|(index, row)| {
+                self.key(row, column)
+                    .map(|key| key.map(|key| (index, key)))
+                    .transpose()
+            })
+            .collect()
+    }
+
+    /// The indices and keys of the rows of a data frame that can be matched on the given column, partitioned by the
+    /// hash of their keys.
+    fn partition_rows<'r>(
+        &self,
+        column: &str,
+        df: &'r Dataframe,
+        count: usize,
+    ) -> RustyPipesResult<Vec<Vec<(usize, JoinKey<'r>)>>> {
+        let keys = df
+            .par_iter()
+            .enumerate()
+            .filter_map(|(index, row)| {
+                self.key(row, column)
+                    .map(|key| key.map(|key| (index, key)))
+                    .transpose()
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+
+        let mut partitions = vec![vec![]; count];
+        for (index, key) in keys {
+            partitions[partition(&key, count)].push((index, key));
+        }
+        Ok(partitions)
+    }
+
+    /// Group the rows that can be matched on the given column by their keys.
+    fn group_rows<'r>(
+        &self,
+        column: &str,
+        rows: impl IntoIterator<Item = &'r Row>,
+    ) -> RustyPipesResult<HashMap<JoinKey<'r>, Vec<&'r Row>>> {
+        let mut rows_by_key: HashMap<JoinKey, Vec<&Row>> = HashMap::new();
+        for row in rows {
+            if let Some(key) = self.key(row, column)? {
+                rows_by_key.entry(key).or_default().push(row);
+            }
+        }
+        Ok(rows_by_key)
+    }
+
+    /// Prepare the right (build) data frame, such that left (probe) data frames can be joined with it one at a time,
+    /// e.g., as batches of a streamed source. The build side is grouped in a hash table. The rows are joined as by a
+    /// hash join, regardless of the strategy.
+    pub fn build<'r>(&self, right: &'r Dataframe) -> RustyPipesResult<JoinTable<'a, 'r>> {
+        let build = Build::Memory(self.group_rows(self.right_key, right)?);
+        self.cancellation.check()?;
+        Ok(JoinTable {
+            join: self.clone(),
+            rows: right.len(),
+            build,
+        })
+    }
+
+    /// Prepare the right (build) side of the join from batches of its rows, holding at most about the given budget of
+    /// them in memory at once (a grace hash join). The rows are held in memory for as long as they fit the budget, in
+    /// which case they are yielded to be joined as usual. Otherwise, they are partitioned to disk by the hash of their
+    /// keys as the batches are consumed, and the partitions that exceed the budget are partitioned further, after which
+    /// left (probe) data frames are joined with one partition at a time (see [`JoinTable::probe`]).
+    pub fn build_batches(
+        &self,
+        batches: impl IntoIterator<Item = RustyPipesResult<Dataframe>>,
+        budget: &MemoryBudget,
+    ) -> RustyPipesResult<BatchedBuild<'a>> {
+        let mut batches = batches.into_iter();
+        let mut rows = vec![];
+        let mut size = 0;
+        while size <= budget.bytes() {
+            self.cancellation.check()?;
+            let Some(batch) = batches.next() else {
+                return Ok(BatchedBuild::Rows(rows));
+            };
+            let batch = batch?;
+            size += batch.iter().map(estimated_size).sum::<usize>();
+            rows.extend(batch);
+        }
+
+        let directory = budget.spill_directory()?;
+        let mut files = 0;
+        let mut writers = create_partitions(&directory, &mut files)?;
+        let mut count = rows.len();
+        for row in &rows {
+            self.spill_row(row, 0, &mut writers)?;
+        }
+        drop(rows);
+        for batch in batches {
+            self.cancellation.check()?;
+            let batch = batch?;
+            count += batch.len();
+            for row in &batch {
+                self.spill_row(row, 0, &mut writers)?;
+            }
+        }
+
+        let mut partitions = vec![];
+        for partition in finish_partitions(writers)? {
+            partitions.push(self.split_partition(partition, 1, budget, &directory, &mut files)?);
+        }
+        Ok(BatchedBuild::Spilled(JoinTable {
+            join: self.clone(),
+            rows: count,
+            build: Build::Spilled {
+                partitions,
+                budget: budget.bytes(),
+                _directory: directory,
+            },
+        }))
+    }
+
+    /// Write a row of the build side to the partition, out of the partitions of the given level, that its key is
+    /// assigned to. Rows that are never matched are left out.
+    fn spill_row(
+        &self,
+        row: &Row,
+        level: usize,
+        writers: &mut [(SpillWriter<Row>, usize)],
+    ) -> RustyPipesResult<()> {
+        if let Some(key) = self.key(row, self.right_key)? {
+            let (writer, size) = &mut writers[grace_partition(&key, level)];
+            writer.write(row)?;
+            *size += estimated_size(row);
+        }
+        Ok(())
+    }
+
+    /// Partition a partition of the build side that exceeds the budget further, by the keys of its rows at the given
+    /// level, until each partition fits the budget or the maximum depth is reached. A partition of which all rows are
+    /// assigned to the same partition of the next level, which most likely means they share a key, is left as it is.
+    fn split_partition(
+        &self,
+        partition: Partition,
+        level: usize,
+        budget: &MemoryBudget,
+        directory: &TempDir,
+        files: &mut usize,
+    ) -> RustyPipesResult<Partition> {
+        let (file, size) = match partition {
+            Partition::Rows { file, size } if size > budget.bytes() && level <= MAX_GRACE_DEPTH => {
+                (file, size)
+            }
+            partition => return Ok(partition),
+        };
+        self.cancellation.check()?;
+        let mut writers = create_partitions(directory, files)?;
+        for row in file.read()? {
+            self.spill_row(&row?, level, &mut writers)?;
+        }
+        file.remove()?;
+
+        let mut partitions = vec![];
+        for partition in finish_partitions(writers)? {
+            partitions.push(match partition {
+                Partition::Rows { size: part, .. } if part == size => partition,
+                partition => self.split_partition(partition, level + 1, budget, directory, files)?,
+            });
+        }
+        Ok(Partition::Split(partitions))
+    }
+
+    /// Join by grouping the left data frame, when it is the smaller one, in a hash table and looking up the right rows in
+    /// it. The matching right rows are collected per left row, so that the joined rows are yielded in the same order as
+    /// when the right data frame is grouped.
+    fn build_left_join(&self, left: &Dataframe, right: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let mut left_rows_by_key: HashMap<JoinKey, Vec<usize>> = HashMap::new();
+        for (index, key) in self.key_rows(self.left_key, left)? {
+            left_rows_by_key.entry(key).or_default().push(index);
+        }
+        self.cancellation.check()?;
+
+        let mut matches = vec![vec![]; left.len()];
+        for row in right {
+            if let Some(matching_rows) = self
+                .key(row, self.right_key)?
+                .and_then(|key| left_rows_by_key.get(&key))
+            {
+                for &index in matching_rows {
+                    matches[index].push(row);
+                }
+            }
+        }
+        Ok(left
+            .iter()
+            .zip(matches)
+            .flat_map(|(row, matching_rows)| {
+                matching_rows
+                    .into_iter()
+                    .map(move |matching_row| join_rows(row, matching_row))
+            })
+            .collect())
+    }
+
+    fn parallel_join(&self, left: &Dataframe, right: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let count = rayon::current_num_threads() * 4;
+        let left_partitions = self.partition_rows(self.left_key, left, count)?;
+        let right_partitions = self.partition_rows(self.right_key, right, count)?;
+        let build_left = left.len() < right.len();
+
+        let mut pairs = left_partitions
+            .into_par_iter()
+            .zip(right_partitions)
+            .map(|(left_rows, right_rows)| {
+                self.cancellation.check()?;
+                let (build, probe) = if build_left {
+                    (left_rows, right_rows)
+                } else {
+                    (right_rows, left_rows)
+                };
+                let mut rows_by_key: HashMap<JoinKey, Vec<usize>> = HashMap::new();
+                for (index, key) in build {
+                    rows_by_key.entry(key).or_default().push(index);
+                }
+                let mut pairs = vec![];
+                for (probe_index, key) in probe {
+                    if let Some(matching_rows) = rows_by_key.get(&key) {
+                        pairs.extend(matching_rows.iter().map(|&build_index| {
+                            if build_left {
+                                (build_index, probe_index)
+                            } else {
+                                (probe_index, build_index)
+                            }
+                        }));
+                    }
+                }
+                Ok(pairs)
+            })
+            .collect::<RustyPipesResult<Vec<Vec<(usize, usize)>>>>()?
+            .concat();
+
+        if self.preserve_order {
+            pairs.par_sort_unstable();
+        }
+        Ok(pairs
+            .into_par_iter()
+            .map(|(left_index, right_index)| join_rows(&left[left_index], &right[right_index]))
+            .collect())
+    }
+
+    fn sort_merge_join(&self, left: &Dataframe, right: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let mut left_rows = self.key_rows(self.left_key, left)?;
+        let mut right_rows = self.key_rows(self.right_key, right)?;
+        let left_sorted = !sort_keyed_rows(&mut left_rows);
+        sort_keyed_rows(&mut right_rows);
+        self.cancellation.check()?;
+
+        let mut pairs = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < left_rows.len() && j < right_rows.len() {
+            match left_rows[i].1.cmp(&right_rows[j].1) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    let key = right_rows[j].1;
+                    let run = right_rows[j..]
+                        .iter()
+                        .take_while(|(_, other)| *other == key)
+                        .count();
+                    while i < left_rows.len() && left_rows[i].1 == key {
+                        pairs.extend(
+                            right_rows[j..j + run]
+                                .iter()
+                                .map(|(right_index, _)| (left_rows[i].0, *right_index)),
+                        );
+                        i += 1;
+                    }
+                    j += run;
+                }
+            }
+        }
+
+        if self.preserve_order && !left_sorted {
+            pairs.par_sort_unstable();
+        }
+        Ok(pairs
+            .into_par_iter()
+            .map(|(left_index, right_index)| join_rows(&left[left_index], &right[right_index]))
+            .collect())
+    }
 }
 
 impl Transformation for InnerJoin<'_> {
     fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
-        let right_rows_by_key = group_rows(self.right_key, dfs[1])?;
-
-        let mut joined = vec![];
-
-        for row in dfs[0] {
-            let value = row.get(self.left_key);
-            if let Some(v) = value {
-                let identifier = extract_identifier(v)?;
-                let rows = right_rows_by_key
-                    .get(&identifier)
-                    .map_or(vec![], |matching_rows| {
-                        matching_rows
-                            .iter()
-                            .map(|matching_row| {
-                                row.iter()
-                                    .chain(matching_row.iter())
-                                    .map(|(key, value)| (key.clone(), value.clone()))
-                                    .collect::<Row>()
-                            })
-                            .collect::<Vec<_>>()
-                    });
-                joined.extend(rows);
+        if self.strategy == JoinStrategy::SortMerge {
+            return Ok(vec![self.sort_merge_join(dfs[0], dfs[1])?]);
+        }
+        if dfs[0].len() + dfs[1].len() >= PARALLEL_THRESHOLD {
+            return Ok(vec![self.parallel_join(dfs[0], dfs[1])?]);
+        }
+        if dfs[0].len() < dfs[1].len() {
+            return Ok(vec![self.build_left_join(dfs[0], dfs[1])?]);
+        }
+        Ok(vec![self.build(dfs[1])?.probe(dfs[0])?])
+    }
+}
+
+/// The partition, out of the partitions of the given level of a grace hash join, that a key is assigned to. Keys are
+/// hashed along with the level, such that the keys of a partition are spread over the partitions of the next level.
+fn grace_partition(key: &JoinKey, level: usize) -> usize {
+    partition(&(level, key), GRACE_PARTITIONS)
+}
+
+/// Create the spill files of the partitions of a level of a grace hash join, along with the estimated size of the rows
+/// written to each.
+fn create_partitions(
+    directory: &TempDir,
+    files: &mut usize,
+) -> RustyPipesResult<Vec<(SpillWriter<Row>, usize)>> {
+    let mut writers = Vec::with_capacity(GRACE_PARTITIONS);
+    for _ in 0..GRACE_PARTITIONS {
+        writers.push((SpillWriter::create(directory.path(), *files)?, 0));
+        *files += 1;
+    }
+    Ok(writers)
+}
+
+fn finish_partitions(writers: Vec<(SpillWriter<Row>, usize)>) -> RustyPipesResult<Vec<Partition>> {
+    writers
+        .into_iter()
+        .map(|(writer, size)| {
+            Ok(Partition::Rows {
+                file: writer.finish()?,
+                size,
+            })
+        })
+        .collect()
+}
+
+/// A partition of the build side of a grace hash join, which holds the rows of which the keys are assigned to it.
+enum Partition {
+    /// The rows of the partition, on disk, along with their estimated size.
+    Rows { file: SpillFile<Row>, size: usize },
+    /// The partitions of the next level, which the rows of a partition that exceeded the budget are partitioned into.
+    Split(Vec<Partition>),
+}
+
+/// The build side of a join, as prepared by [`InnerJoin::build`] or [`InnerJoin::build_batches`].
+enum Build<'r> {
+    /// The rows of the build side, grouped by their keys.
+    Memory(HashMap<JoinKey<'r>, Vec<&'r Row>>),
+    /// The rows of the build side, partitioned to disk by the hash of their keys, of which about the given budget of
+    /// bytes may be loaded at once. The directory holds the partitions, so it is only removed once the table is
+    /// dropped.
+    Spilled {
+        partitions: Vec<Partition>,
+        budget: usize,
+        _directory: TempDir,
+    },
+}
+
+/// The build side of a join as prepared from batches by [`InnerJoin::build_batches`].
+pub enum BatchedBuild<'a> {
+    /// All rows of the build side, which fit the memory budget, to be joined as usual.
+    Rows(Dataframe),
+    /// The build side, partitioned to disk.
+    Spilled(JoinTable<'a, 'static>),
+}
+
+/// The prepared build side of a join, which left (probe) data frames are joined with by [`JoinTable::probe`].
+pub struct JoinTable<'a, 'r> {
+    join: InnerJoin<'a>,
+    rows: usize,
+    build: Build<'r>,
+}
+
+impl JoinTable<'_, '_> {
+    /// The number of rows of the build side.
+    pub fn build_rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Join a left (probe) data frame with the build side. The joined rows are yielded in the order of the left rows
+    /// they originate from, and then of the matching right rows. A spilled build side is loaded one partition at a
+    /// time, skipping the partitions that no left row is assigned to, so each call reads the partitions that its left
+    /// rows are assigned to from disk.
+    pub fn probe(&self, left: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let join = &self.join;
+        match &self.build {
+            Build::Memory(right_rows_by_key) => {
+                let mut joined = vec![];
+                for row in left {
+                    if let Some(matching_rows) = join
+                        .key(row, join.left_key)?
+                        .and_then(|key| right_rows_by_key.get(&key))
+                    {
+                        joined.extend(
+                            matching_rows
+                                .iter()
+                                .map(|matching_row| join_rows(row, matching_row)),
+                        );
+                    }
+                }
+                Ok(joined)
+            }
+            Build::Spilled {
+                partitions, budget, ..
+            } => {
+                // The probe side is held in memory already, so only the indices of its rows are partitioned.
+                let probes = join.key_rows(join.left_key, left)?;
+                let mut joined = vec![];
+                self.probe_partitions(partitions, 0, probes, left, *budget, &mut joined)?;
+
+                joined.sort_by_key(|(index, _)| *index);
+                Ok(joined.into_iter().map(|(_, row)| row).collect())
+            }
+        }
+    }
+
+    /// Join the left rows with the given indices and keys with the partitions, of the given level, that their keys are
+    /// assigned to, loading one partition at a time. The rows of a partition are grouped in a hash table in chunks
+    /// that fit the budget, such that a partition that could not be partitioned to fit the budget is joined one chunk
+    /// at a time. The joined rows are paired with the indices of the left rows they originate from.
+    fn probe_partitions<'l>(
+        &self,
+        partitions: &[Partition],
+        level: usize,
+        probes: Vec<(usize, JoinKey<'l>)>,
+        left: &'l Dataframe,
+        budget: usize,
+        joined: &mut Vec<(usize, Row)>,
+    ) -> RustyPipesResult<()> {
+        let join = &self.join;
+        let mut assigned = vec![vec![]; partitions.len()];
+        for (index, key) in probes {
+            assigned[grace_partition(&key, level)].push((index, key));
+        }
+
+        for (partition, probes) in partitions.iter().zip(assigned) {
+            if probes.is_empty() {
+                continue;
+            }
+            join.cancellation.check()?;
+            let file = match partition {
+                Partition::Split(partitions) => {
+                    self.probe_partitions(partitions, level + 1, probes, left, budget, joined)?;
+                    continue;
+                }
+                Partition::Rows { file, .. } => file,
+            };
+            let mut right_rows = file.read()?.peekable();
+            while right_rows.peek().is_some() {
+                let mut chunk = vec![];
+                let mut size = 0;
+                while size <= budget {
+                    let Some(row) = right_rows.next().transpose()? else {
+                        break;
+                    };
+                    size += estimated_size(&row);
+                    chunk.push(row);
+                }
+                let right_rows_by_key = join.group_rows(join.right_key, &chunk)?;
+                for (index, key) in &probes {
+                    if let Some(matching_rows) = right_rows_by_key.get(key) {
+                        joined.extend(matching_rows.iter().map(|matching_row| {
+                            (*index, join_rows(&left[*index], matching_row))
+                        }));
+                    }
+                }
             }
         }
+        Ok(())
+    }
+}
 
-        Ok(vec![joined])
+impl Transformation for JoinTable<'_, '_> {
+    fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
+        Ok(vec![self.probe(dfs[0])?])
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec)]
 mod test {
     use super::*;
 
@@ -131,7 +802,7 @@ mod test {
             ],
         ];
 
-        let op = InnerJoin::new("id = id").unwrap();
+        let op = InnerJoin::new("id = id", &Context::default()).unwrap();
 
         let df_refs = dfs.iter().collect();
 
@@ -152,7 +823,7 @@ mod test {
             ])],
         ];
 
-        let op = InnerJoin::new("non_existing = non_existing").unwrap();
+        let op = InnerJoin::new("non_existing = non_existing", &Context::default()).unwrap();
         let df_refs = dfs.iter().collect();
 
         let result = op.transform(&df_refs);
@@ -184,7 +855,7 @@ mod test {
             ],
         ];
 
-        let op = InnerJoin::new("id = id").unwrap();
+        let op = InnerJoin::new("id = id", &Context::default()).unwrap();
 
         let df_refs = dfs.iter().collect();
 
@@ -225,7 +896,7 @@ mod test {
             ],
         ];
 
-        let op = InnerJoin::new("id = id").unwrap();
+        let op = InnerJoin::new("id = id", &Context::default()).unwrap();
 
         let df_refs = dfs.iter().collect();
 
@@ -266,7 +937,7 @@ mod test {
             ])],
         ];
 
-        let op = InnerJoin::new("id = id").unwrap();
+        let op = InnerJoin::new("id = id", &Context::default()).unwrap();
 
         let df_refs = dfs.iter().collect();
 
@@ -301,7 +972,7 @@ mod test {
             ])],
         ];
 
-        let op = InnerJoin::new("id = id").unwrap();
+        let op = InnerJoin::new("id = id", &Context::default()).unwrap();
 
         let df_refs = dfs.iter().collect();
 
@@ -316,42 +987,474 @@ mod test {
         )
     }
 
+    /// The number of rows yielded by joining a row with the given left key to a row with the given right key.
+    fn count_matches(
+        op: InnerJoin,
+        left: ColumnValue,
+        right: ColumnValue,
+    ) -> RustyPipesResult<usize> {
+        let left = vec![HashMap::from([(String::from("l"), left)])];
+        let right = vec![HashMap::from([(String::from("r"), right)])];
+        Ok(op.transform(&vec![&left, &right])?[0].len())
+    }
+
     #[test]
-    fn join_on_float_errors() {
-        let dfs = vec![
-            vec![HashMap::from([(
-                String::from("id"),
-                ColumnValue::Decimal(1.0),
-            )])],
-            vec![HashMap::from([(
-                String::from("id"),
+    fn joins_on_decimals() {
+        let join = || InnerJoin::new("l = r", &Context::default()).unwrap();
+
+        assert_eq!(
+            count_matches(join(), ColumnValue::Decimal(1.5), ColumnValue::Decimal(1.5)).unwrap(),
+            1
+        );
+        assert_eq!(
+            count_matches(join(), ColumnValue::Decimal(1.0), ColumnValue::Integer(1)).unwrap(),
+            1
+        );
+        assert_eq!(
+            count_matches(
+                join(),
+                ColumnValue::Decimal(-0.0),
+                ColumnValue::Decimal(0.0)
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_matches(
+                join(),
+                ColumnValue::Decimal(f64::NAN),
+                ColumnValue::Decimal(f64::NAN)
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            count_matches(
+                join(),
+                ColumnValue::Integer(1),
+                ColumnValue::String(String::from("1"))
+            )
+            .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn coerces_keys_according_to_rule() {
+        let join = |coercion| {
+            InnerJoin::new("l = r", &Context::default())
+                .unwrap()
+                .with_key_coercion(coercion)
+        };
+        let string = |s: &str| ColumnValue::String(String::from(s));
+
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Strict),
                 ColumnValue::Decimal(1.0),
-            )])],
-        ];
+                ColumnValue::Integer(1)
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Numeric),
+                ColumnValue::Integer(1),
+                string("1")
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Text),
+                ColumnValue::Integer(1),
+                string("1")
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Text),
+                ColumnValue::Decimal(1.5),
+                string("1.5")
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Text),
+                ColumnValue::Integer(1),
+                string("01")
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Date),
+                string("2024-02-29"),
+                string("2024-02-29T08:30:00Z")
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(
+            count_matches(
+                join(KeyCoercion::Date),
+                string("2024-02-29"),
+                string("2024-03-01")
+            )
+            .unwrap(),
+            0
+        );
+        assert!(count_matches(join(KeyCoercion::Date), string("2023-02-29"), string("2023-03-01"))
+            .is_err_and(|err| matches!(err, RustyPipesError::TransformationError(message) if message.contains("as a date"))));
+        assert!(count_matches(join(KeyCoercion::Date), string("2020é1-01"), string("2020-01-01"))
+            .is_err_and(|err| matches!(err, RustyPipesError::TransformationError(message) if message.contains("as a date"))));
+    }
 
-        let op = InnerJoin::new("id = id").unwrap();
+    #[test]
+    fn parses_dates_as_days_since_epoch() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(11_017));
+        assert_eq!(parse_date("1969-12-31 23:59"), Some(-1));
+        assert_eq!(parse_date("2024-13-01"), None);
+        assert_eq!(parse_date("2024-01-01Z"), None);
+        assert_eq!(parse_date("2024-1-1"), None);
+        assert_eq!(parse_date("2020é1-01"), None);
+    }
 
-        let df_refs = dfs.iter().collect();
+    #[test]
+    fn matches_null_keys_when_configured() {
+        for strategy in [JoinStrategy::Hash, JoinStrategy::SortMerge] {
+            let join = |match_nulls| {
+                InnerJoin::new("l = r", &Context::default())
+                    .unwrap()
+                    .with_strategy(strategy)
+                    .with_matching_nulls(match_nulls)
+            };
 
-        let result = op.transform(&df_refs);
+            assert_eq!(
+                count_matches(join(false), ColumnValue::None, ColumnValue::None).unwrap(),
+                0
+            );
+            assert_eq!(
+                count_matches(join(true), ColumnValue::None, ColumnValue::None).unwrap(),
+                1
+            );
+            assert_eq!(
+                count_matches(join(true), ColumnValue::None, ColumnValue::Integer(0)).unwrap(),
+                0
+            );
+        }
+    }
 
-        assert!(result.is_err_and(|err| {
-            match err {
-                RustyPipesError::TransformationError(message) => {
-                    message.contains("Only ints or strings can be used as identifiers")
-                }
-                _ => false,
-            }
-        }))
+    #[test]
+    fn never_matches_nan_keys() {
+        for coercion in [KeyCoercion::Strict, KeyCoercion::Numeric, KeyCoercion::Text] {
+            let join = InnerJoin::new("l = r", &Context::default())
+                .unwrap()
+                .with_key_coercion(coercion)
+                .with_matching_nulls(true);
+
+            assert_eq!(
+                count_matches(
+                    join.clone(),
+                    ColumnValue::Decimal(f64::NAN),
+                    ColumnValue::Decimal(f64::NAN)
+                )
+                .unwrap(),
+                0
+            );
+            assert_eq!(
+                count_matches(join, ColumnValue::Decimal(f64::NAN), ColumnValue::None).unwrap(),
+                0
+            );
+        }
     }
 
     #[test]
     fn unable_to_parse_clause() {
-        let op = InnerJoin::new("id > 3");
+        let op = InnerJoin::new("id > 3", &Context::default());
         assert!(op.is_err_and(|err| match err {
             RustyPipesError::TransformationError(message) =>
                 message.contains("Unable to parse join clause"),
             _ => false,
         }));
     }
+
+    #[test]
+    fn joins_large_data_frames_in_parallel() {
+        let dfs = vec![
+            (0..6_000)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ColumnValue::Integer(i % 1_000)),
+                        (String::from("foo"), ColumnValue::Integer(i)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+            (0..5_000)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ColumnValue::Integer(i % 2_500)),
+                        (String::from("bar"), ColumnValue::Integer(i)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+        ];
+        let df_refs = dfs.iter().collect();
+        // Left row i matches right rows i % 1000 and i % 1000 + 2500, in that order.
+        let expected = dfs[0]
+            .iter()
+            .enumerate()
+            .flat_map(|(i, left)| {
+                [i % 1_000, i % 1_000 + 2_500].map(|j| join_rows(left, &dfs[1][j]))
+            })
+            .collect::<Dataframe>();
+        let sort_key = |row: &Row| match (&row["foo"], &row["bar"]) {
+            (ColumnValue::Integer(foo), ColumnValue::Integer(bar)) => (*foo, *bar),
+            _ => panic!("expected integers"),
+        };
+
+        let ordered = InnerJoin::new("id = id", &Context::default())
+            .unwrap()
+            .with_preserve_order(true)
+            .transform(&df_refs)
+            .unwrap();
+        let mut unordered = InnerJoin::new("id = id", &Context::default())
+            .unwrap()
+            .transform(&df_refs)
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(expected.len(), 12_000);
+        assert_eq!(ordered[0], expected);
+        unordered.sort_by_key(sort_key);
+        assert_eq!(unordered, expected);
+    }
+
+    #[test]
+    fn sort_merge_join_yields_rows_of_hash_join() {
+        let ids = [
+            ColumnValue::Integer(10),
+            ColumnValue::String(String::from("b")),
+            ColumnValue::Integer(2),
+            ColumnValue::String(String::from("02")),
+            ColumnValue::String(String::from("10")),
+            ColumnValue::Integer(-1),
+        ];
+        let dfs = vec![
+            (0..30)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ids[i % ids.len()].clone()),
+                        (String::from("foo"), ColumnValue::Integer(i as i64)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+            (0..20)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ids[(i * 7) % ids.len()].clone()),
+                        (String::from("bar"), ColumnValue::Integer(i as i64)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+        ];
+        let df_refs = dfs.iter().collect();
+
+        let hash = InnerJoin::new("id = id", &Context::default())
+            .unwrap()
+            .transform(&df_refs)
+            .unwrap();
+        let sort_merge = InnerJoin::new("id = id", &Context::default())
+            .unwrap()
+            .with_strategy(JoinStrategy::SortMerge)
+            .with_preserve_order(true)
+            .transform(&df_refs)
+            .unwrap();
+
+        assert!(!hash[0].is_empty());
+        assert_eq!(sort_merge, hash);
+    }
+
+    #[test]
+    fn sort_merge_join_keeps_order_of_sorted_inputs() {
+        let dfs = vec![
+            (0..10)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ColumnValue::Integer(i / 2)),
+                        (String::from("foo"), ColumnValue::Integer(i)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+            (0..10)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ColumnValue::Integer(i / 3)),
+                        (String::from("bar"), ColumnValue::Integer(i)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+        ];
+        let df_refs = dfs.iter().collect();
+
+        let hash = InnerJoin::new("id = id", &Context::default())
+            .unwrap()
+            .transform(&df_refs)
+            .unwrap();
+        let sort_merge = InnerJoin::new("id = id", &Context::default())
+            .unwrap()
+            .with_strategy(JoinStrategy::SortMerge)
+            .transform(&df_refs)
+            .unwrap();
+
+        assert_eq!(sort_merge, hash);
+    }
+
+    #[test]
+    fn builds_on_smaller_left_data_frame_in_order() {
+        let dfs = vec![
+            (0..20)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("id"), ColumnValue::Integer(i % 5)),
+                        (String::from("foo"), ColumnValue::Integer(i)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+            (0..64)
+                .map(|i| {
+                    HashMap::from([
+                        (String::from("other_id"), ColumnValue::Integer(i % 8)),
+                        (String::from("bar"), ColumnValue::Integer(i)),
+                    ])
+                })
+                .collect::<Dataframe>(),
+        ];
+        let df_refs = dfs.iter().collect();
+        let op = InnerJoin::new("id = other_id", &Context::default()).unwrap();
+
+        let result = op.transform(&df_refs).unwrap();
+
+        assert_eq!(result[0].len(), 20 * 8);
+        assert_eq!(
+            result[0],
+            op.build(&dfs[1]).unwrap().probe(&dfs[0]).unwrap()
+        );
+    }
+
+    /// The sizes of the partitions of a spilled build side that are loaded as a whole, and the deepest level of them.
+    fn loaded_partitions(partitions: &[Partition], level: usize, sizes: &mut Vec<usize>) -> usize {
+        partitions
+            .iter()
+            .map(|partition| match partition {
+                Partition::Rows { size, .. } => {
+                    sizes.push(*size);
+                    level
+                }
+                Partition::Split(partitions) => loaded_partitions(partitions, level + 1, sizes),
+            })
+            .max()
+            .unwrap_or(level)
+    }
+
+    #[test]
+    fn spills_build_batches_exceeding_memory_budget() {
+        let left = (0..200)
+            .map(|i| {
+                HashMap::from([
+                    (String::from("id"), ColumnValue::Integer(i % 50)),
+                    (String::from("foo"), ColumnValue::Integer(i)),
+                ])
+            })
+            .collect::<Dataframe>();
+        let right = (0..1_000)
+            .map(|i| {
+                HashMap::from([
+                    (String::from("other_id"), ColumnValue::Integer(i % 60)),
+                    (
+                        String::from("bar"),
+                        ColumnValue::String(format!("bar{}", i)),
+                    ),
+                ])
+            })
+            .collect::<Dataframe>();
+        let temp = tempfile::tempdir().unwrap();
+        let budget = MemoryBudget::new(5_000).with_directory(temp.path());
+        let op = InnerJoin::new("id = other_id", &Context::default()).unwrap();
+        let batches = right.chunks(7).map(|batch| Ok(batch.to_vec()));
+
+        let BatchedBuild::Spilled(table) = op.build_batches(batches, &budget).unwrap() else {
+            panic!("expected the build side to spill");
+        };
+        let joined = table.probe(&left).unwrap();
+
+        let Build::Spilled { partitions, .. } = &table.build else {
+            panic!("expected the build side to spill");
+        };
+        let mut sizes = vec![];
+        assert!(loaded_partitions(partitions, 0, &mut sizes) > 0);
+        assert!(sizes.iter().all(|size| *size <= budget.bytes()));
+        assert_eq!(table.build_rows(), 1_000);
+        assert_eq!(joined.len(), 4 * (40 * 17 + 10 * 16));
+        assert_eq!(joined, op.build(&right).unwrap().probe(&left).unwrap());
+        drop(table);
+        assert_eq!(std::fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn joins_skewed_build_batches_in_chunks() {
+        let left = (0..10)
+            .map(|i| HashMap::from([(String::from("id"), ColumnValue::Integer(i % 3))]))
+            .collect::<Dataframe>();
+        // Nearly all rows have the same key, so their partition cannot be partitioned to fit the budget.
+        let right = (0..500)
+            .map(|i| {
+                HashMap::from([
+                    (
+                        String::from("other_id"),
+                        ColumnValue::Integer(if i % 100 == 0 { 2 } else { 1 }),
+                    ),
+                    (String::from("bar"), ColumnValue::Integer(i)),
+                ])
+            })
+            .collect::<Dataframe>();
+        let budget = MemoryBudget::new(1_000);
+        let op = InnerJoin::new("id = other_id", &Context::default()).unwrap();
+        let batches = right.chunks(50).map(|batch| Ok(batch.to_vec()));
+
+        let BatchedBuild::Spilled(table) = op.build_batches(batches, &budget).unwrap() else {
+            panic!("expected the build side to spill");
+        };
+        let joined = table.probe(&left).unwrap();
+
+        let Build::Spilled { partitions, .. } = &table.build else {
+            panic!("expected the build side to spill");
+        };
+        let mut sizes = vec![];
+        loaded_partitions(partitions, 0, &mut sizes);
+        assert!(sizes.iter().any(|size| *size > budget.bytes()));
+        assert_eq!(joined.len(), 3 * 495 + 3 * 5);
+        assert_eq!(joined, op.build(&right).unwrap().probe(&left).unwrap());
+    }
+
+    #[test]
+    fn keeps_build_batches_within_memory_budget_in_memory() {
+        let right = (0..10)
+            .map(|i| HashMap::from([(String::from("other_id"), ColumnValue::Integer(i))]))
+            .collect::<Dataframe>();
+        let op = InnerJoin::new("id = other_id", &Context::default()).unwrap();
+        let batches = right.chunks(3).map(|batch| Ok(batch.to_vec()));
+
+        let build = op.build_batches(batches, &MemoryBudget::new(1_000_000));
+
+        assert!(matches!(build, Ok(BatchedBuild::Rows(rows)) if rows == right));
+    }
 }
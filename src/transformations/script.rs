@@ -0,0 +1,385 @@
+use rhai::{Array, Dynamic, EvalAltResult, Map, Scope, AST};
+
+use crate::core::cancellation::CancellationToken;
+use crate::core::context::Context;
+use crate::core::dataframe::{ColumnValue, Dataframe, Row};
+use crate::core::definitions::{ScriptLanguage, ScriptMode};
+use crate::core::error::RustyPipesError;
+use crate::core::result::RustyPipesResult;
+use crate::core::transformation::Transformation;
+
+/// The number of operations that a script performs between checks of the cancellation token.
+const CANCELLATION_INTERVAL: u64 = 1024;
+
+fn script_err(e: Box<EvalAltResult>) -> RustyPipesError {
+    RustyPipesError::TransformationError(format!("Script error: {}", e))
+}
+
+fn to_dynamic(value: &ColumnValue) -> Dynamic {
+    match value {
+        ColumnValue::Integer(i) => Dynamic::from_int(*i),
+        ColumnValue::Decimal(d) => Dynamic::from_float(*d),
+        ColumnValue::String(s) => Dynamic::from(s.clone()),
+        ColumnValue::None => Dynamic::UNIT,
+    }
+}
+
+fn from_dynamic(value: Dynamic) -> RustyPipesResult<ColumnValue> {
+    if value.is_unit() {
+        Ok(ColumnValue::None)
+    } else if value.is_int() {
+        Ok(ColumnValue::Integer(value.as_int().unwrap()))
+    } else if value.is_float() {
+        Ok(ColumnValue::Decimal(value.as_float().unwrap()))
+    } else if value.is_string() {
+        Ok(ColumnValue::String(value.into_string().unwrap()))
+    } else {
+        Err(RustyPipesError::TransformationError(format!(
+            "Script produced a value of unsupported type {}",
+            value.type_name()
+        )))
+    }
+}
+
+fn row_to_map(row: &Row) -> Map {
+    row.iter()
+        .map(|(key, value)| (key.into(), to_dynamic(value)))
+        .collect()
+}
+
+fn map_to_row(map: Map) -> RustyPipesResult<Row> {
+    map.into_iter()
+        .map(|(key, value)| Ok((key.to_string(), from_dynamic(value)?)))
+        .collect()
+}
+
+fn expect_map(value: Dynamic) -> RustyPipesResult<Map> {
+    let type_name = value.type_name();
+    value.try_cast::<Map>().ok_or_else(|| {
+        RustyPipesError::TransformationError(format!(
+            "Script must produce an object map for each row, got {}",
+            type_name
+        ))
+    })
+}
+
+/// Run a script written in Rhai (https://rhai.rs) on a Dataframe. Depending on the mode, the script is run for each
+/// row, or once for the whole dataframe:
+/// - Map: the row is available as `row`. The script either modifies `row` or evaluates to a new object map.
+/// - Filter: the row is available as `row`. Only those rows for which the script evaluates to true are retained.
+/// - Dataframe: the rows are available as `rows`, an array of object maps. The script either modifies `rows` or
+///   evaluates to a new array of object maps.
+///
+/// The parameters of the context are available as `params`. Scripts are sandboxed: they have no access to the file
+/// system or network, and each run is limited in the number of operations it may perform. A script is terminated once
+/// the run is cancelled.
+///
+/// This operation has an arity of one: it requires a single dataframe to be provided as its input.
+pub struct Script {
+    engine: rhai::Engine,
+    ast: AST,
+    mode: ScriptMode,
+    params: Map,
+    cancellation: CancellationToken,
+}
+
+impl Script {
+    /// Construct a new Script by compiling the given code. Each run of the script may perform at most the given
+    /// number of operations.
+    pub fn new(
+        language: ScriptLanguage,
+        code: &str,
+        mode: ScriptMode,
+        max_operations: u64,
+        context: &Context,
+    ) -> RustyPipesResult<Self> {
+        let ScriptLanguage::Rhai = language;
+        let mut engine = rhai::Engine::new();
+        engine
+            .set_max_operations(max_operations)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1 << 20)
+            .set_max_array_size(1 << 24)
+            .set_max_map_size(1 << 16);
+        let cancellation = context.cancellation().clone();
+        let token = cancellation.clone();
+        engine.on_progress(move |operations| {
+            (operations % CANCELLATION_INTERVAL == 0 && token.check().is_err())
+                .then_some(Dynamic::UNIT)
+        });
+        let ast = engine.compile(code).map_err(|e| {
+            RustyPipesError::TransformationError(format!("Unable to compile script: {}", e))
+        })?;
+        let params = context
+            .parameters()
+            .map(|(key, value)| (key.into(), Dynamic::from(value.to_owned())))
+            .collect();
+
+        Ok(Script {
+            engine,
+            ast,
+            mode,
+            params,
+            cancellation,
+        })
+    }
+
+    /// The error of a failed run of the script, which is that of the cancellation token if the script was terminated
+    /// because the run was cancelled.
+    fn eval_err(&self, e: Box<EvalAltResult>) -> RustyPipesError {
+        match self.cancellation.check() {
+            Err(error) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => error,
+            _ => script_err(e),
+        }
+    }
+
+    fn scope(&self) -> Scope<'_> {
+        let mut scope = Scope::new();
+        scope.push_constant("params", self.params.clone());
+        scope
+    }
+
+    fn map_rows(&self, df: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let mut scope = self.scope();
+        let mut mapped = vec![];
+        for row in df {
+            scope.push("row", row_to_map(row));
+            let result: Dynamic = self
+                .engine
+                .eval_ast_with_scope(&mut scope, &self.ast)
+                .map_err(|e| self.eval_err(e))?;
+            let map = if result.is_map() {
+                expect_map(result)?
+            } else {
+                expect_map(scope.get_value::<Dynamic>("row").unwrap_or_default())?
+            };
+            scope.rewind(1);
+            mapped.push(map_to_row(map)?);
+        }
+        Ok(mapped)
+    }
+
+    fn filter_rows(&self, df: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let mut scope = self.scope();
+        let mut filtered = vec![];
+        for row in df {
+            scope.push("row", row_to_map(row));
+            let result: Dynamic = self
+                .engine
+                .eval_ast_with_scope(&mut scope, &self.ast)
+                .map_err(|e| self.eval_err(e))?;
+            scope.rewind(1);
+            let keep = result.as_bool().map_err(|type_name| {
+                RustyPipesError::TransformationError(format!(
+                    "Script must evaluate to a boolean when filtering, got {}",
+                    type_name
+                ))
+            })?;
+            if keep {
+                filtered.push(row.clone());
+            }
+        }
+        Ok(filtered)
+    }
+
+    fn transform_dataframe(&self, df: &Dataframe) -> RustyPipesResult<Dataframe> {
+        let mut scope = self.scope();
+        let rows: Array = df.iter().map(|row| row_to_map(row).into()).collect();
+        scope.push("rows", rows);
+        let result: Dynamic = self
+            .engine
+            .eval_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| self.eval_err(e))?;
+        let result = if result.is_array() {
+            result
+        } else {
+            scope.get_value::<Dynamic>("rows").unwrap_or_default()
+        };
+        let type_name = result.type_name();
+        let rows = result.try_cast::<Array>().ok_or_else(|| {
+            RustyPipesError::TransformationError(format!(
+                "Script must produce an array of object maps, got {}",
+                type_name
+            ))
+        })?;
+        rows.into_iter()
+            .map(|row| map_to_row(expect_map(row)?))
+            .collect()
+    }
+}
+
+impl Transformation for Script {
+    fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
+        let result = match self.mode {
+            ScriptMode::Map => self.map_rows(dfs[0])?,
+            ScriptMode::Filter => self.filter_rows(dfs[0])?,
+            ScriptMode::Dataframe => self.transform_dataframe(dfs[0])?,
+        };
+        Ok(vec![result])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn df() -> Vec<Dataframe> {
+        vec![vec![
+            HashMap::from([
+                (String::from("foo"), ColumnValue::Integer(1)),
+                (String::from("bar"), ColumnValue::Decimal(0.5)),
+            ]),
+            HashMap::from([
+                (String::from("foo"), ColumnValue::Integer(2)),
+                (String::from("bar"), ColumnValue::None),
+            ]),
+        ]]
+    }
+
+    fn script(code: &str, mode: ScriptMode) -> RustyPipesResult<Script> {
+        Script::new(
+            ScriptLanguage::Rhai,
+            code,
+            mode,
+            10_000,
+            &Context::new(HashMap::from([(String::from("suffix"), String::from("!"))])),
+        )
+    }
+
+    #[test]
+    fn maps_rows() {
+        let op = script(
+            r#"row.label = "foo" + row.foo + params.suffix; row.bar = ();"#,
+            ScriptMode::Map,
+        )
+        .unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert_eq!(
+            result.unwrap()[0][0],
+            HashMap::from([
+                (String::from("foo"), ColumnValue::Integer(1)),
+                (String::from("bar"), ColumnValue::None),
+                (
+                    String::from("label"),
+                    ColumnValue::String(String::from("foo1!"))
+                ),
+            ])
+        )
+    }
+
+    #[test]
+    fn maps_rows_to_new_maps() {
+        let op = script("#{ doubled: row.foo * 2 }", ScriptMode::Map).unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert_eq!(
+            result.unwrap()[0],
+            vec![
+                HashMap::from([(String::from("doubled"), ColumnValue::Integer(2))]),
+                HashMap::from([(String::from("doubled"), ColumnValue::Integer(4))]),
+            ]
+        )
+    }
+
+    #[test]
+    fn filters_rows() {
+        let op = script("row.bar != ()", ScriptMode::Filter).unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert_eq!(result.unwrap()[0], vec![dfs[0][0].clone()])
+    }
+
+    #[test]
+    fn transforms_dataframe() {
+        let op = script(
+            "let total = 0; for row in rows { total += row.foo; } [#{ total: total }]",
+            ScriptMode::Dataframe,
+        )
+        .unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert_eq!(
+            result.unwrap()[0],
+            vec![HashMap::from([(
+                String::from("total"),
+                ColumnValue::Integer(3)
+            )])]
+        )
+    }
+
+    #[test]
+    fn reports_compile_errors_with_line_numbers() {
+        let op = script("let x = 1;\nlet = 2;", ScriptMode::Map);
+
+        assert!(op.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) =>
+                message.contains("Unable to compile script") && message.contains("line 2"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn reports_runtime_errors_with_line_numbers() {
+        let op = script("let x = 1;\nrow.foo + \"a\" * 2", ScriptMode::Filter).unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) =>
+                message.contains("Script error") && message.contains("line 2"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn limits_operations() {
+        let op = script("loop {}", ScriptMode::Filter).unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) =>
+                message.contains("Too many operations"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn terminates_when_timed_out() {
+        let cancellation = CancellationToken::new()
+            .with_timeout(std::time::Duration::ZERO, "Transformation looped");
+        let op = Script::new(
+            ScriptLanguage::Rhai,
+            "loop {}",
+            ScriptMode::Filter,
+            u64::MAX,
+            &Context::default().with_cancellation(cancellation),
+        )
+        .unwrap();
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert!(matches!(result, Err(RustyPipesError::TimedOut(_))));
+    }
+}
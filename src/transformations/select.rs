@@ -0,0 +1,64 @@
+use crate::core::dataframe::{Dataframe, Row};
+use crate::core::result::RustyPipesResult;
+use crate::core::transformation::Transformation;
+
+/// Select a subset of the columns of a Dataframe. Only the given columns are retained in each row; columns that a row
+/// lacks remain absent. This operation has an arity of one: it requires a single dataframe to be provided as its input.
+pub struct Select<'a> {
+    columns: &'a [String],
+}
+
+impl<'a> Select<'a> {
+    /// Construct a new Select that retains the given columns.
+    pub fn new(columns: &'a [String]) -> Self {
+        Select { columns }
+    }
+}
+
+impl Transformation for Select<'_> {
+    fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
+        let selected = dfs[0]
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .filter_map(|column| {
+                        row.get(column).map(|value| (column.clone(), value.clone()))
+                    })
+                    .collect::<Row>()
+            })
+            .collect();
+        Ok(vec![selected])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::core::dataframe::ColumnValue;
+    use std::collections::HashMap;
+
+    #[test]
+    fn selects_columns() {
+        let dfs = [vec![
+            HashMap::from([
+                (String::from("foo"), ColumnValue::Integer(0)),
+                (String::from("bar"), ColumnValue::Integer(1)),
+            ]),
+            HashMap::from([(String::from("bar"), ColumnValue::Integer(2))]),
+        ]];
+        let columns = vec![String::from("foo")];
+        let op = Select::new(&columns);
+        let df_refs = dfs.iter().collect();
+
+        let result = op.transform(&df_refs);
+
+        assert_eq!(
+            result.unwrap()[0],
+            vec![
+                HashMap::from([(String::from("foo"), ColumnValue::Integer(0))]),
+                HashMap::new(),
+            ]
+        )
+    }
+}
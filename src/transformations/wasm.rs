@@ -0,0 +1,511 @@
+use std::path::Path;
+
+use serde_json::{json, Map, Number, Value};
+use wasmi::errors::{MemoryError, TableError};
+use wasmi::{
+    Config, Engine, Linker, Module, ResourceLimiter, Store, StoreLimits, StoreLimitsBuilder,
+    TrapCode, TypedFunc, TypedResumableCall, WasmParams, WasmResults,
+};
+use wasmi_core::LimiterError;
+
+use crate::core::cancellation::CancellationToken;
+use crate::core::context::Context;
+use crate::core::dataframe::{ColumnValue, Dataframe, Row};
+use crate::core::error::RustyPipesError;
+use crate::core::result::RustyPipesResult;
+use crate::core::transformation::Transformation;
+
+/// The amount of fuel that a module consumes between checks of the cancellation token.
+const FUEL_SLICE: u64 = 1_000_000;
+
+fn plugin_err(e: impl std::fmt::Display) -> RustyPipesError {
+    RustyPipesError::TransformationError(format!("WASM plugin error: {}", e))
+}
+
+/// The limits of a run of a module. Growing the memory beyond its limit traps, such that the run fails. Growing the
+/// memory while the store lacks the fuel to do so does not trap, so that the call is resumed once more fuel is added.
+struct PluginLimits(StoreLimits);
+
+impl ResourceLimiter for PluginLimits {
+    fn memory_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, LimiterError> {
+        self.0.memory_growing(current, desired, maximum)
+    }
+
+    fn table_growing(
+        &mut self,
+        current: usize,
+        desired: usize,
+        maximum: Option<usize>,
+    ) -> Result<bool, LimiterError> {
+        self.0.table_growing(current, desired, maximum)
+    }
+
+    fn memory_grow_failed(&mut self, error: &MemoryError) -> Result<(), LimiterError> {
+        match error {
+            MemoryError::OutOfFuel { .. } => Ok(()),
+            _ => self.0.memory_grow_failed(error),
+        }
+    }
+
+    fn table_grow_failed(&mut self, error: &TableError) -> Result<(), LimiterError> {
+        self.0.table_grow_failed(error)
+    }
+
+    fn instances(&self) -> usize {
+        self.0.instances()
+    }
+
+    fn tables(&self) -> usize {
+        self.0.tables()
+    }
+
+    fn memories(&self) -> usize {
+        self.0.memories()
+    }
+}
+
+fn to_json(value: &ColumnValue) -> RustyPipesResult<Value> {
+    Ok(match value {
+        ColumnValue::Integer(i) => Value::from(*i),
+        ColumnValue::Decimal(d) => Value::Number(Number::from_f64(*d).ok_or_else(|| {
+            RustyPipesError::TransformationError(format!(
+                "Unable to pass the decimal {} to a WASM plugin; only finite decimals can be passed",
+                d
+            ))
+        })?),
+        ColumnValue::String(s) => Value::from(s.as_str()),
+        ColumnValue::None => Value::Null,
+    })
+}
+
+fn from_json(value: Value) -> RustyPipesResult<ColumnValue> {
+    match value {
+        Value::Null => Ok(ColumnValue::None),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Ok(ColumnValue::Integer(i)),
+            None => Ok(ColumnValue::Decimal(n.as_f64().unwrap_or(f64::NAN))),
+        },
+        Value::String(s) => Ok(ColumnValue::String(s)),
+        other => Err(plugin_err(format!("unsupported value {}", other))),
+    }
+}
+
+fn decode_dataframe(value: Value) -> RustyPipesResult<Dataframe> {
+    let Value::Array(rows) = value else {
+        return Err(plugin_err("each dataframe must be an array of rows"));
+    };
+    rows.into_iter()
+        .map(|row| match row {
+            Value::Object(columns) => columns
+                .into_iter()
+                .map(|(key, value)| Ok((key, from_json(value)?)))
+                .collect::<RustyPipesResult<Row>>(),
+            _ => Err(plugin_err("each row must be an object")),
+        })
+        .collect()
+}
+
+fn decode_output(output: &[u8]) -> RustyPipesResult<Vec<Dataframe>> {
+    let output: Value = serde_json::from_slice(output).map_err(plugin_err)?;
+    if let Some(error) = output.get("error") {
+        return Err(plugin_err(error.as_str().unwrap_or("unknown error")));
+    }
+    match output {
+        Value::Object(mut fields) => match fields.remove("dataframes") {
+            Some(Value::Array(dfs)) => dfs.into_iter().map(decode_dataframe).collect(),
+            _ => Err(plugin_err("output must contain an array of dataframes")),
+        },
+        _ => Err(plugin_err("output must be an object")),
+    }
+}
+
+/// Run a transformation implemented by a WebAssembly module. The module is run in a sandbox: it cannot import any
+/// functions from the host, its execution is bounded by an amount of fuel (roughly, the number of instructions it may
+/// execute), and its linear memory is bounded in size. Each run of the transformation uses a fresh instance of the
+/// module. Running stops once the run is cancelled; this is checked each time the module has consumed a slice of its
+/// fuel.
+///
+/// Dataframes are exchanged with the module as UTF-8 encoded JSON buffers in its linear memory. The module must export:
+/// - `memory`: its linear memory.
+/// - `alloc(len: i32) -> i32`: allocate a buffer of len bytes, returning a pointer to it.
+/// - `transform(ptr: i32, len: i32) -> i64`: transform the input buffer at ptr, returning a pointer to the output
+///   buffer in the upper 32 bits and its length in the lower 32 bits.
+///
+/// The input buffer holds an object with the input dataframes, the configuration of the operation and the parameters
+/// of the context: `{"dataframes": [[{"column": value, ...}, ...], ...], "config": ..., "parameters": {...}}`. The
+/// output buffer holds either `{"dataframes": [...]}` or `{"error": "message"}`. Values are integers, decimals,
+/// strings or null. JSON cannot represent NaN or infinite decimals, so passing these to the module is an error.
+///
+/// The arity of this operation is determined by the module.
+pub struct WasmPlugin {
+    engine: Engine,
+    module: Module,
+    config: Value,
+    parameters: Map<String, Value>,
+    fuel: u64,
+    max_memory_bytes: usize,
+    cancellation: CancellationToken,
+}
+
+impl WasmPlugin {
+    /// Construct a new WasmPlugin by compiling the module at the given path. Each run may consume at most the given
+    /// amount of fuel and grow the memory of the module to at most the given number of bytes.
+    pub fn new(
+        path: &Path,
+        config: &Value,
+        fuel: u64,
+        max_memory_bytes: usize,
+        context: &Context,
+    ) -> RustyPipesResult<Self> {
+        let wasm = std::fs::read(path).map_err(|e| {
+            RustyPipesError::TransformationError(format!(
+                "Unable to read WASM module {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Self::from_bytes(&wasm, config, fuel, max_memory_bytes, context)
+    }
+
+    /// Construct a new WasmPlugin from the bytes of a compiled module.
+    pub fn from_bytes(
+        wasm: &[u8],
+        config: &Value,
+        fuel: u64,
+        max_memory_bytes: usize,
+        context: &Context,
+    ) -> RustyPipesResult<Self> {
+        let mut engine_config = Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module = Module::new(&engine, wasm).map_err(|e| {
+            RustyPipesError::TransformationError(format!("Unable to compile WASM module: {}", e))
+        })?;
+        let parameters = context
+            .parameters()
+            .map(|(key, value)| (key.to_owned(), Value::from(value)))
+            .collect();
+
+        Ok(WasmPlugin {
+            engine,
+            module,
+            config: config.clone(),
+            parameters,
+            fuel,
+            max_memory_bytes,
+            cancellation: context.cancellation().clone(),
+        })
+    }
+
+    /// Call a function of the module, drawing the fuel that it consumes from the given remaining fuel of the run. The
+    /// fuel is added to the store one slice at a time, and the cancellation token is checked before each slice.
+    fn call<Params: WasmParams, Results: WasmResults>(
+        &self,
+        store: &mut Store<PluginLimits>,
+        func: &TypedFunc<Params, Results>,
+        params: Params,
+        fuel: &mut u64,
+    ) -> RustyPipesResult<Results> {
+        let mut call = func.call_resumable(&mut *store, params);
+        loop {
+            match call.map_err(|e| match e.as_trap_code() {
+                Some(TrapCode::GrowthOperationLimited) => plugin_err(format!(
+                    "module exceeded its memory limit of {} bytes",
+                    self.max_memory_bytes
+                )),
+                _ => plugin_err(e),
+            })? {
+                TypedResumableCall::Finished(results) => return Ok(results),
+                TypedResumableCall::HostTrap(trap) => return Err(plugin_err(trap.host_error())),
+                TypedResumableCall::OutOfFuel(invocation) => {
+                    self.cancellation.check()?;
+                    let left = store.get_fuel().map_err(plugin_err)?;
+                    let required = invocation.required_fuel().saturating_sub(left);
+                    if required > *fuel {
+                        return Err(plugin_err(TrapCode::OutOfFuel));
+                    }
+                    let slice = FUEL_SLICE.max(required).min(*fuel);
+                    *fuel -= slice;
+                    store.set_fuel(left + slice).map_err(plugin_err)?;
+                    call = invocation.resume(&mut *store);
+                }
+            }
+        }
+    }
+
+    fn run(&self, input: &[u8]) -> RustyPipesResult<Vec<u8>> {
+        self.cancellation.check()?;
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.max_memory_bytes)
+            .trap_on_grow_failure(true)
+            .build();
+        let mut store = Store::new(&self.engine, PluginLimits(limits));
+        store.limiter(|limits| limits);
+        // Starting the module is not resumable, so it consumes the first slice of fuel on its own.
+        let mut fuel = self.fuel;
+        let slice = fuel.min(FUEL_SLICE);
+        fuel -= slice;
+        store.set_fuel(slice).map_err(plugin_err)?;
+
+        let instance = Linker::new(&self.engine)
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(plugin_err)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| plugin_err("module does not export its memory"))?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(plugin_err)?;
+        let transform = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "transform")
+            .map_err(plugin_err)?;
+
+        let len = i32::try_from(input.len()).map_err(plugin_err)?;
+        let ptr = self.call(&mut store, &alloc, len, &mut fuel)?;
+        memory
+            .write(&mut store, ptr as u32 as usize, input)
+            .map_err(plugin_err)?;
+        let result = self.call(&mut store, &transform, (ptr, len), &mut fuel)? as u64;
+
+        let (out_ptr, out_len) = ((result >> 32) as usize, (result & 0xFFFF_FFFF) as usize);
+        memory
+            .data(&store)
+            .get(out_ptr..out_ptr + out_len)
+            .map(|output| output.to_vec())
+            .ok_or_else(|| plugin_err("output buffer is out of bounds"))
+    }
+}
+
+impl Transformation for WasmPlugin {
+    fn transform(&self, dfs: &Vec<&Dataframe>) -> RustyPipesResult<Vec<Dataframe>> {
+        let dataframes = dfs
+            .iter()
+            .map(|df| {
+                df.iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|(key, value)| Ok((key.clone(), to_json(value)?)))
+                            .collect::<RustyPipesResult<Map<_, _>>>()
+                    })
+                    .collect::<RustyPipesResult<Vec<_>>>()
+            })
+            .collect::<RustyPipesResult<Vec<_>>>()?;
+        let input = json!({
+            "dataframes": dataframes,
+            "config": self.config,
+            "parameters": self.parameters,
+        });
+
+        let output = self.run(input.to_string().as_bytes())?;
+        decode_output(&output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// A module that returns its input as its output, which is valid since the input contains the dataframes.
+    const IDENTITY: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (local.get $len)))))
+    "#;
+
+    /// A module that reports an error, which is stored in a data segment.
+    const FAILING: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "{\"error\": \"bad input\"}")
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "transform") (param i32 i32) (result i64) (i64.const 22)))
+    "#;
+
+    /// A module that never terminates.
+    const LOOPING: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "transform") (param i32 i32) (result i64)
+            (loop $forever (br $forever))
+            (i64.const 0)))
+    "#;
+
+    /// A module that grows its memory by a thousand pages of 64 KiB, after which it returns its input as its output.
+    const GROWING: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 0))
+          (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+            (drop (memory.grow (i32.const 1000)))
+            (i64.or
+              (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+              (i64.extend_i32_u (local.get $len)))))
+    "#;
+
+    fn plugin(module: &str) -> WasmPlugin {
+        plugin_with_fuel(module, 1_000_000, &Default::default())
+    }
+
+    fn plugin_with_fuel(module: &str, fuel: u64, context: &Context) -> WasmPlugin {
+        plugin_with_limits(module, fuel, 1 << 20, context)
+    }
+
+    fn plugin_with_limits(
+        module: &str,
+        fuel: u64,
+        max_memory_bytes: usize,
+        context: &Context,
+    ) -> WasmPlugin {
+        WasmPlugin::from_bytes(
+            &wat::parse_str(module).unwrap(),
+            &Value::Null,
+            fuel,
+            max_memory_bytes,
+            context,
+        )
+        .unwrap()
+    }
+
+    fn df() -> Vec<Dataframe> {
+        vec![vec![HashMap::from([
+            (String::from("id"), ColumnValue::Integer(1)),
+            (String::from("score"), ColumnValue::Decimal(0.5)),
+            (
+                String::from("name"),
+                ColumnValue::String(String::from("Jen")),
+            ),
+            (String::from("note"), ColumnValue::None),
+        ])]]
+    }
+
+    #[test]
+    fn exchanges_dataframes_with_module() {
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = plugin(IDENTITY).transform(&df_refs);
+
+        assert_eq!(result.unwrap(), dfs);
+    }
+
+    #[test]
+    fn reports_module_errors() {
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = plugin(FAILING).transform(&df_refs);
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) => message.contains("bad input"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn limits_fuel() {
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = plugin(LOOPING).transform(&df_refs);
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) => message.contains("fuel"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn draws_fuel_in_slices() {
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result =
+            plugin_with_fuel(LOOPING, 5 * FUEL_SLICE / 2, &Default::default()).transform(&df_refs);
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) => message.contains("fuel"),
+            _ => false,
+        }));
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+        let cancellation = CancellationToken::new();
+        let context = Context::default().with_cancellation(cancellation.clone());
+        let plugin = plugin_with_fuel(LOOPING, u64::MAX, &context);
+        std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            cancellation.cancel();
+        });
+
+        let result = plugin.transform(&df_refs);
+
+        assert!(matches!(result, Err(RustyPipesError::Cancelled)));
+    }
+
+    #[test]
+    fn limits_memory() {
+        let dfs = df();
+        let df_refs = dfs.iter().collect();
+
+        let result = plugin(GROWING).transform(&df_refs);
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) =>
+                message.contains("exceeded its memory limit of 1048576 bytes"),
+            _ => false,
+        }));
+        let result = plugin_with_limits(GROWING, 1_000_000_000, 1 << 30, &Default::default())
+            .transform(&df_refs);
+        assert_eq!(result.unwrap(), dfs);
+    }
+
+    #[test]
+    fn rejects_non_finite_decimals() {
+        for decimal in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let df = vec![HashMap::from([(
+                String::from("score"),
+                ColumnValue::Decimal(decimal),
+            )])];
+            let df_refs = vec![&df];
+
+            let result = plugin(IDENTITY).transform(&df_refs);
+
+            assert!(result.is_err_and(|err| match err {
+                RustyPipesError::TransformationError(message) =>
+                    message.contains("only finite decimals"),
+                _ => false,
+            }));
+        }
+    }
+
+    #[test]
+    fn invalid_module_errors() {
+        let result = WasmPlugin::from_bytes(
+            b"not a module",
+            &Value::Null,
+            1_000,
+            1 << 20,
+            &Default::default(),
+        );
+
+        assert!(result.is_err_and(|err| match err {
+            RustyPipesError::TransformationError(message) =>
+                message.contains("Unable to compile WASM module"),
+            _ => false,
+        }));
+    }
+}